@@ -1,29 +1,26 @@
 use crate::{prelude::*, slider};
-use std::collections::BTreeSet;
-
-fn format_day(day_name: Weekday, day: u32) -> String {
-    let day_name = t(match day_name {
-        Weekday::Mon => "Lundi",
-        Weekday::Tue => "Mardi",
-        Weekday::Wed => "Mercredi",
-        Weekday::Thu => "Jeudi",
-        Weekday::Fri => "Vendredi",
-        Weekday::Sat => "Samedi",
-        Weekday::Sun => "Dimanche",
-    });
-
-    format!("{} {}", day_name, day)
-}
+use chrono::Months;
 
 pub struct Agenda {
     selected_day: NaiveDate,
+    view: AgendaView,
     slider: Rc<RefCell<slider::SliderManager>>,
+    /// Incremented to open the date picker
+    calendar_requests: usize,
+    _shortcuts: Shortcuts,
 }
 
 pub enum AgendaMsg {
     Previous,
     Next,
+    /// Moves to the next day when the current one is over, without pointing the url to it.
+    SkipDay,
     Goto { day: u32, month: u32, year: i32 },
+    Today,
+    OpenCalendar,
+    SetView(AgendaView),
+    /// Shows a day in the day view, without making it the default view.
+    OpenDay(NaiveDate),
     Refresh,
     AppMsg(Box<AppMsg>),
 }
@@ -33,7 +30,10 @@ pub struct AgendaProps {
     pub app_link: AppLink,
     pub events: Rc<Vec<RawEvent>>,
     #[prop_or_default]
-    pub profile_src: Option<String>,
+    pub profile: Option<UserDesc>,
+    /// Shown in the header for the schedules of teachers and rooms
+    #[prop_or_default]
+    pub title: Option<String>,
     #[prop_or_default]
     pub selected_day: Option<NaiveDate>,
     pub user_info: Rc<Option<UserInfo>>,
@@ -41,16 +41,58 @@ pub struct AgendaProps {
     pub seen_comment_counts: Rc<CommentCounts>,
     pub friends: Rc<Option<FriendLists>>,
     pub colors: Rc<Colors>,
+    #[prop_or_default]
+    pub homeworks: Rc<Vec<HomeworkItem>>,
+    /// Incremented when the settings change in another tab, to pick up the new view, calendar and language.
+    #[prop_or_default]
+    pub settings_generation: usize,
 }
 
 impl PartialEq for AgendaProps {
     fn eq(&self, other: &Self) -> bool {
         self.events == other.events
+            && self.title == other.title
+            && self.selected_day == other.selected_day
             && self.user_info == other.user_info
             && self.comment_counts == other.comment_counts
             && self.seen_comment_counts == other.seen_comment_counts
             && self.friends == other.friends
             && self.colors == other.colors
+            && self.homeworks == other.homeworks
+            && self.settings_generation == other.settings_generation
+    }
+}
+
+impl Agenda {
+    fn select(&mut self, day: NaiveDate) {
+        self.selected_day = day;
+        self.slider
+            .borrow_mut()
+            .set_offset(-20 * (self.selected_day.num_days_from_ce() - 730000));
+    }
+
+    fn next_day(&mut self, ctx: &Context<Self>) {
+        let day_this_week = self.selected_day;
+        if self.selected_day.weekday() == Weekday::Sat {
+            self.select(self.selected_day + chrono::Duration::days(2));
+        } else if self.selected_day.weekday() == Weekday::Fri
+            && !has_event_on_day(&ctx.props().events, day_this_week, Weekday::Sat)
+        {
+            self.select(self.selected_day + chrono::Duration::days(3));
+        } else {
+            self.select(self.selected_day + chrono::Duration::days(1));
+        }
+    }
+
+    fn previous_day(&mut self, ctx: &Context<Self>) {
+        let day_prev_week = self.selected_day - chrono::Duration::days(7);
+        if self.selected_day.weekday() != Weekday::Mon {
+            self.select(self.selected_day - chrono::Duration::days(1));
+        } else if !has_event_on_day(&ctx.props().events, day_prev_week, Weekday::Sat) {
+            self.select(self.selected_day - chrono::Duration::days(3));
+        } else {
+            self.select(self.selected_day - chrono::Duration::days(2));
+        }
     }
 }
 
@@ -76,64 +118,100 @@ impl Component for Agenda {
                     let link2 = ctx.link().clone();
                     spawn_local(async move {
                         sleep(Duration::from_millis(500)).await;
-                        link2.send_message(AgendaMsg::Next);
+                        link2.send_message(AgendaMsg::SkipDay);
                     });
                 }
                 now.date_naive()
             }
         };
 
+        let link = ctx.link().clone();
         Self {
             selected_day,
+            view: SETTINGS.agenda_view(),
             slider: slider::SliderManager::init(
                 ctx.link().clone(),
                 -20 * (selected_day.num_days_from_ce() - 730000),
             ),
+            calendar_requests: 0,
+            _shortcuts: Shortcuts::register(move |key| {
+                match key {
+                    "ArrowLeft" => link.send_message(AgendaMsg::Previous),
+                    "ArrowRight" => link.send_message(AgendaMsg::Next),
+                    "t" => link.send_message(AgendaMsg::Today),
+                    "g" => link.send_message(AgendaMsg::OpenCalendar),
+                    "/" => link.send_message(AgendaMsg::AppMsg(Box::new(AppMsg::SetPage(Page::Search)))),
+                    "?" => link.send_message(AgendaMsg::AppMsg(Box::new(AppMsg::ShowShortcutHelp(true)))),
+                    _ => return false,
+                }
+                true
+            }),
         }
     }
 
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if let Some(selected_day) = ctx.props().selected_day {
+            if old_props.selected_day != Some(selected_day) && selected_day != self.selected_day {
+                self.select(selected_day);
+            }
+        }
+        if ctx.props().settings_generation != old_props.settings_generation {
+            self.view = SETTINGS.agenda_view();
+        }
+        true
+    }
+
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             AgendaMsg::Previous => {
-                let day_prev_week = self.selected_day - chrono::Duration::days(7);
-                if self.selected_day.weekday() != Weekday::Mon {
-                    self.selected_day -= chrono::Duration::days(1);
-                } else if self.selected_day.weekday() == Weekday::Mon
-                    && !has_event_on_day(&ctx.props().events, day_prev_week, Weekday::Sat)
-                {
-                    self.selected_day -= chrono::Duration::days(3);
-                } else {
-                    self.selected_day -= chrono::Duration::days(2);
+                match self.view {
+                    AgendaView::Week | AgendaView::Day => self.previous_day(ctx),
+                    AgendaView::Month => self.select(self.selected_day - Months::new(1)),
+                    AgendaView::List => self.select(self.selected_day - chrono::Duration::days(LIST_DAYS)),
                 }
-                self.slider
-                    .borrow_mut()
-                    .set_offset(-20 * (self.selected_day.num_days_from_ce() - 730000));
+                ctx.props().app_link.send_message(AppMsg::AgendaDayChanged(self.selected_day));
                 true
             }
             AgendaMsg::Next => {
-                let day_this_week = self.selected_day;
-                if self.selected_day.weekday() == Weekday::Sat {
-                    self.selected_day += chrono::Duration::days(2);
-                } else if self.selected_day.weekday() == Weekday::Fri
-                    && !has_event_on_day(&ctx.props().events, day_this_week, Weekday::Sat)
-                {
-                    self.selected_day += chrono::Duration::days(3);
-                } else {
-                    self.selected_day += chrono::Duration::days(1);
+                match self.view {
+                    AgendaView::Week | AgendaView::Day => self.next_day(ctx),
+                    AgendaView::Month => self.select(self.selected_day + Months::new(1)),
+                    AgendaView::List => self.select(self.selected_day + chrono::Duration::days(LIST_DAYS)),
                 }
-                self.slider
-                    .borrow_mut()
-                    .set_offset(-20 * (self.selected_day.num_days_from_ce() - 730000));
-
+                ctx.props().app_link.send_message(AppMsg::AgendaDayChanged(self.selected_day));
+                true
+            }
+            AgendaMsg::SkipDay => {
+                self.next_day(ctx);
                 true
             }
             AgendaMsg::Goto { day, month, year } => {
                 if let Some(new_selected_day) = NaiveDate::from_ymd_opt(year, month, day) {
-                    self.selected_day = new_selected_day;
+                    self.select(new_selected_day);
                 }
-                self.slider
-                    .borrow_mut()
-                    .set_offset(-20 * (self.selected_day.num_days_from_ce() - 730000));
+                ctx.props().app_link.send_message(AppMsg::AgendaDayChanged(self.selected_day));
+                true
+            }
+            AgendaMsg::Today => {
+                self.select(Local::now().with_timezone(&Paris).date_naive());
+                ctx.props().app_link.send_message(AppMsg::AgendaDayChanged(self.selected_day));
+                true
+            }
+            AgendaMsg::OpenCalendar => {
+                self.calendar_requests += 1;
+                true
+            }
+            AgendaMsg::SetView(view) => {
+                SETTINGS.set_agenda_view(view as usize);
+                self.view = view;
+                // The day container is rendered again when going back to the week
+                self.slider.borrow_mut().forget_day_container();
+                true
+            }
+            AgendaMsg::OpenDay(day) => {
+                self.view = AgendaView::Day;
+                self.select(day);
+                ctx.props().app_link.send_message(AppMsg::AgendaDayChanged(self.selected_day));
                 true
             }
             AgendaMsg::Refresh => {
@@ -163,172 +241,91 @@ impl Component for Agenda {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let opt_profile_src = ctx.props().profile_src.as_ref().cloned();
-        let profile_src_none = opt_profile_src.is_none();
+        let opt_profile_avatar = ctx.props().profile.as_ref().map(|profile| profile.avatar());
+        let opt_title = ctx.props().title.clone();
+        // Searching and homeworks are about the schedule of the user
+        let own_agenda = opt_profile_avatar.is_none() && opt_title.is_none();
         let screen_width = crate::slider::width();
         let mobile = screen_width <= 1000;
 
-        // Go on the first day of the week
-        let mut current_day = self.selected_day;
-        match mobile {
-            true => current_day -= chrono::Duration::days(2),
-            false => {
-                for _ in 0..self.selected_day.weekday().num_days_from_monday() {
-                    current_day -= chrono::Duration::days(1);
-                }
-            }
-        };
-
-        // Build each day and put events in them
         let mut days = Vec::new();
         let mut day_names = Vec::new();
-        for d in 0..6 {
-            let day_start = Paris
-                .from_local_datetime(&current_day.and_hms_opt(0, 0, 0).unwrap())
-                .unwrap()
-                .timestamp() as u64;
-
-            // Iterate over events, starting from the first one that starts during the current day
-            let mut idx = match ctx
-                .props()
-                .events
-                .binary_search_by_key(&day_start, |e| e.start_unixtime)
-            {
-                Ok(idx) => idx,
-                Err(idx) => idx,
-            };
-            let mut events = Vec::new();
-            while let Some(e) = ctx.props().events.get(idx) {
-                if e.start_unixtime > day_start + 24 * 3600 {
-                    break;
-                }
-                events.push(e);
-                idx += 1;
-            }
-
-            // Find overlapping events
-            let mut overlapping_events: HashMap<usize, BTreeSet<usize>> = HashMap::new();
-            for (i, e) in events.iter().enumerate() {
-                for (i2, e2) in events.iter().enumerate() {
-                    let e2_starts_in_e =
-                        e2.start_unixtime >= e.start_unixtime && e2.start_unixtime < e.end_unixtime;
-                    let e2_ends_in_e =
-                        e2.end_unixtime > e.start_unixtime && e2.end_unixtime <= e.end_unixtime;
-                    let e2_covers_e =
-                        e2.start_unixtime <= e.start_unixtime && e2.end_unixtime >= e.end_unixtime;
-                    if e2_starts_in_e || e2_ends_in_e || e2_covers_e {
-                        overlapping_events.entry(i).or_default().insert(i2);
-                        overlapping_events.entry(i2).or_default().insert(i);
+        let mut other_view = html! {};
+        match self.view {
+            AgendaView::Week => {
+                // Go on the first day of the week
+                let mut current_day = self.selected_day;
+                match mobile {
+                    true => current_day -= chrono::Duration::days(2),
+                    false => {
+                        for _ in 0..self.selected_day.weekday().num_days_from_monday() {
+                            current_day -= chrono::Duration::days(1);
+                        }
                     }
-                }
-            }
+                };
 
-            // Generate event components
-            let mut event_comps = Vec::new();
-            for (i, e) in events.iter().enumerate() {
-                let overlapping_this = overlapping_events
-                    .get(&i)
-                    .expect("intersecting at least itself");
-
-                let idx_overlapping = overlapping_this.iter().position(|i2| i == *i2).unwrap_or(0);
+                for d in 0..6 {
+                    // Generate day styles
+                    let mut day_style = String::new();
+                    if mobile {
+                        day_style.push_str(&format!(
+                            "position: absolute; left: {}%;",
+                            (current_day.num_days_from_ce() - 730000) * 20
+                        ));
+                    }
 
-                // Look for the largest intersection
-                let max_overlapping = overlapping_events
-                    .get(&i)
-                    .map(|s| {
-                        s.iter()
-                            .filter(|i2| **i2 != i)
-                            .map(|i2| {
-                                overlapping_events
-                                    .get(i2)
-                                    .expect("intersecting at least itself")
-                                    .intersection(overlapping_this)
-                                    .count()
-                            })
-                            .max()
-                            .unwrap_or(1)
-                    })
-                    .unwrap_or(1);
+                    day_names.push(html! {
+                        <span id={if current_day == self.selected_day {"selected-day"} else {""}}>
+                            { day_name(current_day) }
+                        </span>
+                    });
+                    days.push(day_column(ctx, d, current_day, day_style, ""));
 
-                let e: RawEvent = (*e).clone();
-                event_comps.push(html! {
-                    <EventComp
-                        week_day={d}
-                        event={e}
-                        day_start={day_start}
-                        agenda_link={ctx.link().clone()}
-                        vertical_offset={(idx_overlapping, max_overlapping)}
-                        comment_counts={Rc::clone(&ctx.props().comment_counts)}
-                        seen_comment_counts={Rc::clone(&ctx.props().seen_comment_counts)}
-                        colors={Rc::clone(&ctx.props().colors)}>
-                    </EventComp>
-                });
-                idx += 1;
+                    current_day += chrono::Duration::days(1);
+                }
             }
-
-            // Generate day styles
-            let mut day_style = String::new();
-            if mobile {
-                day_style.push_str(&format!(
-                    "position: absolute; left: {}%;",
-                    (current_day.num_days_from_ce() - 730000) * 20
-                ));
+            AgendaView::Day => {
+                day_names.push(html! { <span id="selected-day">{ day_name(self.selected_day) }</span> });
+                days.push(day_column(ctx, 0, self.selected_day, String::new(), "single-day"));
             }
-
-            let day_name = match SETTINGS.calendar() {
-                CalendarKind::Gregorian => format_day(current_day.weekday(), current_day.day()),
-                CalendarKind::Republican => match RepublicanDateTime::try_from(current_day) {
-                    Ok(datetime) => match datetime.num_month() {
-                        13 => datetime.decade_day().to_string(),
-                        _ => format!("{} {}", datetime.decade_day(), datetime.day()),
-                    },
-                    Err(_) => String::from("invalid date"),
-                },
-            };
-            day_names.push(html! {
-                <span id={if current_day == self.selected_day {"selected-day"} else {""}}>
-                    { day_name }
-                </span>
-            });
-
-            const MOBILISATION: NaiveDate = NaiveDate::from_ymd_opt(2025, 9, 10).unwrap();
-            if current_day == MOBILISATION {
-                days.push(html! {
-                    <div class="day appel-mobilisation" id={format!("day{d}")} style={day_style}>
-                        <div class="margin"></div>
-                        <h2>{"Appel à la Mobilisation Nationale"}</h2>
-                        <p>
-                            {"Rejoignez le mouvement pour un arrêt total du pays dès le "}
-                            <strong>{"10 Septembre 2025"}</strong>
-                            {", une action pacifique pour dénoncer les injustices et reprendre le pouvoir collectivement."}<br/><br/>
-                            {"Stop à l'austérité Bayrou ! Le gouvernement sacrifie nos droits : deux jours fériés supprimés, coupes massives dans la santé, gel des retraites, suppression de milliers de postes publics... "}
-                            {"Ce plan injuste frappe les plus fragiles et détruit nos services essentiels. Une autre politique est possible : solidaire, juste, et humaine."}<br/><br/>
-                            <strong>{"Mobilisons-nous !"}</strong><br/><br/>
-                        </p>
-                        <a href="https://10s25.fr">{"Nous rejoindre"}</a>
-                        <div class="margin"></div>
-                    </div>
-                });
-            } else {
-                days.push(html! {
-                    <div class="day" id={format!("day{d}")} style={day_style}>
-                        { event_comps }
-                    </div>
-                });
+            AgendaView::Month => {
+                day_names.push(html! { <span>{ month_name(self.selected_day) }</span> });
+                other_view = month_grid(ctx, self.selected_day);
+            }
+            AgendaView::List => {
+                let last_day = self.selected_day + chrono::Duration::days(LIST_DAYS - 1);
+                day_names.push(html! { <span>{ format!("{} – {}", day_name(self.selected_day), day_name(last_day)) }</span> });
+                other_view = event_list(ctx, self.selected_day);
             }
-
-            current_day += chrono::Duration::days(1);
         }
 
+        let view_buttons = [
+            (AgendaView::Week, "Semaine"),
+            (AgendaView::Day, "Jour"),
+            (AgendaView::Month, "Mois"),
+            (AgendaView::List, "Liste"),
+        ].into_iter().map(|(view, label)| {
+            let class = classes!("agenda-view-button", (view == self.view).then_some("selected"));
+            let onclick = ctx.link().callback(move |_| AgendaMsg::SetView(view));
+            html! { <button {class} {onclick}>{ t(label) }</button> }
+        }).collect::<Vec<_>>();
+
         let calendar = html! {
             <Calendar
                 agenda_link={ctx.link().clone()}
+                open_requests={self.calendar_requests}
+                settings_generation={ctx.props().settings_generation}
                 day={self.selected_day.day()}
                 month={self.selected_day.month()}
                 year={self.selected_day.year()} />
         };
 
-        let day_container_style = if mobile {
+        let timeline = matches!(self.view, AgendaView::Week | AgendaView::Day);
+        let (day_container_id, agenda_top_class) = match self.view {
+            AgendaView::Week => ("day-container", ""),
+            _ => ("single-day-container", "with-arrows"),
+        };
+        let day_container_style = if mobile && self.view == AgendaView::Week {
             format!(
                 "right: {}%",
                 100 * (self.selected_day.num_days_from_ce() - 730000)
@@ -337,8 +334,14 @@ impl Component for Agenda {
             String::new()
         };
 
+        let now = now();
+        let homework_count = ctx.props().homeworks.iter().filter(|h| h.homework.due >= now).count();
+        let has_homeworks = own_agenda && homework_count > 0;
+
         template_html!(
             "src/agenda/agenda.html",
+            onclick_homeworks = {ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Homeworks))},
+            onclick_search = {ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Search))},
             onclick_rick = {ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Rick))},
             onclick_previous = {ctx.link().callback(|_| AgendaMsg::Previous)},
             onclick_next = {ctx.link().callback(|_| AgendaMsg::Next)},
@@ -0,0 +1,295 @@
+//! Layouts of the [Agenda], one per [AgendaView].
+//!
+//! The week and day views are timelines made of the same day columns,
+//! while the month and list views summarize events as chips and rows.
+
+use crate::prelude::*;
+use chrono::Months;
+use std::collections::BTreeSet;
+
+/// Number of days shown by the list view.
+pub const LIST_DAYS: i64 = 14;
+
+/// Events shown in a cell of the month view, before collapsing the rest into a counter.
+const MONTH_CHIPS: usize = 3;
+
+const DEFAULT_COLOR: &str = "#CB6CE6";
+
+const MOBILISATION: NaiveDate = NaiveDate::from_ymd_opt(2025, 9, 10).unwrap();
+
+fn format_weekday(weekday: Weekday) -> &'static str {
+    t(match weekday {
+        Weekday::Mon => "Lundi",
+        Weekday::Tue => "Mardi",
+        Weekday::Wed => "Mercredi",
+        Weekday::Thu => "Jeudi",
+        Weekday::Fri => "Vendredi",
+        Weekday::Sat => "Samedi",
+        Weekday::Sun => "Dimanche",
+    })
+}
+
+fn format_month(month: u32) -> &'static str {
+    t(match month {
+        1 => "Janvier",
+        2 => "Février",
+        3 => "Mars",
+        4 => "Avril",
+        5 => "Mai",
+        6 => "Juin",
+        7 => "Juillet",
+        8 => "Août",
+        9 => "Septembre",
+        10 => "Octobre",
+        11 => "Novembre",
+        12 => "Décembre",
+        _ => unreachable!(),
+    })
+}
+
+/// Name of a day in the calendar picked by the user.
+pub fn day_name(day: NaiveDate) -> String {
+    match SETTINGS.calendar() {
+        CalendarKind::Gregorian => format!("{} {}", format_weekday(day.weekday()), day.day()),
+        CalendarKind::Republican => match RepublicanDateTime::try_from(day) {
+            Ok(datetime) => match datetime.num_month() {
+                13 => datetime.decade_day().to_string(),
+                _ => format!("{} {}", datetime.decade_day(), datetime.day()),
+            },
+            Err(_) => String::from("invalid date"),
+        },
+    }
+}
+
+/// Name of the month containing a day, in the calendar picked by the user.
+pub fn month_name(day: NaiveDate) -> String {
+    match SETTINGS.calendar() {
+        CalendarKind::Gregorian => format!("{} {}", format_month(day.month()), day.year()),
+        CalendarKind::Republican => match RepublicanDateTime::try_from(day) {
+            Ok(datetime) => format!("{} {}", datetime.month(), datetime.year()),
+            Err(_) => String::from("invalid date"),
+        },
+    }
+}
+
+pub fn day_start(day: NaiveDate) -> u64 {
+    Paris
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .timestamp() as u64
+}
+
+/// Events starting during a day, given events sorted by start.
+pub fn events_of_day(events: &[RawEvent], day: NaiveDate) -> &[RawEvent] {
+    let day_start = day_start(day);
+    let first = events.partition_point(|e| e.start_unixtime < day_start);
+    let count = events[first..].partition_point(|e| e.start_unixtime <= day_start + 24 * 3600);
+    &events[first..first + count]
+}
+
+fn event_color(colors: &Colors, event: &RawEvent) -> String {
+    colors.get(&event.summary).map(|c| c.to_string()).unwrap_or_else(|| String::from(DEFAULT_COLOR))
+}
+
+fn open_event(ctx: &Context<Agenda>, eid: &str) -> Callback<MouseEvent> {
+    let eid = eid.to_string();
+    ctx.link().callback(move |_| AgendaMsg::AppMsg(Box::new(AppMsg::SetPage(Page::Event { eid: eid.clone() }))))
+}
+
+/// A column of the timeline, with events placed by time.
+/// `style` positions the column when days slide on mobile.
+pub fn day_column(ctx: &Context<Agenda>, d: u8, day: NaiveDate, style: String, class: &'static str) -> Html {
+    let day_start = day_start(day);
+    let events = events_of_day(&ctx.props().events, day);
+
+    if day == MOBILISATION {
+        return html! {
+            <div class={classes!("day", class, "appel-mobilisation")} id={format!("day{d}")} {style}>
+                <div class="margin"></div>
+                <h2>{"Appel à la Mobilisation Nationale"}</h2>
+                <p>
+                    {"Rejoignez le mouvement pour un arrêt total du pays dès le "}
+                    <strong>{"10 Septembre 2025"}</strong>
+                    {", une action pacifique pour dénoncer les injustices et reprendre le pouvoir collectivement."}<br/><br/>
+                    {"Stop à l'austérité Bayrou ! Le gouvernement sacrifie nos droits : deux jours fériés supprimés, coupes massives dans la santé, gel des retraites, suppression de milliers de postes publics... "}
+                    {"Ce plan injuste frappe les plus fragiles et détruit nos services essentiels. Une autre politique est possible : solidaire, juste, et humaine."}<br/><br/>
+                    <strong>{"Mobilisons-nous !"}</strong><br/><br/>
+                </p>
+                <a href="https://10s25.fr">{"Nous rejoindre"}</a>
+                <div class="margin"></div>
+            </div>
+        };
+    }
+
+    // Find overlapping events
+    let mut overlapping_events: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+    for (i, e) in events.iter().enumerate() {
+        for (i2, e2) in events.iter().enumerate() {
+            let e2_starts_in_e =
+                e2.start_unixtime >= e.start_unixtime && e2.start_unixtime < e.end_unixtime;
+            let e2_ends_in_e =
+                e2.end_unixtime > e.start_unixtime && e2.end_unixtime <= e.end_unixtime;
+            let e2_covers_e =
+                e2.start_unixtime <= e.start_unixtime && e2.end_unixtime >= e.end_unixtime;
+            if e2_starts_in_e || e2_ends_in_e || e2_covers_e {
+                overlapping_events.entry(i).or_default().insert(i2);
+                overlapping_events.entry(i2).or_default().insert(i);
+            }
+        }
+    }
+
+    // Generate event components
+    let mut event_comps = Vec::new();
+    for (i, e) in events.iter().enumerate() {
+        let overlapping_this = overlapping_events
+            .get(&i)
+            .expect("intersecting at least itself");
+
+        let idx_overlapping = overlapping_this.iter().position(|i2| i == *i2).unwrap_or(0);
+
+        // Look for the largest intersection
+        let max_overlapping = overlapping_events
+            .get(&i)
+            .map(|s| {
+                s.iter()
+                    .filter(|i2| **i2 != i)
+                    .map(|i2| {
+                        overlapping_events
+                            .get(i2)
+                            .expect("intersecting at least itself")
+                            .intersection(overlapping_this)
+                            .count()
+                    })
+                    .max()
+                    .unwrap_or(1)
+            })
+            .unwrap_or(1);
+
+        event_comps.push(html! {
+            <EventComp
+                week_day={d}
+                event={e.clone()}
+                day_start={day_start}
+                agenda_link={ctx.link().clone()}
+                vertical_offset={(idx_overlapping, max_overlapping)}
+                comment_counts={Rc::clone(&ctx.props().comment_counts)}
+                seen_comment_counts={Rc::clone(&ctx.props().seen_comment_counts)}
+                colors={Rc::clone(&ctx.props().colors)}>
+            </EventComp>
+        });
+    }
+
+    // Homeworks due this day
+    let homework_chips = ctx.props().homeworks.iter()
+        .filter(|h| (day_start as i64..day_start as i64 + 24 * 3600).contains(&h.homework.due))
+        .map(|h| {
+            let (eid, cid) = (h.eid.clone(), h.cid);
+            let onclick = ctx.props().app_link.callback(move |_| AppMsg::OpenComment { eid: eid.clone(), cid });
+            html! {
+                <div class="day-homework" {onclick} title={markdown_to_plain_text(&h.content)}>
+                    { format!("Devoir : {}", h.summary) }
+                </div>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    html! {
+        <div class={classes!("day", class)} id={format!("day{d}")} {style}>
+            { event_comps }
+            if !homework_chips.is_empty() {
+                <div class="day-homeworks">{ homework_chips }</div>
+            }
+        </div>
+    }
+}
+
+/// Weeks of the month containing `selected_day`, with their events as chips.
+/// Clicking a day opens it in the day view.
+pub fn month_grid(ctx: &Context<Agenda>, selected_day: NaiveDate) -> Html {
+    let first = selected_day.with_day(1).unwrap();
+    let last = first + Months::new(1) - chrono::Duration::days(1);
+    let mut day = first - chrono::Duration::days(first.weekday().num_days_from_monday() as i64);
+    let end = last + chrono::Duration::days(6 - last.weekday().num_days_from_monday() as i64);
+    let today = Local::now().date_naive();
+
+    let weekdays = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun]
+        .into_iter()
+        .map(|weekday| html! { <span>{ format_weekday(weekday).chars().take(3).collect::<String>() }</span> })
+        .collect::<Html>();
+
+    let mut cells = Vec::new();
+    while day <= end {
+        let events = events_of_day(&ctx.props().events, day);
+        let chips = events.iter().take(MONTH_CHIPS).map(|e| html! {
+            <div class="month-chip" style={format!("background-color: {}", event_color(&ctx.props().colors, e))} title={e.format_time()}>
+                { e.format_name() }
+            </div>
+        }).collect::<Html>();
+        let hidden = events.len().saturating_sub(MONTH_CHIPS);
+
+        let class = classes!(
+            "month-day",
+            (day.month() != first.month()).then_some("other-month"),
+            (day == selected_day).then_some("selected"),
+            (day == today).then_some("today"),
+        );
+        let onclick = ctx.link().callback(move |_| AgendaMsg::OpenDay(day));
+        cells.push(html! {
+            <div {class} {onclick}>
+                <span class="month-day-number">{ day.day() }</span>
+                { chips }
+                if hidden > 0 {
+                    <span class="month-more">{ format!("+{hidden}") }</span>
+                }
+            </div>
+        });
+        day += chrono::Duration::days(1);
+    }
+
+    html! {
+        <div id="agenda-month">
+            <div id="agenda-month-weekdays">{ weekdays }</div>
+            <div id="agenda-month-grid">{ cells }</div>
+        </div>
+    }
+}
+
+/// Events of the [LIST_DAYS] days from `from`, grouped by day.
+pub fn event_list(ctx: &Context<Agenda>, from: NaiveDate) -> Html {
+    let days = (0..LIST_DAYS)
+        .map(|offset| from + chrono::Duration::days(offset))
+        .filter_map(|day| {
+            let events = events_of_day(&ctx.props().events, day);
+            if events.is_empty() {
+                return None;
+            }
+            let rows = events.iter().map(|e| html! {
+                <div class="list-event" onclick={open_event(ctx, &e.eid)}>
+                    <span class="list-event-color" style={format!("background-color: {}", event_color(&ctx.props().colors, e))}></span>
+                    <span class="list-event-time">{ e.format_time() }</span>
+                    <span class="list-event-name">{ e.format_name() }</span>
+                    if let Some(location) = e.format_location() {
+                        <span class="list-event-location">{ location }</span>
+                    }
+                </div>
+            }).collect::<Html>();
+            let onclick = ctx.link().callback(move |_| AgendaMsg::OpenDay(day));
+            Some(html! {
+                <section class="list-day">
+                    <h3 {onclick}>{ day_name(day) }</h3>
+                    { rows }
+                </section>
+            })
+        })
+        .collect::<Vec<_>>();
+
+    html! {
+        <div id="agenda-list">
+            if days.is_empty() {
+                <p class="list-empty">{ t("Aucun cours") }</p>
+            } else {
+                { days }
+            }
+        </div>
+    }
+}
@@ -0,0 +1,373 @@
+use super::*;
+use web_sys::{AbortController, RequestCredentials};
+
+/// Default time after which a request is aborted.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+/// Default number of retries for requests that can safely be sent twice.
+const DEFAULT_RETRIES: u32 = 2;
+/// Delay before the first retry, doubled at each new attempt.
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+/// Number of times a request rejected with `counter_too_low` is sent again after resyncing the counter.
+const MAX_COUNTER_RESYNCS: u32 = 3;
+/// Time the API is left alone after a ban without `Retry-After`, doubled at each consecutive ban.
+const BAN_BASE: Duration = Duration::from_secs(5);
+/// Longest time the API is left alone after a ban.
+const MAX_BAN_DELAY: Duration = Duration::from_secs(300);
+/// Number of times a banned request is sent again once the ban is over.
+const MAX_BAN_RETRIES: u32 = 2;
+
+/// A response whose body has been fully read.
+#[derive(Clone)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub headers: web_sys::Headers,
+    pub text: String,
+}
+
+impl ApiResponse {
+    /// Decodes the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        serde_json::from_str(&self.text).map_err(|e| ApiError::Unknown(format!("Failed to parse JSON: {e}").into()))
+    }
+}
+
+/// Why an attempt failed, to decide whether it's worth retrying.
+enum AttemptError {
+    Network(JsValue),
+    Timeout,
+    Status(ApiResponse),
+}
+
+impl AttemptError {
+    fn kind(&self) -> Option<ApiErrorKind> {
+        let AttemptError::Status(response) = self else { return None };
+        serde_json::from_str::<KnownApiError>(&response.text).ok().map(|error| error.kind)
+    }
+
+    /// The counter the server last accepted, if it rejected ours.
+    fn rejected_counter(&self) -> Option<Option<u64>> {
+        if self.kind()? != ApiErrorKind::CounterTooLow {
+            return None;
+        }
+        let AttemptError::Status(response) = self else { return None };
+        Some(response.headers.get(COUNTER_HEADER).ok().flatten().and_then(|counter| counter.parse().ok()))
+    }
+
+    /// How long the server asked us to wait, if it banned us.
+    fn ban_delay(&self) -> Option<Option<Duration>> {
+        if self.kind()?.recovery() != Recovery::BackOff {
+            return None;
+        }
+        let AttemptError::Status(response) = self else { return None };
+        Some(response.headers.get("Retry-After").ok().flatten().and_then(|seconds| seconds.trim().parse().ok()).map(Duration::from_secs))
+    }
+
+    /// Network errors and timeouts are retried, as well as 5xx responses unless they carry an error that can't be recovered from.
+    fn is_retryable(&self) -> bool {
+        match self {
+            AttemptError::Network(_) | AttemptError::Timeout => true,
+            AttemptError::Status(response) => match self.kind() {
+                Some(kind) => kind.recovery() == Recovery::Retry,
+                None => response.status >= 500,
+            },
+        }
+    }
+}
+
+impl From<AttemptError> for ApiError {
+    fn from(error: AttemptError) -> Self {
+        match error {
+            AttemptError::Network(e) => ApiError::Network(e),
+            AttemptError::Timeout => ApiError::Network(JsValue::from_str("La requête a expiré")),
+            AttemptError::Status(response) => match serde_json::from_str::<KnownApiError>(&response.text) {
+                Ok(error) => ApiError::Known(error),
+                Err(_) => ApiError::Unknown(JsValue::from_str(&format!("HTTP {}: {}", response.status, response.text))),
+            },
+        }
+    }
+}
+
+/// A GET request being sent, whose result is shared by every caller sending the same request meanwhile.
+#[derive(Clone)]
+struct InFlight {
+    /// Resolved once the result is available.
+    done: js_sys::Promise,
+    result: Rc<RefCell<Option<Result<ApiResponse, ApiError>>>>,
+}
+
+thread_local! {
+    /// GET requests being sent, by method, url and headers.
+    static IN_FLIGHT: RefCell<HashMap<String, InFlight>> = RefCell::new(HashMap::new());
+    /// Until when requests to the API are held after a ban, in milliseconds since the epoch, and the number of consecutive bans.
+    static BAN: Cell<(f64, u32)> = const { Cell::new((0.0, 0)) };
+}
+
+/// Holds every request to the API until `delay` has elapsed.
+/// Without a delay from the server, the hold doubles at each consecutive ban.
+fn hold_after_ban(delay: Option<Duration>) -> Duration {
+    BAN.with(|ban| {
+        let (until, bans) = ban.get();
+        let delay = delay.unwrap_or_else(|| BAN_BASE * 2u32.saturating_pow(bans)).min(MAX_BAN_DELAY);
+        ban.set((until.max(js_sys::Date::now() + delay.as_millis() as f64), bans.saturating_add(1)));
+        delay
+    })
+}
+
+/// Waits until the API can be called again, if we were banned.
+async fn wait_for_ban() {
+    loop {
+        let remaining = BAN.with(|ban| ban.get().0) - js_sys::Date::now();
+        if remaining <= 0.0 {
+            return;
+        }
+        sleep(Duration::from_millis(remaining as u64)).await;
+    }
+}
+
+/// A request to the API, or to any other server.
+///
+/// All requests go through [ApiRequest::send], which handles timeouts, retries and error decoding.
+/// Identical GET requests sent while one is pending are coalesced into a single network request.
+/// ```ignore
+/// let comments = ApiRequest::endpoint(&GetComments { eid }).timeout(Duration::from_secs(5)).fetch::<Vec<Comment>>().await?;
+/// ```
+#[derive(Clone)]
+pub struct ApiRequest {
+    method: &'static str,
+    url: String,
+    body: Option<(String, &'static str)>,
+    headers: Vec<(&'static str, String)>,
+    timeout: Duration,
+    retries: u32,
+    credentials: bool,
+}
+
+impl ApiRequest {
+    /// Creates a request to any url.
+    /// Only GET, HEAD, PUT and DELETE requests are retried by default, since they can safely be sent twice.
+    pub fn new(method: &'static str, url: impl Into<String>) -> Self {
+        let idempotent = matches!(method, "GET" | "HEAD" | "PUT" | "DELETE");
+        ApiRequest {
+            method,
+            url: url.into(),
+            body: None,
+            headers: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retries: if idempotent { DEFAULT_RETRIES } else { 0 },
+            credentials: false,
+        }
+    }
+
+    /// Creates a request to an endpoint declared in `common`, encoding its parameters as the endpoint expects.
+    pub fn endpoint<E: Endpoint>(endpoint: &E) -> Self {
+        let request = ApiRequest::new(E::METHOD.as_str(), endpoint.url());
+        match E::ENCODING {
+            Encoding::Query => request,
+            Encoding::Form => request.form(encode_params(&endpoint.params())),
+            Encoding::Json => request.json(endpoint),
+        }
+    }
+
+    pub fn json<T: Serialize>(mut self, data: &T) -> Self {
+        self.body = Some((serde_json::to_string(data).unwrap(), "application/json"));
+        self
+    }
+
+    pub fn form(mut self, body: impl Into<String>) -> Self {
+        self.body = Some((body.into(), "application/x-www-form-urlencoded"));
+        self
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Number of times the request is sent again after a network error, a timeout or a 5xx response.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sends cookies along with cross-origin requests.
+    pub fn with_credentials(mut self) -> Self {
+        self.credentials = true;
+        self
+    }
+
+    fn build(&self, controller: &AbortController, counter: Option<u64>) -> Result<Request, ApiError> {
+        let mut req_init = RequestInit::new();
+        req_init.method(self.method);
+        req_init.signal(Some(&controller.signal()));
+        if let Some((body, _)) = &self.body {
+            req_init.body(Some(&JsValue::from_str(body)));
+        }
+        if self.credentials {
+            req_init.credentials(RequestCredentials::Include);
+        }
+
+        let request = Request::new_with_str_and_init(&self.url, &req_init)?;
+        let headers = request.headers();
+
+        // When built in debug, fake authentication on our own API
+        #[cfg(debug_assertions)]
+        if self.url.starts_with("/api/") {
+            headers.set("X-Insa-Auth-Email", USER)?;
+        }
+
+        if let Some(counter) = counter {
+            headers.set(COUNTER_HEADER, &counter.to_string())?;
+        }
+
+        // Requests without a body stay simple, so that they aren't preflighted across origins
+        if let Some((_, content_type)) = &self.body {
+            headers.set("Content-Type", content_type)?;
+        }
+        for (name, value) in &self.headers {
+            headers.set(name, value)?;
+        }
+
+        Ok(request)
+    }
+
+    async fn attempt(&self) -> Result<ApiResponse, AttemptError> {
+        // Each attempt takes a new counter, since the server rejects the ones it already saw
+        let counter = match self.url.starts_with("/api/") {
+            true => Some(Counter::next().await),
+            false => None,
+        };
+        let controller = AbortController::new().map_err(AttemptError::Network)?;
+        let request = self.build(&controller, counter).map_err(|e| AttemptError::Network(JsValue::from_str(&e.to_string())))?;
+
+        // The body is read before clearing the timeout, so that a stalled download is aborted too
+        let abort = {
+            let controller = controller.clone();
+            Closure::once(move || controller.abort())
+        };
+        let handle = window()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(abort.as_ref().unchecked_ref(), self.timeout.as_millis() as i32)
+            .map_err(AttemptError::Network)?;
+
+        let result = async {
+            let response = JsFuture::from(window().fetch_with_request(&request)).await?;
+            let response: web_sys::Response = response.dyn_into()?;
+            let text = JsFuture::from(response.text()?).await?;
+            Ok::<_, JsValue>(ApiResponse {
+                status: response.status(),
+                headers: response.headers(),
+                text: text.as_string().unwrap_or_default(),
+            })
+        }.await;
+
+        window().clear_timeout_with_handle(handle);
+        drop(abort);
+
+        match result {
+            // 304 is only sent to conditional requests, which handle it
+            Ok(response) if (200..300).contains(&response.status) || response.status == 304 => Ok(response),
+            Ok(response) => Err(AttemptError::Status(response)),
+            Err(_) if controller.signal().aborted() => Err(AttemptError::Timeout),
+            Err(e) => Err(AttemptError::Network(e)),
+        }
+    }
+
+    /// Sends the request, retrying with exponential backoff on network errors, timeouts and 5xx responses.
+    /// Non-2xx responses are decoded into an [ApiError].
+    ///
+    /// If the same GET request is already pending, its result is awaited instead of sending another one.
+    pub async fn send(&self) -> Result<ApiResponse, ApiError> {
+        if self.method != "GET" {
+            return self.send_with_retries().await;
+        }
+
+        let key = format!("{} {} {:?}", self.method, self.url, self.headers);
+        if let Some(in_flight) = IN_FLIGHT.with(|requests| requests.borrow().get(&key).cloned()) {
+            let _ = JsFuture::from(in_flight.done).await;
+            if let Some(result) = in_flight.result.borrow().clone() {
+                return result;
+            }
+        }
+
+        let mut resolve = None;
+        let done = js_sys::Promise::new(&mut |res: Function, _| resolve = Some(res));
+        let in_flight = InFlight { done, result: Rc::new(RefCell::new(None)) };
+        IN_FLIGHT.with(|requests| requests.borrow_mut().insert(key.clone(), in_flight.clone()));
+
+        let result = self.send_with_retries().await;
+        *in_flight.result.borrow_mut() = Some(result.clone());
+        IN_FLIGHT.with(|requests| requests.borrow_mut().remove(&key));
+        if let Some(resolve) = resolve {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+        result
+    }
+
+    async fn send_with_retries(&self) -> Result<ApiResponse, ApiError> {
+        let mut attempt = 0;
+        let mut resyncs = 0;
+        let mut renewed_session = false;
+        let mut bans = 0;
+        loop {
+            if self.url.starts_with("/api/") {
+                wait_for_ban().await;
+            }
+            match self.attempt().await {
+                Ok(response) => {
+                    if self.url.starts_with("/api/") {
+                        Session::observe(&response.headers);
+                        BAN.with(|ban| ban.set((ban.get().0, 0)));
+                    }
+                    return Ok(response)
+                },
+                // The session expired: renew it in the background and send the request again, as if nothing happened
+                Err(error) if !renewed_session && self.url.starts_with("/api/") && error.kind().map(|k| k.recovery()) == Some(Recovery::Reauthenticate) => {
+                    renewed_session = true;
+                    if !Session::renew().await {
+                        return Err(error.into());
+                    }
+                }
+                // The request was rejected before having any effect, so it's safe to send it again whatever its method
+                Err(error) if resyncs < MAX_COUNTER_RESYNCS && error.rejected_counter().is_some() => {
+                    Counter::resync(error.rejected_counter().flatten());
+                    resyncs += 1;
+                }
+                // Every request to the API waits for the ban to be over, so that we stop hammering the server
+                Err(error) if self.url.starts_with("/api/") && error.ban_delay().is_some() => {
+                    let delay = hold_after_ban(error.ban_delay().flatten());
+                    if bans >= MAX_BAN_RETRIES {
+                        return Err(error.into());
+                    }
+                    log!("Request to {} was rejected by the firewall, retrying in {:?}", self.url, delay);
+                    bans += 1;
+                }
+                Err(error) if error.is_retryable() && attempt < self.retries => {
+                    let delay = BACKOFF_BASE * 2u32.pow(attempt);
+                    let jitter = Duration::from_millis((js_sys::Math::random() * 250.0) as u64);
+                    log!("Request to {} failed, retrying in {:?}", self.url, delay + jitter);
+                    sleep(delay + jitter).await;
+                    attempt += 1;
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Sends the request and decodes the JSON response.
+    pub async fn fetch<T: DeserializeOwned>(&self) -> Result<T, ApiError> {
+        self.send().await?.json()
+    }
+
+    /// Sends a request to an endpoint and decodes its response, which may be an empty body, see [Endpoint::empty_response].
+    pub async fn fetch_endpoint<E: Endpoint>(&self) -> Result<E::Response, ApiError> {
+        let response = self.send().await?;
+        match response.text.is_empty() {
+            true => E::empty_response().ok_or_else(|| ApiError::Unknown(JsValue::from_str(&format!("Empty response from /api/{}", E::PATH)))),
+            false => response.json(),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Replay protection: every request to the API carries a counter greater than the one of the previous request.
+//!
+//! Tabs share the counter through localStorage, and take values under a Web Lock so that two tabs never read the same one.
+//! Each tab also follows the values taken by the others through `storage` events.
+//! Without Web Locks, two tabs may still take the same value at the same time: the server then rejects one of the requests
+//! with `counter_too_low` and tells the last counter it accepted, from which the client resyncs and sends the request again.
+
+use super::*;
+
+const STORAGE_KEY: &str = "counter";
+/// Web Lock held by the tab taking a counter.
+const LOCK_NAME: &str = "genda-counter";
+/// How far to jump ahead when the server didn't tell which counter it expects.
+const BLIND_JUMP: u64 = 111;
+
+thread_local! {
+    /// Highest counter taken by any tab, as far as this tab knows.
+    static COUNTER: Cell<u64> = const { Cell::new(0) };
+}
+
+fn read_stored() -> u64 {
+    window().local_storage().ok().flatten()
+        .and_then(|local_storage| local_storage.get_item(STORAGE_KEY).ok().flatten())
+        .and_then(|counter| counter.parse().ok())
+        .unwrap_or_default()
+}
+
+fn store(counter: u64) {
+    if let Ok(Some(local_storage)) = window().local_storage() {
+        let _ = local_storage.set_item(STORAGE_KEY, &counter.to_string());
+    }
+}
+
+/// Runs `f` while this tab holds the Web Lock named `name`, which is released as soon as `f` returns.
+async fn with_lock(name: &str, f: impl FnOnce() -> JsValue + 'static) -> Result<JsValue, JsValue> {
+    let locks = Reflect::get(&window().navigator(), &JsValue::from_str("locks"))?;
+    if locks.is_undefined() {
+        return Err(JsValue::from_str("Web Locks unavailable"));
+    }
+    let request: Function = Reflect::get(&locks, &JsValue::from_str("request"))?.dyn_into()?;
+    let callback = Closure::once_into_js(move |_lock: JsValue| f());
+    let promise: js_sys::Promise = request.call2(&locks, &JsValue::from_str(name), &callback)?.dyn_into()?;
+    JsFuture::from(promise).await
+}
+
+pub struct Counter;
+
+impl Counter {
+    /// Loads the counter and starts following the values taken by other tabs.
+    pub fn init() {
+        Self::observe(read_stored());
+
+        let onstorage = Closure::<dyn FnMut(web_sys::StorageEvent)>::new(|e: web_sys::StorageEvent| {
+            if e.key().as_deref() == Some(STORAGE_KEY) {
+                if let Some(counter) = e.new_value().and_then(|counter| counter.parse().ok()) {
+                    Self::observe(counter);
+                }
+            }
+        });
+        window().add_event_listener_with_callback("storage", onstorage.as_ref().unchecked_ref()).unwrap();
+        onstorage.forget();
+    }
+
+    fn observe(counter: u64) {
+        COUNTER.with(|c| c.set(c.get().max(counter)));
+    }
+
+    /// Takes the next counter, to be sent with a request.
+    pub async fn next() -> u64 {
+        match with_lock(LOCK_NAME, || JsValue::from_f64(Self::take() as f64)).await {
+            Ok(counter) => counter.as_f64().map(|counter| counter as u64).unwrap_or_else(Self::take),
+            Err(_) => Self::take(),
+        }
+    }
+
+    /// Reads, increments and stores the counter, which must not be done by two tabs at the same time.
+    fn take() -> u64 {
+        Self::observe(read_stored());
+        let next = COUNTER.with(|c| {
+            c.set(c.get() + 1);
+            c.get()
+        });
+        store(next);
+        next
+    }
+
+    /// Moves past the counter the server last accepted, after it rejected one of ours.
+    /// When the server didn't send it, jumps ahead blindly.
+    pub fn resync(server_counter: Option<u64>) {
+        let current = COUNTER.with(|c| c.get());
+        let counter = server_counter.unwrap_or(current + BLIND_JUMP);
+        log!("Counter resynced from {current} to {counter}");
+        Self::observe(counter);
+        store(COUNTER.with(|c| c.get()));
+    }
+}
@@ -3,34 +3,50 @@ use crate::{prelude::*, redirect};
 use js_sys::{Reflect, Function, Array, Object};
 use serde::Deserialize;
 
+/// Minimum time between two alerts about being banned, in milliseconds.
+const BACK_OFF_ALERT_INTERVAL: f64 = 60_000.0;
+
+thread_local! {
+    /// When the user was last told that we were banned, in milliseconds since the epoch.
+    static LAST_BACK_OFF_ALERT: Cell<f64> = const { Cell::new(f64::NEG_INFINITY) };
+}
+
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct KnownApiError {
-    pub kind: String, 
+    pub kind: ApiErrorKind,
     messages: Option<HashMap<String, String>>,
     message_en: Option<String>,
     message_fr: Option<String>,
     origin: Option<String>,
 }
 
+impl KnownApiError {
+    /// The message sent by the server in the locale of the user, or the one declared in `common`.
+    pub fn message(&self) -> String {
+        let locale = SETTINGS.locale();
+        if let Some(msg) = self.messages.as_ref().and_then(|messages| messages.get(locale)) {
+            return msg.clone();
+        }
+        match (locale, &self.message_fr, &self.message_en) {
+            ("fr", Some(msg_fr), _) => msg_fr.clone(),
+            (_, _, Some(msg_en)) if locale != "fr" => msg_en.clone(),
+            _ => self.kind.message(locale).to_string(),
+        }
+    }
+}
+
 impl std::fmt::Display for KnownApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let KnownApiError { kind, messages, message_en, message_fr, .. } = self;
-        if let Some(messages) = messages {
-            let msg = messages.get(SETTINGS.locale()).unwrap_or(kind);
-            write!(f, "{msg} ({kind})")    
-        } else if let (Some(msg_fr), Some(msg_en)) = (message_fr.as_ref(), message_en.as_ref()) {
-            let msg = if SETTINGS.locale() == "fr" { msg_fr } else { msg_en };
-            write!(f, "{msg} ({kind})")    
-        } else {
-            write!(f, "{kind}")
-        }
+        write!(f, "{} ({})", self.message(), self.kind)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ApiError {
     Known(KnownApiError),
+    /// The server couldn't be reached, or didn't answer in time.
+    Network(JsValue),
     Unknown(JsValue),
 }
 
@@ -38,7 +54,7 @@ impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ApiError::Known(e) => e.fmt(f),
-            ApiError::Unknown(e) => {
+            ApiError::Network(e) | ApiError::Unknown(e) => {
                 match e.as_string() {
                     Some(s) => write!(f, "{}", s),
                     None => write!(f, "{:?}", e),
@@ -51,7 +67,7 @@ impl std::fmt::Display for ApiError {
 impl From<JsValue> for ApiError {
     fn from(value: JsValue) -> Self {
         if let (Ok(Some(kind)), Ok(message_en), Ok(message_fr), Ok(origin)) = (Reflect::get(&value, &"kind".into()).map(|v| v.as_string()), Reflect::get(&value, &"message_en".into()).map(|v| v.as_string()), Reflect::get(&value, &"message_fr".into()).map(|v| v.as_string()), Reflect::get(&value, &"origin".into()).map(|v| v.as_string())) {
-            return ApiError::Known(KnownApiError { kind, messages: None, message_en, message_fr, origin })
+            return ApiError::Known(KnownApiError { kind: ApiErrorKind::from(kind), messages: None, message_en, message_fr, origin })
         }
         ApiError::Unknown(value)
     }
@@ -63,28 +79,75 @@ impl From<KnownApiError> for ApiError {
     }
 }
 
-impl ApiError{
-    /// Handle API errors and redirect the user to the login page if necessary
-    pub fn handle_api_error(&self) {
+impl ApiError {
+    pub fn kind(&self) -> Option<&ApiErrorKind> {
         match self {
-            ApiError::Known(error) if error.kind == "counter_too_low" => {
-                log!("Counter too low");
-                counter_to_the_moon();
+            ApiError::Known(error) => Some(&error.kind),
+            _ => None,
+        }
+    }
+
+    /// What to do about the error. Network errors are retried, unknown errors are shown to the user.
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            ApiError::Known(error) => error.kind.recovery(),
+            ApiError::Network(_) => Recovery::Retry,
+            ApiError::Unknown(_) => Recovery::Surface,
+        }
+    }
+
+    /// Handle API errors and redirect the user to the login page if necessary, to come back to the current page afterwards
+    pub fn handle_api_error(&self) {
+        self.report(None)
+    }
+
+    /// Applies the recovery policy of the error.
+    /// When the error is shown to the user, it is prefixed by `context`, which describes what failed.
+    /// Errors that could be retried are shown too when there is a context, since the client already retried them and the caller gives up.
+    /// ```ignore
+    /// Err(e) => e.report(Some("Impossible de mettre à jour les groupes")),
+    /// ```
+    pub fn report(&self, context: Option<&str>) {
+        let message = match context {
+            Some(context) => format!("{context} : {self}"),
+            None => self.to_string(),
+        };
+        match self.recovery() {
+            Recovery::Retry => {
+                log!("{message}");
+                if self.kind() == Some(&ApiErrorKind::CounterTooLow) {
+                    Counter::resync(None);
+                }
+                if context.is_some() {
+                    alert(message);
+                }
             }
-            ApiError::Known(error) if error.kind == "counter_too_low" => {
-                log!("firewall_ban");
-                alert("Vous envoyez trop de requêtes! Veuillez réessayer plus tard.");
+            // The client already tried to renew the session silently. Drafts and queued changes are stored, so nothing is lost
+            Recovery::Reauthenticate => {
+                log!("{message}");
+                redirect(&Session::login_url());
             }
-            ApiError::Known(error) => {
-                log!("{}", error.to_string());
-                alert(error.to_string());
-                if error.kind == "invalid_api_key" || error.kind == "authentification_required" || error.kind == "api_key_does_not_exist" || error.kind == "api_key_expired" {
-                    redirect("/login");
+            // Every request fails while we are banned, so the user is only told once in a while
+            Recovery::BackOff => {
+                log!("{message}");
+                let now = js_sys::Date::now();
+                if now - LAST_BACK_OFF_ALERT.with(|last| last.get()) >= BACK_OFF_ALERT_INTERVAL {
+                    LAST_BACK_OFF_ALERT.with(|last| last.set(now));
+                    alert(message);
                 }
             }
-            ApiError::Unknown(error) => {
-                log!("Failed to call api: {:?}", error);
+            Recovery::Surface => {
+                log!("{message}");
+                alert(message);
             }
         }
     }
+
+    /// Like [ApiError::report], for a request that will be sent again later: errors that can be retried are only logged.
+    pub fn report_retried(&self, context: &str) {
+        match self.recovery() {
+            Recovery::Retry => log!("{context} : {self}"),
+            _ => self.report(Some(context)),
+        }
+    }
 }
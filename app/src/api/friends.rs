@@ -1,92 +1,190 @@
-use js_sys::encode_uri_component;
-
 use super::*;
 
 pub async fn get_friends() -> Result<FriendLists, ApiError> {
-    api_get("friends/").await
+    api_call(GetFriends {}).await
 }
 
 pub async fn request_friend(email: String) -> Result<(), ApiError> {
-    api_post_form(&format!("email={}", encode_uri_component(&email)), "friends/request").await
+    Outbox::submit(Mutation::RequestFriend(RequestFriend { email })).await.map(drop)
 }
 
 pub async fn accept_friend(uid: i64) -> Result<(), ApiError> {
-    api_post_form(&format!("uid={uid}"), "friends/accept").await
+    Outbox::submit(Mutation::AcceptFriend(AcceptFriend { uid })).await.map(drop)
 }
 
 pub async fn decline_friend(uid: i64) -> Result<(), ApiError> {
-    api_post_form(&format!("uid={uid}"), "friends/decline").await
+    Outbox::submit(Mutation::DeclineFriend(DeclineFriend { uid })).await.map(drop)
 }
 
 pub async fn remove_friend(uid: i64) -> Result<(), ApiError> {
-    api_post_form(&format!("uid={uid}"), "friends/remove").await
+    Outbox::submit(Mutation::RemoveFriend(RemoveFriend { uid })).await.map(drop)
 }
 
 pub async fn get_friends_schedule(uid: i64) -> Result<Vec<RawEvent>, ApiError> {
-    api_get(&format!("schedule?uid={uid}")).await
+    api_call(GetSchedule { uid: Some(uid) }).await
+}
+
+
+/// Maximum size of the cached schedules of friends, measured in bytes of JSON.
+const FRIENDS_CACHE_BUDGET: usize = 2_000_000;
+/// Age after which the schedule of a friend is fetched again, while the cached one is displayed.
+const FRIEND_SCHEDULE_TTL: i64 = 5 * 3600;
+/// Time before fetching a schedule again after a failure, doubled at each consecutive failure.
+const FRIEND_RETRY_DELAY: i64 = 30;
+/// Longest time between two fetches of a schedule that keeps failing.
+const MAX_FRIEND_RETRY_DELAY: i64 = 3600;
+/// Precision at which the last use of a schedule is persisted, so that rendering doesn't write to storage every time.
+const LAST_USED_RESOLUTION: i64 = 60;
+
+/// The schedule of a friend, with what is needed to decide when to fetch or evict it.
+#[derive(Clone)]
+struct FriendSchedule {
+    /// When the schedule was fetched.
+    fetched: i64,
+    /// When the schedule was last displayed, to evict the least recently used ones first.
+    last_used: Cell<i64>,
+    /// Size of the schedule in JSON, counted against [FRIENDS_CACHE_BUDGET].
+    size: usize,
+    events: Rc<Vec<RawEvent>>,
+}
+
+impl FriendSchedule {
+    fn new(fetched: i64, last_used: i64, events: Vec<RawEvent>) -> Self {
+        let size = serde_json::to_string(&events).map(|json| json.len()).unwrap_or_default();
+        FriendSchedule { fetched, last_used: Cell::new(last_used), size, events: Rc::new(events) }
+    }
+
+    fn is_stale(&self) -> bool {
+        now() - self.fetched > FRIEND_SCHEDULE_TTL
+    }
 }
 
+/// The last fetch of a schedule that didn't succeed yet.
+#[derive(Clone, Copy)]
+struct FetchAttempt {
+    /// When the fetch was started.
+    started: i64,
+    /// Number of consecutive fetches that failed.
+    failures: u32,
+}
+
+impl FetchAttempt {
+    /// Whether the fetch is still pending, or failed too recently to be sent again.
+    fn is_recent(&self) -> bool {
+        let delay = match self.failures {
+            0 => FRIEND_SCHEDULE_TTL,
+            failures => (FRIEND_RETRY_DELAY << (failures - 1).min(16)).min(MAX_FRIEND_RETRY_DELAY),
+        };
+        now() - self.started < delay
+    }
+}
 
+/// Schedules of friends, cached up to [FRIENDS_CACHE_BUDGET] and evicted least recently used first.
 #[derive(Default, Clone)]
 pub struct FriendsEvents {
-    events: HashMap<i64, (u64, Rc<Vec<RawEvent>>)>,
+    schedules: HashMap<i64, FriendSchedule>,
+    /// Fetches that are pending or failed, by uid. Shared with the fetches themselves so that they can record their failure.
+    attempts: Rc<RefCell<HashMap<i64, FetchAttempt>>>,
 }
 
 impl FriendsEvents {
     pub fn init() -> Self {
-        let local_storage = window().local_storage().unwrap().unwrap();
-    
-        let Ok(Some(cached_str)) = local_storage.get("cached_friends_events") else { return Self::default() };
-        let Ok(cached) = serde_json::from_str::<HashMap<i64, (u64, Vec<RawEvent>)>>(&cached_str) else { return Self::default() };
-
-        let mut event_map = HashMap::new();
-        for (uid, (time, events)) in cached {
-            event_map.insert(uid, (time, Rc::new(events)));
+        // Schedules were stored in another format before the budget was introduced
+        if Storage::get("cached_friends_events").is_some() {
+            Storage::remove("cached_friends_events");
         }
-    
-        Self { events: event_map }
+
+        let cached = Storage::get_json::<HashMap<i64, (i64, i64, Vec<RawEvent>)>>("cached_friend_schedules").unwrap_or_default();
+        let last_used = Storage::get_json::<HashMap<i64, i64>>("friend_schedules_last_used").unwrap_or_default();
+        let schedules = cached.into_iter()
+            .map(|(uid, (fetched, inserted, events))| {
+                let used = last_used.get(&uid).copied().unwrap_or(inserted).max(inserted);
+                (uid, FriendSchedule::new(fetched, used, events))
+            })
+            .collect();
+        Self { schedules, attempts: Rc::default() }
     }
 
     fn save(&self) {
-        // Sort by time, then take the 2 most recent
-        let mut records = self.events.iter().map(|(uid, (time, events))| (*uid, (*time, events.as_ref().to_owned()))).collect::<Vec<(i64, (u64, Vec<RawEvent>))>>();
-        records.sort_by_key(|(_, (time, _))| u64::MAX-time);
-        records.truncate(2);
-        
-        // Save to local storage
-        let local_storage = window().local_storage().unwrap().unwrap();
-        let events = records.into_iter().collect::<HashMap<i64, (u64, Vec<RawEvent>)>>();
-        local_storage.set("cached_friends_events", &serde_json::to_string(&events).unwrap()).unwrap();
+        let records = self.schedules.iter()
+            .map(|(uid, s)| (*uid, (s.fetched, s.last_used.get(), s.events.as_ref())))
+            .collect::<HashMap<i64, (i64, i64, &Vec<RawEvent>)>>();
+        Storage::set_json("cached_friend_schedules", &records);
+        self.save_last_used();
+    }
+
+    /// Persists when each schedule was last displayed, apart from the schedules themselves since it changes much more often.
+    fn save_last_used(&self) {
+        let last_used = self.schedules.iter().map(|(uid, s)| (*uid, s.last_used.get())).collect::<HashMap<i64, i64>>();
+        Storage::set_json("friend_schedules_last_used", &last_used);
+    }
+
+    /// Evicts the least recently used schedules until the cache fits in its budget, always keeping `keep`.
+    fn evict(&mut self, keep: i64) {
+        let mut total: usize = self.schedules.values().map(|s| s.size).sum();
+        while total > FRIENDS_CACHE_BUDGET {
+            let lru = self.schedules.iter()
+                .filter(|(uid, _)| **uid != keep)
+                .min_by_key(|(_, schedule)| schedule.last_used.get())
+                .map(|(uid, _)| *uid);
+            let Some(uid) = lru else { break };
+            if let Some(schedule) = self.schedules.remove(&uid) {
+                total -= schedule.size;
+            }
+        }
     }
 
-    pub fn update_friend(uid: i64, app_link: AppLink) {
+    /// Fetches the schedule of a friend, unless it is already being fetched or failed too recently.
+    pub fn update_friend(&self, uid: i64, app_link: AppLink) {
+        let failures = match self.attempts.borrow().get(&uid) {
+            Some(attempt) if attempt.is_recent() => return,
+            Some(attempt) => attempt.failures,
+            None => 0,
+        };
+        self.attempts.borrow_mut().insert(uid, FetchAttempt { started: now(), failures });
+
+        let attempts = Rc::clone(&self.attempts);
         spawn_local(async move {
             match get_friends_schedule(uid).await {
                 Ok(events) => app_link.send_message(AppMsg::FriendsEventsSuccess { uid, events }),
-                Err(err) => app_link.send_message(AppMsg::ApiFailure(err)),
+                Err(err) => {
+                    let failures = failures + 1;
+                    attempts.borrow_mut().insert(uid, FetchAttempt { started: now(), failures });
+                    log!("Failed to fetch the schedule of friend {uid} ({failures} times in a row)");
+                    app_link.send_message(AppMsg::ApiFailure(err));
+                }
             }
         })
     }
 
     pub fn insert(&mut self, uid: i64, mut events: Vec<RawEvent>) {
         events.sort_by_key(|event| event.start_unixtime);
-        self.events.insert(uid, (now() as u64, Rc::new(events)));
+        let now = now();
+        self.attempts.borrow_mut().remove(&uid);
+        self.schedules.insert(uid, FriendSchedule::new(now, now, events));
+        self.evict(uid);
         self.save();
     }
 
+    /// Returns the cached schedule of a friend, and fetches it if it's missing or stale.
+    /// Failed fetches are retried with an increasing delay, so this can be called on every render.
     pub fn get_events(&self, uid: i64, app_link: AppLink) -> Option<Rc<Vec<RawEvent>>> {
-        let res = self.events.get(&uid).map(|(last_updated, events)| (last_updated, Rc::clone(events)));
-        match res {
-            Some((last_updated, events)) => {
-                if now() - *last_updated as i64 > 5*3600 {
-                    Self::update_friend(uid, app_link);
-                }
-                Some(events)
-            },
-            None => {
-                Self::update_friend(uid, app_link);
-                None
-            }
+        let Some(schedule) = self.schedules.get(&uid) else {
+            self.update_friend(uid, app_link);
+            return None;
+        };
+        let now = now();
+        if now - schedule.last_used.replace(now) >= LAST_USED_RESOLUTION {
+            self.save_last_used();
         }
+        if schedule.is_stale() {
+            self.update_friend(uid, app_link);
+        }
+        Some(Rc::clone(&schedule.events))
+    }
+
+    /// Events of every cached schedule, the same course appearing once per friend following it.
+    pub fn all_events(&self) -> impl Iterator<Item = &RawEvent> {
+        self.schedules.values().flat_map(|schedule| schedule.events.iter())
     }
 }
@@ -1,25 +1,50 @@
 use serde::de::DeserializeOwned;
+use std::{future::Future, pin::Pin};
 use crate::prelude::*;
 use super::*;
 
-pub trait CachedData: DeserializeOwned + Serialize {
+pub type LocalFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// Changes are requested a bit before the last update, in case the clocks of the client and the server differ.
+/// This is harmless since changes are merged by id.
+const CHANGES_MARGIN: i64 = 300;
+/// Time during which a server that doesn't serve the changes of some data isn't asked for them again.
+const CHANGES_UNSUPPORTED_DURATION: i64 = 7 * 24 * 3600;
+
+pub trait CachedData: DeserializeOwned + Serialize + Clone + 'static {
     fn storage_key() -> &'static str;
-    fn endpoint() -> &'static str;
+    /// The endpoint returning the data.
+    type Endpoint: Endpoint<Response = Self> + Default;
     fn cache_duration() -> u64;
     fn force_reload(&self) -> bool { false }
     fn on_cache(&mut self) {}
     fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>);
 
+    /// Fetches only what changed since the cached data was last updated, if the data supports it.
+    /// Returns `None` to fetch the whole data.
+    fn load_changes(_cached: Self, _since: i64) -> Option<LocalFuture<Result<Self, ApiError>>> { None }
+
     fn save(&self) {
-        let now = (js_sys::Date::new_0().get_time() / 1000.0) as i64;
         let storage_key = Self::storage_key();
-        let local_storage = window().local_storage().unwrap().unwrap();
-        let _ = local_storage.set(&format!("last_updated_{storage_key}"), &now.to_string());
-        let _ = local_storage.set(&format!("cached_{storage_key}"), &serde_json::to_string(self).unwrap());    
+        Storage::set(&format!("last_updated_{storage_key}"), now().to_string());
+        Storage::set_json(&format!("cached_{storage_key}"), self);
+    }
+
+    /// Marks the cached data as up to date, without rewriting it.
+    fn touch() {
+        Storage::set(&format!("last_updated_{}", Self::storage_key()), now().to_string());
+    }
+
+    /// Forgets the cached data, so that the next refresh fetches it entirely.
+    fn invalidate() {
+        let storage_key = Self::storage_key();
+        for key in ["last_updated", "cached", "etag", "last_modified"] {
+            Storage::remove(&format!("{key}_{storage_key}"));
+        }
     }
 
     fn init(app_link: Scope<App>) -> Option<Self> {
-        let now = (js_sys::Date::new_0().get_time() / 1000.0) as i64;
+        let now = now();
 
         // Get cached
         let mut default = None;
@@ -46,46 +71,85 @@ pub trait CachedData: DeserializeOwned + Serialize {
 }
 
 fn load_cached<T: CachedData>() -> Option<(i64, T)> {
-    let local_storage = window().local_storage().unwrap().unwrap();
     let storage_key = T::storage_key();
-
-    let Ok(Some(Ok(last_updated))) = local_storage.get(&format!("last_updated_{storage_key}")).map(|v| v.map(|v| v.parse())) else { return None };
-    if storage_key == "events" { // Temporary
-        if last_updated < 1684973740 {
-            return None;
-        }
-    }
-    let Ok(Some(cached_str)) = local_storage.get(&format!("cached_{storage_key}")) else { return None };
-    let Ok(cached) = serde_json::from_str::<T>(&cached_str) else { return None };
+    let last_updated = Storage::get(&format!("last_updated_{storage_key}"))?.parse().ok()?;
+    let cached = Storage::get_json::<T>(&format!("cached_{storage_key}"))?;
 
     Some((last_updated, cached))
 }
 
+/// Whether the server recently failed to serve the changes of the data, in a way that means it doesn't support them.
+fn changes_unsupported<T: CachedData>() -> bool {
+    Storage::get(&format!("changes_unsupported_{}", T::storage_key()))
+        .and_then(|since| since.parse::<i64>().ok())
+        .is_some_and(|since| now() - since < CHANGES_UNSUPPORTED_DURATION)
+}
+
+/// Network errors and expired changes say nothing about whether the server supports changes, unlike any other error.
+fn means_unsupported(error: &ApiError) -> bool {
+    match error {
+        ApiError::Network(_) => false,
+        ApiError::Known(e) => e.kind != ApiErrorKind::ChangesUnavailable && e.kind.recovery() == Recovery::Surface,
+        ApiError::Unknown(_) => true,
+    }
+}
+
+/// Loads the data from the server, as cheaply as possible:
+/// - by merging the changes since the last update, if the data and the server support it,
+/// - or with a conditional request, that only returns the data if it changed since it was cached.
 async fn load<T: CachedData>() -> Result<T, ApiError> {
-    let request = Request::new_with_str(T::endpoint())?;
+    let cached = load_cached::<T>();
 
-    #[cfg(debug_assertions)]
-    request.headers().set("X-Insa-Auth-Email", USER)?;
+    if let Some((last_updated, cached)) = cached.clone().filter(|_| !changes_unsupported::<T>()) {
+        if let Some(changes) = T::load_changes(cached, last_updated - CHANGES_MARGIN) {
+            match changes.await {
+                Ok(value) => {
+                    value.save();
+                    return Ok(value);
+                }
+                Err(e) => {
+                    log!("Failed to load changes of {}, loading everything: {e}", T::storage_key());
+                    // Servers without the endpoint are only asked again once in a while, to spare a round trip on every refresh
+                    if means_unsupported(&e) {
+                        Storage::set(&format!("changes_unsupported_{}", T::storage_key()), now().to_string());
+                    }
+                }
+            }
+        }
+    }
 
-    let resp = JsFuture::from(window().fetch_with_request(&request)).await?;
-    let resp: web_sys::Response = resp.dyn_into()?;
+    let storage_key = T::storage_key();
+    let mut request = ApiRequest::endpoint(&T::Endpoint::default());
+    if cached.is_some() {
+        if let Some(etag) = Storage::get(&format!("etag_{storage_key}")) {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = Storage::get(&format!("last_modified_{storage_key}")) {
+            request = request.header("If-Modified-Since", last_modified);
+        }
+    }
 
-    if resp.status() == 400 || resp.status() == 500 {
-        let text = JsFuture::from(resp.text()?).await?;
-        let error: KnownApiError = serde_json::from_str(&text.as_string().unwrap()).map_err(|e| ApiError::Unknown(JsValue::from_str(&e.to_string())))?;
-        return Err(ApiError::Known(error));
+    let response = request.send().await?;
+    if let (304, Some((_, cached))) = (response.status, cached) {
+        T::touch();
+        return Ok(cached);
     }
 
-    let text = JsFuture::from(resp.text()?).await?;
-    let value: T = serde_json::from_str(&text.as_string().unwrap()).map_err(|e| ApiError::Unknown(JsValue::from_str(&e.to_string())))?;
+    let value: T = response.json()?;
     value.save();
+    for (header, key) in [("ETag", "etag"), ("Last-Modified", "last_modified")] {
+        match response.headers.get(header) {
+            Ok(Some(header)) => Storage::set(&format!("{key}_{storage_key}"), header),
+            _ => Storage::remove(&format!("{key}_{storage_key}")),
+        }
+    }
 
     Ok(value)
 }
 
 impl CachedData for Vec<RawEvent> {
     fn storage_key() ->  &'static str { "events" }
-    fn endpoint() ->  &'static str { "/api/schedule" }
+    type Endpoint = GetSchedule;
     fn cache_duration() -> u64 { 3600 / 2 }
     fn force_reload(&self) -> bool { self.is_empty() }
     fn on_cache(&mut self) { self.sort_by_key(|e| e.start_unixtime); }
@@ -98,11 +162,20 @@ impl CachedData for Vec<RawEvent> {
             Err(e) => app_link.send_message(AppMsg::ScheduleFailure(e)),
         }
     }
+    fn load_changes(mut cached: Self, since: i64) -> Option<LocalFuture<Result<Self, ApiError>>> {
+        if cached.is_empty() {
+            return None;
+        }
+        Some(Box::pin(async move {
+            api_call(GetScheduleChanges { since }).await?.apply(&mut cached);
+            Ok(cached)
+        }))
+    }
 }
 
 impl CachedData for UserInfo {
     fn storage_key() ->  &'static str { "user_info" }
-    fn endpoint() ->  &'static str { "/api/user-info" }
+    type Endpoint = GetUserInfo;
     fn cache_duration() -> u64 { 3600*6 }
     fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>) {
         match result {
@@ -116,7 +189,7 @@ impl CachedData for UserInfo {
 
 impl CachedData for FriendLists {
     fn storage_key() ->  &'static str { "friends" }
-    fn endpoint() ->  &'static str { "/api/friends/" }
+    type Endpoint = GetFriends;
     fn cache_duration() -> u64 { 10 }
     fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>) {
         match result {
@@ -130,7 +203,7 @@ pub type CommentCounts = HashMap<String, usize>;
 
 impl CachedData for CommentCounts {
     fn storage_key() ->  &'static str { "comment_counts" }
-    fn endpoint() ->  &'static str { "/api/textbook-course-ids" }
+    type Endpoint = GetCommentCounts;
     fn cache_duration() -> u64 { 3600 }
     fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>) {
         match result {
@@ -140,11 +213,39 @@ impl CachedData for CommentCounts {
     }
 }
 
+impl CachedData for Vec<Mention> {
+    fn storage_key() ->  &'static str { "mentions" }
+    type Endpoint = GetMentions;
+    fn cache_duration() -> u64 { 300 }
+    fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>) {
+        match result {
+            Ok(val) => app_link.send_message(AppMsg::MentionsSuccess(val)),
+            Err(e) => app_link.send_message(AppMsg::ApiFailure(e)),
+        }
+    }
+}
+
+impl CachedData for Vec<HomeworkItem> {
+    fn storage_key() ->  &'static str { "homeworks" }
+    type Endpoint = GetHomeworks;
+    fn cache_duration() -> u64 { 600 }
+    fn on_cache(&mut self) { self.sort_by_key(|h| h.homework.due); }
+    fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>) {
+        match result {
+            Ok(mut val) => {
+                val.sort_by_key(|h| h.homework.due);
+                app_link.send_message(AppMsg::HomeworksSuccess(val))
+            },
+            Err(e) => app_link.send_message(AppMsg::ApiFailure(e)),
+        }
+    }
+}
+
 pub type Colors = HashMap<String, String>;
 
 impl CachedData for Colors {
     fn storage_key() ->  &'static str { "colors" }
-    fn endpoint() ->  &'static str { "/api/colors" }
+    type Endpoint = GetColors;
     fn cache_duration() -> u64 { 1800 }
     fn on_load(result: Result<Self, ApiError>, app_link: Scope<App>) {
         match result {
@@ -0,0 +1,17 @@
+use super::*;
+
+pub async fn report_comment(report: ReportRequest) -> Result<(), ApiError> {
+    api_call(report).await
+}
+
+pub async fn get_reported_comments() -> Result<Vec<ReportedComment>, ApiError> {
+    api_call(GetReports {}).await
+}
+
+pub async fn get_moderation_log() -> Result<Vec<ModerationLogEntry>, ApiError> {
+    api_call(GetModerationLog {}).await
+}
+
+pub async fn moderate_comment(request: ModerationRequest) -> Result<(), ApiError> {
+    api_call(request).await
+}
@@ -0,0 +1,296 @@
+use super::*;
+
+/// Delay before trying to send queued mutations again, in case the `online` event never fires.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// A change made by the user that must reach the server, even if the network is down when it's made.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "request")]
+pub enum Mutation {
+    SetColors(SetColors),
+    SetGroups(SetGroups),
+    Vote(VoteRequest),
+    Comment(CommentRequest),
+    DeleteComment(DeleteComment),
+    Homework(HomeworkRequest),
+    RequestFriend(RequestFriend),
+    AcceptFriend(AcceptFriend),
+    DeclineFriend(DeclineFriend),
+    RemoveFriend(RemoveFriend),
+}
+
+impl Mutation {
+    async fn send(&self) -> Result<(), ApiError> {
+        match self {
+            Mutation::SetColors(request) => api_call(request.clone()).await,
+            Mutation::SetGroups(request) => api_call(request.clone()).await,
+            Mutation::Vote(request) => api_call(request.clone()).await,
+            Mutation::Comment(request) => api_call(request.clone()).await,
+            Mutation::DeleteComment(request) => api_call(request.clone()).await,
+            Mutation::Homework(request) => api_call(request.clone()).await,
+            Mutation::RequestFriend(request) => api_call(request.clone()).await,
+            Mutation::AcceptFriend(request) => api_call(request.clone()).await,
+            Mutation::DeclineFriend(request) => api_call(request.clone()).await,
+            Mutation::RemoveFriend(request) => api_call(request.clone()).await,
+        }
+    }
+
+    /// Removes from an older queued mutation what this one overrides.
+    /// Returns whether the older mutation has become useless.
+    fn supersedes(&self, older: &mut Mutation) -> bool {
+        match (self, older) {
+            (Mutation::SetColors(new), Mutation::SetColors(old)) => {
+                old.0.retain(|(summary, _)| !new.0.iter().any(|(s, _)| s == summary));
+                old.0.is_empty()
+            }
+            (Mutation::SetGroups(_), Mutation::SetGroups(_)) => true,
+            (Mutation::Vote(new), Mutation::Vote(old)) => new.eid == old.eid && new.cid == old.cid,
+            (Mutation::Comment(new), Mutation::Comment(old)) => new.cid.is_some() && new.eid == old.eid && new.cid == old.cid,
+            (Mutation::Homework(new), Mutation::Homework(old)) => new.eid == old.eid && new.cid == old.cid,
+            (Mutation::DeleteComment(new), Mutation::Vote(old)) => new.eid == old.eid && new.cid == old.cid,
+            (Mutation::DeleteComment(new), Mutation::Comment(old)) => new.eid == old.eid && old.cid == Some(new.cid as i64),
+            (Mutation::DeleteComment(new), Mutation::Homework(old)) => new.eid == old.eid && new.cid == old.cid,
+            (Mutation::RequestFriend(new), Mutation::RequestFriend(old)) => new == old,
+            (Mutation::AcceptFriend(new), Mutation::AcceptFriend(old)) => new == old,
+            (Mutation::DeclineFriend(new), Mutation::DeclineFriend(old)) => new == old,
+            (Mutation::RemoveFriend(new), Mutation::RemoveFriend(old)) => new == old,
+            _ => false,
+        }
+    }
+
+    /// Whether this mutation and an older queued one cancel each other, so that neither has to be sent.
+    /// This is the case of deleting a comment whose creation the server hasn't received yet.
+    fn cancels(&self, older: &Mutation) -> bool {
+        match (self, older) {
+            (Mutation::DeleteComment(new), Mutation::Comment(old)) => old.cid.is_none() && new.eid == old.eid && old.idempotency_key == Some(idempotency_key(new.cid)),
+            _ => false,
+        }
+    }
+}
+
+/// Idempotency key of the creation of the comment displayed with this local cid.
+pub fn idempotency_key(local_cid: u64) -> String {
+    format!("{local_cid:016x}")
+}
+
+/// What became of a mutation given to [Outbox::submit].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Delivery {
+    /// The server applied it.
+    Sent,
+    /// It waits in the queue, and the server will only know about it once the queue is empty.
+    Queued,
+}
+
+/// Web Lock held by the tab sending the queued mutations.
+const LOCK_NAME: &str = "genda-outbox";
+/// Prefix of the records of [Storage] holding queued mutations, one per mutation, ordered by key.
+const KEY_PREFIX: &str = "outbox_";
+/// Record in which the whole queue used to be stored.
+const LEGACY_KEY: &str = "outbox";
+
+#[derive(Default)]
+struct OutboxState {
+    /// Whether this tab sends the queued mutations.
+    /// Only one tab does, so that they aren't sent twice.
+    owner: bool,
+    /// Whether the first mutation of the queue is being sent.
+    replaying: bool,
+    /// Number of mutations queued by this tab, to order the ones queued during the same millisecond.
+    sequence: u32,
+    app_link: Option<AppLink>,
+}
+
+thread_local! {
+    static OUTBOX: RefCell<OutboxState> = RefCell::new(OutboxState::default());
+}
+
+/// Calls `on_granted` once this tab holds the Web Lock named `name`, which it keeps until it is closed.
+fn hold_lock(name: &str, on_granted: impl FnOnce() + 'static) -> Result<(), JsValue> {
+    let locks = Reflect::get(&window().navigator(), &JsValue::from_str("locks"))?;
+    if locks.is_undefined() {
+        return Err(JsValue::from_str("Web Locks unavailable"));
+    }
+    let request: Function = Reflect::get(&locks, &JsValue::from_str("request"))?.dyn_into()?;
+    let callback = Closure::once_into_js(move |_lock: JsValue| {
+        on_granted();
+        // Never resolved, so that the lock is released only when the tab is closed
+        js_sys::Promise::new(&mut |_, _| ())
+    });
+    request.call2(&locks, &JsValue::from_str(name), &callback)?;
+    Ok(())
+}
+
+/// Persistent queue of the mutations that couldn't be sent because of the network.
+///
+/// Queued mutations are replayed in order once the connection is back, and the app is told how many are pending.
+/// Every tab can queue mutations, but only the one holding the lock replays them.
+/// ```ignore
+/// Outbox::submit(Mutation::Vote(VoteRequest { eid, vote, cid })).await?;
+/// ```
+pub struct Outbox;
+
+impl Outbox {
+    /// Starts sending the mutations left by a previous session, once no other tab does.
+    pub fn init(app_link: AppLink) {
+        OUTBOX.with(|outbox| outbox.borrow_mut().app_link = Some(app_link));
+
+        let ononline = Closure::<dyn FnMut()>::new(Self::replay);
+        window().add_event_listener_with_callback("online", ononline.as_ref().unchecked_ref()).unwrap();
+        ononline.forget();
+
+        if let Err(e) = hold_lock(LOCK_NAME, Self::take_ownership) {
+            log!("This tab sends queued mutations, other tabs may too: {e:?}");
+            Self::take_ownership();
+        }
+    }
+
+    fn take_ownership() {
+        OUTBOX.with(|outbox| outbox.borrow_mut().owner = true);
+        if let Some(queue) = Storage::get_json::<Vec<Mutation>>(LEGACY_KEY) {
+            for mutation in &queue {
+                Self::store(mutation);
+            }
+            Storage::remove(LEGACY_KEY);
+            Self::notify();
+        }
+        Self::replay();
+    }
+
+    /// Whether a record of [Storage] belongs to the queue.
+    pub fn is_queue_key(key: &str) -> bool {
+        key.starts_with(KEY_PREFIX)
+    }
+
+    /// Takes the changes another tab made to the queue into account.
+    pub fn changed() {
+        Self::notify();
+        Self::replay();
+    }
+
+    /// Keys of the queued mutations, in order.
+    fn keys() -> Vec<String> {
+        let mut keys = Storage::keys_with_prefix(KEY_PREFIX);
+        keys.sort();
+        keys
+    }
+
+    /// Number of mutations waiting for the network.
+    pub fn len() -> usize {
+        Self::keys().len()
+    }
+
+    /// Sends a mutation, or queues it if it failed with an error that can be retried, such as a network error or a ban.
+    /// Only errors that can't be recovered from are returned.
+    pub async fn submit(mutation: Mutation) -> Result<Delivery, ApiError> {
+        // Mutations must reach the server in order, so nothing skips the queue
+        if Self::len() > 0 || !window().navigator().on_line() {
+            Self::enqueue(mutation);
+            Self::replay();
+            return Ok(Delivery::Queued);
+        }
+
+        match mutation.send().await {
+            Ok(()) => Ok(Delivery::Sent),
+            Err(e) if matches!(e.recovery(), Recovery::Retry | Recovery::BackOff) => {
+                e.report_retried("Modification mise en attente");
+                Self::enqueue(mutation);
+                Self::schedule_retry();
+                Ok(Delivery::Queued)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn enqueue(mutation: Mutation) {
+        // The first mutation may be being sent, by this tab or another, so it can't be changed anymore
+        for key in Self::keys().iter().skip(1) {
+            let Some(mut older) = Storage::get_json::<Mutation>(key) else { continue };
+            if mutation.cancels(&older) {
+                Storage::remove(key);
+                Self::notify();
+                return;
+            }
+            match mutation.supersedes(&mut older) {
+                true => Storage::remove(key),
+                false => Storage::set_json(key, &older),
+            }
+        }
+        Self::store(&mutation);
+        Self::notify();
+    }
+
+    /// Adds a mutation at the end of the queue.
+    fn store(mutation: &Mutation) {
+        let sequence = OUTBOX.with(|outbox| {
+            let mut outbox = outbox.borrow_mut();
+            outbox.sequence += 1;
+            outbox.sequence
+        });
+        // Keys of different tabs must not collide
+        let key = format!("{KEY_PREFIX}{:015}{sequence:06}_{:08x}", js_sys::Date::now() as u64, (js_sys::Math::random() * u32::MAX as f64) as u32);
+        Storage::set_json(&key, mutation);
+    }
+
+    fn notify() {
+        let app_link = OUTBOX.with(|outbox| outbox.borrow().app_link.clone());
+        if let Some(app_link) = app_link {
+            app_link.send_message(AppMsg::OutboxChanged(Self::len()));
+        }
+    }
+
+    fn schedule_retry() {
+        spawn_local(async {
+            sleep(RETRY_DELAY).await;
+            Self::replay();
+        });
+    }
+
+    /// Sends the queued mutations one by one, stopping at the first error that can be retried.
+    /// Does nothing in the tabs that don't hold the lock.
+    fn replay() {
+        let first = OUTBOX.with(|outbox| {
+            let mut outbox = outbox.borrow_mut();
+            if !outbox.owner || outbox.replaying {
+                return None;
+            }
+            let first = Self::keys().into_iter().next()?;
+            outbox.replaying = true;
+            Some(first)
+        });
+        let Some(mut key) = first else { return };
+
+        spawn_local(async move {
+            loop {
+                // Mutations removed by another tab meanwhile are skipped
+                if let Some(mutation) = Storage::get_json::<Mutation>(&key) {
+                    match mutation.send().await {
+                        Ok(()) => (),
+                        Err(e) if matches!(e.recovery(), Recovery::Retry | Recovery::BackOff) => {
+                            e.report_retried(&format!("{} modifications toujours en attente", Self::len()));
+                            OUTBOX.with(|outbox| outbox.borrow_mut().replaying = false);
+                            Self::schedule_retry();
+                            return;
+                        }
+                        // The session couldn't be renewed: the mutations are kept for when the user is logged in again
+                        Err(e) if e.recovery() == Recovery::Reauthenticate => {
+                            OUTBOX.with(|outbox| outbox.borrow_mut().replaying = false);
+                            e.report(Some(&format!("{} modifications toujours en attente", Self::len())));
+                            return;
+                        }
+                        // Mutations rejected by the server are dropped, since sending them again would fail too
+                        Err(e) => e.report(Some("Une modification faite hors ligne n'a pas pu être envoyée")),
+                    }
+                }
+                Storage::remove(&key);
+                Self::notify();
+                let next = Self::keys().into_iter().next();
+                OUTBOX.with(|outbox| outbox.borrow_mut().replaying = next.is_some());
+                match next {
+                    Some(next) => key = next,
+                    None => return,
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,127 @@
+//! Live updates: the app subscribes to [PUSH_PATH] and refreshes what the server says has changed.
+//!
+//! When the connection drops, it is opened again after a delay that doubles with each failure.
+//! Events sent in the meantime are lost, so the data they could have been about is refreshed once reconnected.
+//! Servers that don't serve [PUSH_PATH] aren't asked again: the data is then only refreshed when the app loads, as before push existed.
+
+use super::*;
+use web_sys::{AbortController, EventSource, MessageEvent};
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// An open event source, along with its listeners.
+struct Connection {
+    source: EventSource,
+    _onopen: Closure<dyn FnMut()>,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+    _onerror: Closure<dyn FnMut()>,
+}
+
+thread_local! {
+    static CONNECTION: RefCell<Option<Connection>> = const { RefCell::new(None) };
+    /// Delay before the next reconnection.
+    static RECONNECT_DELAY: Cell<Duration> = const { Cell::new(MIN_RECONNECT_DELAY) };
+}
+
+pub struct Push;
+
+impl Push {
+    /// Subscribes to the updates of the server.
+    pub fn init(app_link: AppLink) {
+        Self::connect(app_link, false);
+    }
+
+    fn connect(app_link: AppLink, reconnecting: bool) {
+        let source = match EventSource::new(PUSH_PATH) {
+            Ok(source) => source,
+            Err(e) => {
+                log!("Failed to open the push channel: {e:?}");
+                return;
+            }
+        };
+
+        let opened = Rc::new(Cell::new(false));
+        let onopen = {
+            let (app_link, opened) = (app_link.clone(), Rc::clone(&opened));
+            Closure::<dyn FnMut()>::new(move || {
+                opened.set(true);
+                RECONNECT_DELAY.with(|delay| delay.set(MIN_RECONNECT_DELAY));
+                if reconnecting {
+                    refresh_all(&app_link);
+                }
+            })
+        };
+
+        let onmessage = {
+            let app_link = app_link.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                match e.data().as_string().map(|data| serde_json::from_str::<PushEvent>(&data)) {
+                    Some(Ok(event)) => apply(event, &app_link),
+                    Some(Err(e)) => log!("Invalid push event: {e}"),
+                    None => (),
+                }
+            })
+        };
+
+        // The browser gives up on its own retries when the server replies with an error, so retries are handled here
+        let onerror = Closure::<dyn FnMut()>::new(move || {
+            let Some(connection) = CONNECTION.with(|c| c.borrow_mut().take()) else { return };
+            connection.source.close();
+            let delay = RECONNECT_DELAY.with(|delay| delay.replace((delay.get() * 2).min(MAX_RECONNECT_DELAY)));
+
+            let (app_link, opened) = (app_link.clone(), opened.get());
+            spawn_local(async move {
+                // The listener being run belongs to the connection, so it's dropped once it returned
+                drop(connection);
+                if !opened && is_unsupported().await {
+                    log!("The server doesn't support push, updates will only be fetched when the app loads");
+                    return;
+                }
+                log!("Push channel lost, reconnecting in {}s", delay.as_secs());
+                sleep(delay).await;
+                Self::connect(app_link, true);
+            });
+        });
+
+        source.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+        source.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        source.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        CONNECTION.with(|c| *c.borrow_mut() = Some(Connection { source, _onopen: onopen, _onmessage: onmessage, _onerror: onerror }));
+    }
+}
+
+/// Whether the server answers [PUSH_PATH] with a status meaning that it doesn't serve it at all.
+/// The event source doesn't tell the status of its failures, so it is requested again, and aborted as soon as the status is known.
+async fn is_unsupported() -> bool {
+    let Ok(controller) = AbortController::new() else { return false };
+    let mut req_init = RequestInit::new();
+    req_init.signal(Some(&controller.signal()));
+    let response = JsFuture::from(window().fetch_with_str_and_init(PUSH_PATH, &req_init)).await;
+    controller.abort();
+    match response.ok().and_then(|response| response.dyn_into::<web_sys::Response>().ok()) {
+        Some(response) => matches!(response.status(), 404 | 405 | 501),
+        // Network errors say nothing about the server
+        None => false,
+    }
+}
+
+fn apply(event: PushEvent, app_link: &AppLink) {
+    match event {
+        PushEvent::Comments { eid } => {
+            <CommentCounts as CachedData>::refresh(app_link.clone());
+            <Vec<Mention> as CachedData>::refresh(app_link.clone());
+            app_link.send_message(AppMsg::CommentsChanged(eid));
+        }
+        PushEvent::Friends => <FriendLists as CachedData>::refresh(app_link.clone()),
+        PushEvent::Schedule => <Vec<RawEvent> as CachedData>::refresh(app_link.clone()),
+    }
+}
+
+/// Refreshes everything pushed events are about, after missing some.
+fn refresh_all(app_link: &AppLink) {
+    <Vec<RawEvent> as CachedData>::refresh(app_link.clone());
+    <FriendLists as CachedData>::refresh(app_link.clone());
+    <CommentCounts as CachedData>::refresh(app_link.clone());
+    <Vec<Mention> as CachedData>::refresh(app_link.clone());
+}
@@ -0,0 +1,127 @@
+//! Renews the session of the user without leaving the app when possible.
+//!
+//! The server sends the expiry of the session with every response, in the [SESSION_EXPIRES_HEADER] header.
+//! Shortly before it, or when a request fails because the session expired, the login flow is run in a hidden frame.
+//! If the auth origin still knows the user, it comes back to [SILENT_LOGIN_RETURN] without any interaction,
+//! which tells the app that the session was renewed. Otherwise, the user is sent to the login page and brought back afterwards.
+//!
+//! Servers that never sent the header don't implement this flow, so the user is sent to the login page right away rather than
+//! after waiting for a silent login that can't succeed.
+
+use super::*;
+
+/// How long before the expiry the session is renewed.
+const RENEW_MARGIN: i64 = 5 * 60;
+/// Time after which a silent login is considered failed.
+const SILENT_LOGIN_TIMEOUT: Duration = Duration::from_secs(15);
+/// Prefix of the messages posted by [SILENT_LOGIN_RETURN].
+const SILENT_LOGIN_MESSAGE: &str = "genda-silent-login:";
+
+thread_local! {
+    /// Expiry of the session, if the server ever told it.
+    static EXPIRES: Cell<Option<i64>> = const { Cell::new(None) };
+    /// Timeout renewing the session before it expires.
+    static RENEW_TIMER: Cell<Option<i32>> = const { Cell::new(None) };
+    /// The silent login being run, resolving to whether it succeeded.
+    static SILENT_LOGIN: RefCell<Option<js_sys::Promise>> = const { RefCell::new(None) };
+}
+
+pub struct Session;
+
+impl Session {
+    /// Reads the expiry of the session from a response of the API, and plans its renewal.
+    pub fn observe(headers: &web_sys::Headers) {
+        let Some(expires) = headers.get(SESSION_EXPIRES_HEADER).ok().flatten().and_then(|e| e.parse::<i64>().ok()) else { return };
+        if EXPIRES.with(|e| e.replace(Some(expires))) == Some(expires) {
+            return;
+        }
+
+        if let Some(timer) = RENEW_TIMER.with(|t| t.take()) {
+            window().clear_timeout_with_handle(timer);
+        }
+        let delay = (expires - RENEW_MARGIN - now()).max(0) * 1000;
+        let renew = Closure::once_into_js(|| {
+            RENEW_TIMER.with(|t| t.set(None));
+            spawn_local(async {
+                if !Session::renew().await {
+                    log!("Session could not be renewed ahead of its expiry");
+                }
+            });
+        });
+        // Browsers run timeouts longer than 2^31 ms right away
+        if let Ok(timer) = window().set_timeout_with_callback_and_timeout_and_arguments_0(renew.unchecked_ref(), delay.min(i32::MAX as i64) as i32) {
+            RENEW_TIMER.with(|t| t.set(Some(timer)));
+        }
+    }
+
+    /// Runs the login flow in a hidden frame, and returns whether the session was renewed.
+    /// Concurrent calls share the same attempt.
+    /// Fails right away when the server doesn't support silent logins.
+    pub async fn renew() -> bool {
+        if EXPIRES.with(|e| e.get()).is_none() {
+            return false;
+        }
+        let promise = SILENT_LOGIN.with(|login| login.borrow_mut().get_or_insert_with(silent_login).clone());
+        let renewed = JsFuture::from(promise).await.ok().and_then(|r| r.as_bool()).unwrap_or(false);
+        SILENT_LOGIN.with(|login| login.borrow_mut().take());
+        log!("Silent login {}", if renewed { "succeeded" } else { "failed" });
+        renewed
+    }
+
+    /// The login page, bringing the user back to the current page afterwards.
+    pub fn login_url() -> String {
+        let location = window().location();
+        let current = format!("{}{}", location.pathname().unwrap_or_default(), location.search().unwrap_or_default());
+        format!("/login?{}", encode_params(&[("return", current)]))
+    }
+}
+
+/// Loads the login flow in a hidden frame, and resolves to whether [SILENT_LOGIN_RETURN] reported a renewed session.
+fn silent_login() -> js_sys::Promise {
+    js_sys::Promise::new(&mut |resolve: Function, _| {
+        let document = window().doc();
+        let Ok(iframe) = document.create_element("iframe") else {
+            let _ = resolve.call1(&JsValue::NULL, &JsValue::FALSE);
+            return;
+        };
+        let src = format!("/login?{}", encode_params(&[("silent", String::from("true")), ("return", SILENT_LOGIN_RETURN.to_string())]));
+        let _ = iframe.set_attribute("src", &src);
+        let _ = iframe.set_attribute("style", "display: none");
+        let _ = iframe.set_attribute("aria-hidden", "true");
+
+        // Called once, by whichever of the message and the timeout comes first
+        let listener: Rc<RefCell<Option<Closure<dyn FnMut(web_sys::MessageEvent)>>>> = Rc::new(RefCell::new(None));
+        let finish = {
+            let (iframe, listener) = (iframe.clone(), Rc::clone(&listener));
+            Rc::new(move |renewed: bool| {
+                let Some(onmessage) = listener.borrow_mut().take() else { return };
+                let _ = window().remove_event_listener_with_callback("message", onmessage.as_ref().unchecked_ref());
+                // The listener may be the caller, so it's dropped once it returned
+                spawn_local(async move { drop(onmessage) });
+                iframe.remove();
+                let _ = resolve.call1(&JsValue::NULL, &JsValue::from_bool(renewed));
+            })
+        };
+
+        let onmessage = {
+            let finish = Rc::clone(&finish);
+            Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |e: web_sys::MessageEvent| {
+                if e.origin() != window().location().origin().unwrap_or_default() {
+                    return;
+                }
+                if let Some(result) = e.data().as_string().and_then(|data| data.strip_prefix(SILENT_LOGIN_MESSAGE).map(String::from)) {
+                    finish(result == "ok");
+                }
+            })
+        };
+        let _ = window().add_event_listener_with_callback("message", onmessage.as_ref().unchecked_ref());
+        *listener.borrow_mut() = Some(onmessage);
+
+        let ontimeout = Closure::once_into_js(move || finish(false));
+        let _ = window().set_timeout_with_callback_and_timeout_and_arguments_0(ontimeout.unchecked_ref(), SILENT_LOGIN_TIMEOUT.as_millis() as i32);
+
+        if document.body().and_then(|body| body.append_child(&iframe).ok()).is_none() {
+            log!("Failed to insert the silent login frame");
+        }
+    })
+}
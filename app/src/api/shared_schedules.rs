@@ -0,0 +1,81 @@
+//! Schedules of a teacher or a room, across the groups of the user and their friends.
+//!
+//! They are built from the events the app already knows, and completed by the server when it supports
+//! [GetTeacherSchedule] and [GetRoomSchedule].
+
+use super::*;
+use std::collections::HashSet;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SharedSchedule {
+    Teacher(String),
+    /// A room, identified by [Location::room_id]
+    Room(String),
+}
+
+impl SharedSchedule {
+    pub fn includes(&self, event: &RawEvent) -> bool {
+        match self {
+            SharedSchedule::Teacher(teacher) => event.teachers.contains(teacher),
+            SharedSchedule::Room(room) => event.location.as_ref().map(|location| location.room_id() == *room).unwrap_or(false),
+        }
+    }
+
+    async fn fetch(&self) -> Result<Vec<RawEvent>, ApiError> {
+        match self {
+            SharedSchedule::Teacher(teacher) => api_call(GetTeacherSchedule { teacher: teacher.clone() }).await,
+            SharedSchedule::Room(room) => api_call(GetRoomSchedule { room: room.clone() }).await,
+        }
+    }
+}
+
+/// Schedules fetched from the server, kept for the session.
+#[derive(Default)]
+pub struct SharedSchedules {
+    fetched: HashMap<SharedSchedule, Vec<RawEvent>>,
+    /// Schedules requested once already, so that servers lacking the endpoints aren't asked again on every render.
+    requested: RefCell<HashSet<SharedSchedule>>,
+    /// Schedules already built, so that rendering again gives the same `Rc` without going through every event.
+    built: RefCell<HashMap<SharedSchedule, Rc<Vec<RawEvent>>>>,
+}
+
+impl SharedSchedules {
+    pub fn insert(&mut self, schedule: SharedSchedule, events: Vec<RawEvent>) {
+        self.built.get_mut().remove(&schedule);
+        self.fetched.insert(schedule, events);
+    }
+
+    /// Must be called when the known events change, for the schedules to be built again from them.
+    pub fn known_events_changed(&mut self) {
+        self.built.get_mut().clear();
+    }
+
+    /// Events of a schedule among `known` ones and the fetched ones, once each and sorted by start.
+    /// The schedule is fetched the first time it's asked for, and built again only after [SharedSchedules::insert] or [SharedSchedules::known_events_changed].
+    pub fn get_events<'a>(&self, schedule: &SharedSchedule, known: impl Iterator<Item = &'a RawEvent>, app_link: AppLink) -> Rc<Vec<RawEvent>> {
+        if self.requested.borrow_mut().insert(schedule.clone()) {
+            let schedule = schedule.clone();
+            spawn_local(async move {
+                match schedule.fetch().await {
+                    Ok(events) => app_link.send_message(AppMsg::SharedScheduleSuccess { schedule, events }),
+                    Err(e) => log!("Schedule of {schedule:?} unavailable from the server, showing known events: {e:?}"),
+                }
+            });
+        }
+
+        if let Some(events) = self.built.borrow().get(schedule) {
+            return Rc::clone(events);
+        }
+
+        let mut seen = HashSet::new();
+        let mut events = known
+            .chain(self.fetched.get(schedule).into_iter().flatten())
+            .filter(|event| schedule.includes(event) && seen.insert(event.eid.clone()))
+            .cloned()
+            .collect::<Vec<_>>();
+        events.sort_by_key(|event| event.start_unixtime);
+        let events = Rc::new(events);
+        self.built.borrow_mut().insert(schedule.clone(), Rc::clone(&events));
+        events
+    }
+}
@@ -1,21 +1,25 @@
 use super::*;
 
-#[derive(Serialize, Deserialize)]
-struct VoteQuery {
-    pub eid: String,
-    pub vote: i8,
-    pub cid: u64,
+pub async fn update_vote(eid: impl Into<String>, vote: i8, cid: u64) -> Result<Delivery, ApiError> {
+    Outbox::submit(Mutation::Vote(VoteRequest { eid: eid.into(), vote, cid })).await
 }
 
-pub async fn update_vote(eid: impl Into<String>, vote: i8, cid: u64) -> Result<(), ApiError> {
-    api_post(VoteQuery { eid: eid.into(), vote, cid }, "vote").await
-}
-
-pub async fn update_comment(eid: impl Into<String>, cid: Option<u64>, parent: Option<u64>, content: String) -> Result<(), ApiError> {
-    api_post(CommentRequest {
+/// Creates a comment when `cid` is `None`, in which case `idempotency_key` should be set.
+pub async fn update_comment(eid: impl Into<String>, cid: Option<u64>, parent: Option<u64>, content: String, mentions: Vec<i64>, idempotency_key: Option<String>) -> Result<Delivery, ApiError> {
+    Outbox::submit(Mutation::Comment(CommentRequest {
         eid: eid.into(),
         cid: cid.map(|cid| cid as i64),
         parent: parent.map(|parent| parent as i64),
         content,
-    }, "comment").await
+        mentions,
+        idempotency_key,
+    })).await
+}
+
+pub async fn set_homework(request: HomeworkRequest) -> Result<Delivery, ApiError> {
+    Outbox::submit(Mutation::Homework(request)).await
+}
+
+pub async fn get_comment_revisions(eid: impl Into<String>, cid: u64) -> Result<Vec<CommentRevision>, ApiError> {
+    api_call(GetCommentRevisions { eid: eid.into(), cid }).await
 }
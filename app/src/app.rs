@@ -6,16 +6,35 @@ use crate::{prelude::*, settings::SettingsPage, onboarding::OnboardingPage};
 pub enum AppMsg {
     /// Switch page
     SetPage { page: Page, silent: bool },
+    /// Go back or forward in the history, restoring how far the page was scrolled
+    PopState { page: Page, scroll: i32 },
+    /// Sent by the agenda when the user moved to another day, to point the url to it
+    AgendaDayChanged(NaiveDate),
     MarkCommentsAsSeen(String),
+    /// Open an event with a comment thread focused
+    OpenComment { eid: String, cid: u64 },
     UpdateFriends(FriendLists), // Use to locally update the friendlist
     UpdateColor { summary: String, color: String },
+    /// Sent by the outbox when the number of changes waiting for the network changed
+    OutboxChanged(usize),
+    /// Sent when another tab changed a record of the storage, with its key
+    StorageChanged(String),
+    /// Sent when another tab changed the settings
+    SettingsChanged,
+    /// Sent by the push channel when the comments of an event changed
+    CommentsChanged(String),
+    /// Show or hide the list of keyboard shortcuts
+    ShowShortcutHelp(bool),
 
     // Data updating messages sent by the loader in /src/api/generic.rs
     ColorsSuccess(HashMap<String, String>),
     UserInfoSuccess(UserInfo),
     FriendsSuccess(FriendLists),
     FriendsEventsSuccess{ uid: i64, events: Vec<RawEvent> },
+    SharedScheduleSuccess { schedule: SharedSchedule, events: Vec<RawEvent> },
     CommentCountsSuccess(CommentCounts),
+    MentionsSuccess(Vec<Mention>),
+    HomeworksSuccess(Vec<HomeworkItem>),
     ApiFailure(ApiError),
     ScheduleSuccess(Vec<RawEvent>),
     ScheduleFailure(ApiError),
@@ -33,6 +52,36 @@ impl AppMsg {
     }
 }
 
+fn load_seen_mentions() -> Vec<u64> {
+    Storage::get_json("seen_mentions").unwrap_or_default()
+}
+
+/// Returns the event of the most recent mention that hasn't been opened yet, among the events that can be opened.
+fn unseen_mention_eid(mentions: &[Mention], events: &[RawEvent]) -> Option<String> {
+    let seen = load_seen_mentions();
+    mentions.iter()
+        .filter(|m| !seen.contains(&m.cid) && events.iter().any(|e| e.eid == m.eid))
+        .max_by_key(|m| m.ts)
+        .map(|m| m.eid.clone())
+}
+
+fn mark_mentions_as_seen(mentions: &[Mention], eid: &str) {
+    let mut seen = load_seen_mentions();
+    seen.retain(|cid| mentions.iter().any(|m| m.cid == *cid));
+    seen.extend(mentions.iter().filter(|m| m.eid == eid && !seen.contains(&m.cid)).map(|m| m.cid).collect::<Vec<_>>());
+    Storage::set_json("seen_mentions", &seen);
+}
+
+/// Reads the data cached by another tab, if `key` is the one it is stored under.
+fn synced<T: CachedData>(key: &str) -> Option<T> {
+    if key != format!("cached_{}", T::storage_key()) {
+        return None;
+    }
+    let mut value: T = Storage::get_json(key)?;
+    value.on_cache();
+    Some(value)
+}
+
 /// The main component of the app.
 /// Stores data that is shared between pages, as well as the page that is currently displayed.
 pub struct App {
@@ -41,10 +90,28 @@ pub struct App {
     events: Rc<Vec<RawEvent>>,
     friends: Rc<Option<FriendLists>>,
     friends_events: FriendsEvents,
+    /// Schedules of teachers and rooms sent by the server.
+    shared_schedules: SharedSchedules,
     comment_counts: Rc<CommentCounts>,
     colors: Rc<Colors>,
     seen_comment_counts: Rc<CommentCounts>,
-    tabbar_bait_points: (bool, bool, bool),
+    /// Comments in which the user has been mentioned.
+    mentions: Vec<Mention>,
+    homeworks: Rc<Vec<HomeworkItem>>,
+    /// Number of changes waiting for the network to be sent.
+    pending_mutations: usize,
+    /// Comment thread to focus when opening the event popup.
+    focused_comment: Option<u64>,
+    /// Incremented when the comments of the opened event change, for the popup to load them again.
+    comments_version: usize,
+    /// Scroll position to restore once the page is rendered, after going back in the history.
+    pending_scroll: Option<i32>,
+    /// Bait points of the agenda, friends and settings tabs, and the event to open when the user has been mentioned.
+    tabbar_bait_points: (bool, bool, bool, Option<String>),
+    /// Incremented when the settings change in another tab, and passed to the components that read them.
+    settings_generation: usize,
+    /// Whether the list of keyboard shortcuts is shown over the page.
+    shortcut_help: bool,
     page: Page,
 }
 
@@ -54,31 +121,40 @@ impl Component for App {
 
     fn create(ctx: &Context<Self>) -> Self {
         // Handle popstate events (back browser button)
+        // History entries hold how far their page was scrolled, which the app restores itself
+        if let Ok(history) = window().history() {
+            let _ = Reflect::set(&history, &JsValue::from_str("scrollRestoration"), &JsValue::from_str("manual"));
+        }
         let link2 = ctx.link().clone();
         let closure = Closure::wrap(Box::new(move |e: web_sys::PopStateEvent| {
-            let state = e.state().as_string();
-            let page = state.map(|s| Page::from_path(&s)).unwrap_or(Page::Agenda);
-            link2.send_message(AppMsg::SilentSetPage(page));
+            let page = Page::from_path(&window().location().pathname().unwrap_or_default());
+            let scroll = e.state().as_f64().unwrap_or_default() as i32;
+            link2.send_message(AppMsg::PopState { page, scroll });
         }) as Box<dyn FnMut(_)>);
         window().add_event_listener_with_callback("popstate", closure.as_ref().unchecked_ref()).unwrap();
         closure.forget();
 
         // Update data
-        let events = CachedData::init(ctx.link().clone()).unwrap_or_default();
+        let events: Vec<RawEvent> = CachedData::init(ctx.link().clone()).unwrap_or_default();
         let user_info: Option<UserInfo> = CachedData::init(ctx.link().clone());
         let friends = CachedData::init(ctx.link().clone());
         let friends_events = FriendsEvents::init();
         let comment_counts = CachedData::init(ctx.link().clone()).unwrap_or_default();
         let colors = CachedData::init(ctx.link().clone()).unwrap_or_default();
+        let mentions: Vec<Mention> = CachedData::init(ctx.link().clone()).unwrap_or_default();
+        let homeworks: Vec<HomeworkItem> = CachedData::init(ctx.link().clone()).unwrap_or_default();
+
+        // Send the changes made while offline
+        Outbox::init(ctx.link().clone());
+
+        // Follow the changes made in other tabs
+        TabSync::init(ctx.link().clone());
+
+        // Receive live updates
+        Push::init(ctx.link().clone());
 
         // Load seen comment counts
-        let local_storage = window().local_storage().unwrap().unwrap();
-        let mut seen_comment_counts = Rc::new(HashMap::new());
-        'try_load: {
-            let Ok(Some(data)) = local_storage.get("seen_comment_counts") else { break 'try_load };
-            let Ok(data) = serde_json::from_str::<HashMap<String, usize>>(&data) else { break 'try_load };
-            seen_comment_counts = Rc::new(data);
-        }
+        let seen_comment_counts = Rc::new(Storage::get_json::<CommentCounts>("seen_comment_counts").unwrap_or_default());
     
         // Open corresponding page
         let path = window().location().pathname().unwrap_or_default();
@@ -89,6 +165,7 @@ impl Component for App {
             false,
             friends.as_ref().map(|f: &FriendLists| !f.incoming.is_empty()).unwrap_or(false),
             false,
+            unseen_mention_eid(&mentions, &events),
         );
 
         Self {
@@ -97,10 +174,19 @@ impl Component for App {
             user_info: Rc::new(user_info),
             friends: Rc::new(friends),
             friends_events,
+            shared_schedules: SharedSchedules::default(),
             comment_counts: Rc::new(comment_counts),
             colors: Rc::new(colors),
             seen_comment_counts,
+            mentions,
+            homeworks: Rc::new(homeworks),
+            pending_mutations: Outbox::len(),
+            focused_comment: None,
+            comments_version: 0,
+            pending_scroll: None,
             tabbar_bait_points,
+            settings_generation: 0,
+            shortcut_help: false,
             page
         }
     }
@@ -129,14 +215,21 @@ impl Component for App {
             }
             AppMsg::FriendsEventsSuccess { uid, events } => {
                 self.friends_events.insert(uid, events);
-                matches!(self.page, Page::FriendAgenda { .. } )
+                self.shared_schedules.known_events_changed();
+                matches!(self.page, Page::FriendAgenda { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. })
+            },
+            AppMsg::SharedScheduleSuccess { schedule, events } => {
+                self.shared_schedules.insert(schedule, events);
+                matches!(self.page, Page::TeacherAgenda { .. } | Page::RoomAgenda { .. })
             },
             AppMsg::ScheduleSuccess(events) => {
                 if events.len() <= 25 {
                     alert("Votre agenda semble quasiment vide. Cochez bien tous vos groupes dans les paramètres.");
                 }
                 self.events = Rc::new(events);
-                matches!(self.page, Page::Agenda | Page::Event { .. })
+                self.shared_schedules.known_events_changed();
+                self.tabbar_bait_points.3 = unseen_mention_eid(&self.mentions, &self.events);
+                matches!(self.page, Page::Agenda { .. } | Page::Event { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. })
             },
             AppMsg::UserInfoSuccess(user_info) => {
                 let mut should_refresh = false;
@@ -145,6 +238,8 @@ impl Component for App {
                 if let Some(old_user_info) = self.user_info.as_ref() {
                     if old_user_info.groups != user_info.groups {
                         self.events = Rc::new(Vec::new());
+                        self.shared_schedules.known_events_changed();
+                        <Vec<RawEvent>>::invalidate();
                         <Vec<RawEvent>>::refresh(ctx.link().clone());
                         should_refresh = true;
                     }
@@ -159,11 +254,28 @@ impl Component for App {
                     ctx.link().send_message(AppMsg::SetPage(Page::Onboarding));
                 }
 
-                should_refresh || matches!(self.page, Page::Settings | Page::Onboarding)
+                should_refresh || matches!(self.page, Page::Settings | Page::Onboarding | Page::Moderation)
+            },
+            AppMsg::MentionsSuccess(mentions) => {
+                mentions.save();
+                self.mentions = mentions;
+                let mention_bait_point = unseen_mention_eid(&self.mentions, &self.events);
+                let changed = self.tabbar_bait_points.3 != mention_bait_point;
+                self.tabbar_bait_points.3 = mention_bait_point;
+                changed
+            },
+            AppMsg::HomeworksSuccess(homeworks) => {
+                self.homeworks = Rc::new(homeworks);
+                matches!(self.page, Page::Agenda { .. } | Page::Homeworks)
+            },
+            AppMsg::OpenComment { eid, cid } => {
+                ctx.link().send_message(AppMsg::SetPage(Page::Event { eid }));
+                self.focused_comment = Some(cid);
+                false
             },
             AppMsg::CommentCountsSuccess(comment_counts) => {
                 self.comment_counts = Rc::new(comment_counts);
-                matches!(self.page, Page::Agenda)
+                matches!(self.page, Page::Agenda { .. })
             },
             AppMsg::ScheduleFailure(api_error) => {
                 api_error.handle_api_error();
@@ -179,20 +291,32 @@ impl Component for App {
             AppMsg::SetPage { page, silent } => {
                 // Remove bait points
                 match page {
-                    Page::Agenda => self.tabbar_bait_points.0 = false,
+                    Page::Agenda { .. } => self.tabbar_bait_points.0 = false,
                     Page::Friends => self.tabbar_bait_points.1 = false,
                     Page::Settings => self.tabbar_bait_points.2 = false,
                     _ => (),
                 }
 
+                if !matches!(page, Page::Event { .. }) {
+                    self.focused_comment = None;
+                }
+
+                // Opening an event clears the mentions it contains
+                if let Page::Event { eid } = &page {
+                    if self.mentions.iter().any(|m| m.eid == *eid) {
+                        mark_mentions_as_seen(&self.mentions, eid);
+                        self.tabbar_bait_points.3 = unseen_mention_eid(&self.mentions, &self.events);
+                    }
+                }
+
                 // FIXME TODO
-                // Prevent user to go on an event page from the friend-agenda page as it is not supported
-                if matches!(self.page, Page::FriendAgenda { .. }) && matches!(page, Page::Event { .. }) {
+                // Prevent user to go on an event page from the agendas of others as it is not supported
+                if matches!(self.page, Page::FriendAgenda { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. }) && matches!(page, Page::Event { .. }) {
                     return false;
                 }
 
                 // When going from event to agenda, restore the selected day
-                if let (Page::Event { eid }, Page::Agenda) = (&self.page, &page) {
+                if let (Page::Event { eid }, Page::Agenda { target: None }) = (&self.page, &page) {
                     if let Some(event) = self.events.iter().find(|e| e.eid == *eid) {
                         if let Some(start) = Paris.timestamp_opt(event.start_unixtime as i64, 0).single() {
                             self.next_selected_day = Some(start.date_naive());
@@ -201,19 +325,34 @@ impl Component for App {
                 }
 
                 let document = window().doc();
-                let (data, title) = page.data_and_title();
+                let title = page.title();
                 if !silent {
                     if let Ok(history) = window().history() {
-                        let _ = history.push_state_with_url(&JsValue::from_str(&data), title, Some(&format!("/{data}")));
+                        // Remember where the page being left was scrolled, for when the user comes back
+                        let _ = history.replace_state(&JsValue::from_f64(scroll_position() as f64), "");
+                        let _ = history.push_state_with_url(&JsValue::from_f64(0.0), title, Some(&page.path()));
                     }
                 }
                 document.set_title(title);
                 self.page = page;
                 true
             },
+            AppMsg::PopState { page, scroll } => {
+                self.pending_scroll = Some(scroll);
+                self.update(ctx, AppMsg::SilentSetPage(page))
+            },
+            AppMsg::AgendaDayChanged(day) => {
+                if let Some(page) = self.page.with_day(day) {
+                    if let Ok(history) = window().history() {
+                        let _ = history.replace_state_with_url(&JsValue::from_f64(scroll_position() as f64), page.title(), Some(&page.path()));
+                    }
+                    self.page = page;
+                }
+                false
+            },
             AppMsg::ColorsSuccess(new_colors) => {
                 self.colors = Rc::new(new_colors);
-                matches!(self.page, Page::Agenda | Page::Event { .. })
+                matches!(self.page, Page::Agenda { .. } | Page::Event { .. })
             },
             AppMsg::MarkCommentsAsSeen(eid) => {
                 let val = self.comment_counts.get(&eid).copied().unwrap_or_default();
@@ -221,12 +360,11 @@ impl Component for App {
                 seen_comment_counts.retain(|eid,_| self.events.iter().any(|e| e.eid == *eid));
                 seen_comment_counts.insert(eid, val);
                 self.seen_comment_counts = Rc::new(seen_comment_counts);
-                let local_storage = window().local_storage().unwrap().unwrap();
-                let _ = local_storage.set("seen_comment_counts", &serde_json::to_string(&self.seen_comment_counts.deref()).unwrap());
+                Storage::set_json("seen_comment_counts", self.seen_comment_counts.deref());
                 true
             },
             AppMsg::UpdateColor { summary, color } => {
-                let to_publish = vec!((summary.clone(), color.clone()));
+                let to_publish = SetColors(vec!((summary.clone(), color.clone())));
 
                 let mut new_colors: HashMap<_, _> = self.colors.as_ref().clone();
                 new_colors.insert(summary, color);
@@ -234,37 +372,127 @@ impl Component for App {
                 Colors::save(&self.colors);
 
                 spawn_local(async move {
-                    if let Err(e) = api_post(to_publish, "colors").await {
-                        alert(format!("Failed to update color: {e}"));
+                    if let Err(e) = Outbox::submit(Mutation::SetColors(to_publish)).await {
+                        e.report(Some("Impossible de mettre à jour la couleur"));
                     };
                 });
 
-                matches!(self.page, Page::Agenda | Page::FriendAgenda { .. } | Page::Event { .. })
+                matches!(self.page, Page::Agenda { .. } | Page::FriendAgenda { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. } | Page::Event { .. })
+            }
+            AppMsg::OutboxChanged(pending_mutations) => {
+                let changed = self.pending_mutations != pending_mutations;
+                self.pending_mutations = pending_mutations;
+                changed
+            }
+            AppMsg::StorageChanged(key) => {
+                if let Some(events) = synced::<Vec<RawEvent>>(&key) {
+                    self.events = Rc::new(events);
+                    self.shared_schedules.known_events_changed();
+                    self.tabbar_bait_points.3 = unseen_mention_eid(&self.mentions, &self.events);
+                    matches!(self.page, Page::Agenda { .. } | Page::Event { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. })
+                } else if let Some(user_info) = synced::<UserInfo>(&key) {
+                    self.user_info = Rc::new(Some(user_info));
+                    matches!(self.page, Page::Settings | Page::Onboarding | Page::Moderation)
+                } else if let Some(friends) = synced::<FriendLists>(&key) {
+                    self.friends = Rc::new(Some(friends));
+                    matches!(self.page, Page::Friends | Page::Event { .. })
+                } else if let Some(comment_counts) = synced::<CommentCounts>(&key) {
+                    self.comment_counts = Rc::new(comment_counts);
+                    matches!(self.page, Page::Agenda { .. })
+                } else if let Some(colors) = synced::<Colors>(&key) {
+                    self.colors = Rc::new(colors);
+                    matches!(self.page, Page::Agenda { .. } | Page::FriendAgenda { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. } | Page::Event { .. })
+                } else if let Some(homeworks) = synced::<Vec<HomeworkItem>>(&key) {
+                    self.homeworks = Rc::new(homeworks);
+                    matches!(self.page, Page::Agenda { .. } | Page::Homeworks)
+                } else if let Some(mentions) = synced::<Vec<Mention>>(&key) {
+                    self.mentions = mentions;
+                    self.tabbar_bait_points.3 = unseen_mention_eid(&self.mentions, &self.events);
+                    true
+                } else if key == "seen_mentions" {
+                    self.tabbar_bait_points.3 = unseen_mention_eid(&self.mentions, &self.events);
+                    true
+                } else if key == "seen_comment_counts" {
+                    self.seen_comment_counts = Rc::new(Storage::get_json::<CommentCounts>("seen_comment_counts").unwrap_or_default());
+                    matches!(self.page, Page::Agenda { .. })
+                } else if key == "cached_friend_schedules" {
+                    self.friends_events = FriendsEvents::init();
+                    self.shared_schedules.known_events_changed();
+                    matches!(self.page, Page::FriendAgenda { .. } | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. })
+                } else if Outbox::is_queue_key(&key) {
+                    // Sends the mutations queued by the other tab if this one holds the queue
+                    Outbox::changed();
+                    false
+                } else {
+                    false
+                }
+            }
+            AppMsg::ShowShortcutHelp(shown) => {
+                let changed = self.shortcut_help != shown;
+                self.shortcut_help = shown;
+                changed
+            }
+            AppMsg::SettingsChanged => {
+                self.settings_generation += 1;
+                true
+            }
+            AppMsg::CommentsChanged(eid) => {
+                if !matches!(&self.page, Page::Event { eid: opened } if *opened == eid) {
+                    return false;
+                }
+                self.comments_version += 1;
+                true
             }
         }
     }
-    
+
     fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <>
+                {self.view_page(ctx)}
+                if self.shortcut_help {
+                    <ShortcutHelp app_link={ctx.link().clone()} />
+                }
+            </>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if matches!(self.page, Page::Agenda { .. }) {
+            self.next_selected_day = None;
+        }
+        if let Some(scroll) = self.pending_scroll.take() {
+            restore_scroll(scroll);
+        }
+    }
+}
+
+impl App {
+    fn view_page(&self, ctx: &Context<Self>) -> Html {
         match &self.page {
-            Page::Agenda => html!(<>
+            Page::Agenda { target } => html!(<>
                 <Agenda
-                    selected_day={self.next_selected_day}
+                    selected_day={self.next_selected_day.or(target.map(|target| target.day()))}
                     events={Rc::clone(&self.events)}
                     app_link={ctx.link().clone()}
                     user_info={Rc::clone(&self.user_info)}
                     friends={Rc::clone(&self.friends)}
                     comment_counts={Rc::clone(&self.comment_counts)}
                     seen_comment_counts={Rc::clone(&self.seen_comment_counts)}
-                    colors={Rc::clone(&self.colors)} />
-                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points} />
+                    colors={Rc::clone(&self.colors)}
+                    homeworks={Rc::clone(&self.homeworks)}
+                    settings_generation={self.settings_generation} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
             </>),
             Page::Event { eid }  => {
                 let event = match self.events.iter().find(|e| e.eid == *eid) {
                     Some(event) => event.to_owned(),
-                    None => {
-                        ctx.link().send_message(AppMsg::SetPage(Page::Agenda));
+                    // The schedule is still loading
+                    None if self.events.is_empty() => {
+                        ctx.link().send_message(AppMsg::SetPage(Page::Agenda { target: None }));
                         return html!();
                     }
+                    None => return self.view_not_found(ctx),
                 };
 
                 html!(<>
@@ -273,46 +501,68 @@ impl Component for App {
                         app_link={ctx.link().clone()}
                         friends={Rc::clone(&self.friends)}
                         user_info={Rc::clone(&self.user_info)}
-                        colors={Rc::clone(&self.colors)} />
-                    <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points} />
+                        colors={Rc::clone(&self.colors)}
+                        events={Rc::clone(&self.events)}
+                        focused_comment={self.focused_comment}
+                        comments_version={self.comments_version}
+                        settings_generation={self.settings_generation}
+                        pending_mutations={self.pending_mutations} />
+                    <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
                 </>)
             },
             Page::Friends => html!(<>
                 <FriendsPage friends={Rc::clone(&self.friends)} app_link={ctx.link().clone()} />
-                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
             </>),
-            Page::FriendAgenda { pseudo } => {
+            Page::FriendAgenda { pseudo, date } => {
                 let email = format!("{pseudo}@insa-rouen.fr");
-                let uid = match self.friends.deref().as_ref().and_then(|f| f.friends.iter().find(|f| f.0.email == *email)) {
-                    Some(f) => f.0.uid,
-                    None => return html!("404 friend not found"), // TODO 404 page
+                let Some(friends) = self.friends.deref() else { return html!() };
+                let friend = match friends.friends.iter().find(|f| f.0.email == *email) {
+                    Some(f) => f.0.clone(),
+                    None => return self.view_not_found(ctx),
                 };
-                let events = self.friends_events.get_events(uid, ctx.link().clone()).unwrap_or_default();
-                let profile_src = format!("https://api.dicebear.com/5.x/identicon/svg?seed={}", uid);
+                let events = self.friends_events.get_events(friend.uid, ctx.link().clone()).unwrap_or_default();
                 html!(<>
                     <Agenda
                         events={events}
+                        selected_day={*date}
                         app_link={ctx.link().clone()}
-                        profile_src={profile_src}
+                        profile={friend}
                         friends={Rc::clone(&self.friends)}
                         user_info={Rc::clone(&self.user_info)}
                         comment_counts={Rc::clone(&self.comment_counts)}
                         seen_comment_counts={Rc::clone(&self.seen_comment_counts)}
-                        colors={Rc::clone(&self.colors)} />
-                    <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points} />
+                        colors={Rc::clone(&self.colors)}
+                        settings_generation={self.settings_generation} />
+                    <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
                 </>)
             },
+            Page::TeacherAgenda { teacher, date } => self.view_shared_schedule(ctx, SharedSchedule::Teacher(teacher.clone()), teacher.clone(), *date),
+            Page::RoomAgenda { room, date } => self.view_shared_schedule(ctx, SharedSchedule::Room(room.clone()), format!("Salle {room}"), *date),
             Page::Stotra => html!(<>
-                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
                 <iframe src={STOTRA_URL} id="stotra-iframe"></iframe>
             </>),
             Page::Settings => html!(<>
-                <SettingsPage app_link={ ctx.link().clone() } user_info={Rc::clone(&self.user_info)} />
-                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points} />
+                <SettingsPage app_link={ ctx.link().clone() } user_info={Rc::clone(&self.user_info)} settings_generation={self.settings_generation} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
             </>),
             Page::Onboarding => html!(<>
                 <OnboardingPage app_link={ ctx.link().clone() } user_info={Rc::clone(&self.user_info)} />
             </>),
+            Page::Homeworks => html!(<>
+                <HomeworksPage app_link={ ctx.link().clone() } homeworks={Rc::clone(&self.homeworks)} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
+            </>),
+            Page::Search => html!(<>
+                <SearchPage app_link={ ctx.link().clone() } events={Rc::clone(&self.events)} colors={Rc::clone(&self.colors)} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
+            </>),
+            Page::Moderation => html!(<>
+                <ModerationPage app_link={ ctx.link().clone() } user_info={Rc::clone(&self.user_info)} settings_generation={self.settings_generation} />
+                <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
+            </>),
+            Page::NotFound { .. } => self.view_not_found(ctx),
             Page::Rick => {
                 let random = js_sys::Math::random();
                 let rick = if random > 0.1 {"rick1"} else {"rick2"};
@@ -322,9 +572,30 @@ impl Component for App {
         }
     }
 
-    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
-        if matches!(self.page, Page::Agenda) {
-            self.next_selected_day = None;
-        }
+    /// Agenda of a teacher or a room, among the events of the user and their friends.
+    fn view_shared_schedule(&self, ctx: &Context<Self>, schedule: SharedSchedule, title: String, date: Option<NaiveDate>) -> Html {
+        let known = self.events.iter().chain(self.friends_events.all_events());
+        let events = self.shared_schedules.get_events(&schedule, known, ctx.link().clone());
+        html!(<>
+            <Agenda
+                events={events}
+                selected_day={date}
+                app_link={ctx.link().clone()}
+                title={title}
+                friends={Rc::clone(&self.friends)}
+                user_info={Rc::clone(&self.user_info)}
+                comment_counts={Rc::clone(&self.comment_counts)}
+                seen_comment_counts={Rc::clone(&self.seen_comment_counts)}
+                colors={Rc::clone(&self.colors)}
+                settings_generation={self.settings_generation} />
+            <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
+        </>)
+    }
+
+    fn view_not_found(&self, ctx: &Context<Self>) -> Html {
+        html!(<>
+            <NotFoundPage app_link={ctx.link().clone()} path={self.page.path()} />
+            <TabBar app_link={ctx.link()} page={self.page.clone()} bait_points={self.tabbar_bait_points.clone()} pending_mutations={self.pending_mutations} />
+        </>)
     }
 }
@@ -0,0 +1,94 @@
+use crate::prelude::*;
+
+/// Scrambles the uid so that close uids produce very different identicons.
+fn mix(uid: i64) -> u64 {
+    // splitmix64 finalizer
+    let mut x = (uid as u64).wrapping_add(0x9E3779B97F4A7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 31)
+}
+
+/// Generates a deterministic 5x5 symmetric identicon for the given uid.
+pub fn identicon_svg(uid: i64) -> String {
+    let hash = mix(uid);
+    let hue = hash % 360;
+    let saturation = 45 + (hash >> 9) % 20;
+    let lightness = 45 + (hash >> 14) % 15;
+
+    let mut rects = String::new();
+    for row in 0..5 {
+        for col in 0..3 {
+            let bit = 20 + row * 3 + col;
+            if (hash >> bit) & 1 == 0 {
+                continue;
+            }
+            rects.push_str(&format!("<rect x='{col}' y='{row}' width='1' height='1'/>"));
+            if col < 2 {
+                rects.push_str(&format!("<rect x='{}' y='{row}' width='1' height='1'/>", 4 - col));
+            }
+        }
+    }
+
+    format!("<svg xmlns='http://www.w3.org/2000/svg' viewBox='-0.5 -0.5 6 6' shape-rendering='crispEdges'><g fill='hsl({hue},{saturation}%,{lightness}%)'>{rects}</g></svg>")
+}
+
+/// Returns the identicon of the given uid as a data URI, usable as an image source.
+pub fn identicon_url(uid: i64) -> String {
+    let svg = identicon_svg(uid);
+    let mut url = String::with_capacity(svg.len() + 32);
+    url.push_str("data:image/svg+xml,");
+    for c in svg.chars() {
+        match c {
+            '<' => url.push_str("%3C"),
+            '>' => url.push_str("%3E"),
+            '#' => url.push_str("%23"),
+            '%' => url.push_str("%25"),
+            '"' => url.push_str("%22"),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+/// Returns the picture of the user if set, or its identicon otherwise.
+pub fn avatar_url(uid: i64, picture: Option<&str>) -> String {
+    match picture {
+        Some(picture) if !picture.is_empty() => picture.to_string(),
+        _ => identicon_url(uid),
+    }
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct AvatarProps {
+    pub uid: i64,
+    #[prop_or_default]
+    pub picture: Option<String>,
+    #[prop_or_default]
+    pub alt: Option<String>,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// Displays the avatar of a user.
+/// Pictures are honored when set, otherwise an identicon is generated locally so that no uid leaks to a third party.
+#[function_component(Avatar)]
+pub fn avatar(props: &AvatarProps) -> Html {
+    let src = use_memo((props.uid, props.picture.clone()), |(uid, picture)| avatar_url(*uid, picture.as_deref()));
+    let alt = props.alt.clone().unwrap_or_else(|| String::from("Avatar"));
+
+    html! {
+        <img class={props.class.clone()} src={(*src).clone()} alt={alt} />
+    }
+}
+
+pub trait HackTraitAvatar {
+    fn avatar(&self) -> Html;
+}
+
+impl HackTraitAvatar for UserDesc {
+    fn avatar(&self) -> Html {
+        let alt = format!("Avatar of {}", self.as_username());
+        html! { <Avatar uid={self.uid} picture={self.picture.clone()} alt={alt} /> }
+    }
+}
@@ -3,14 +3,20 @@ use crate::{prelude::*, slider::width};
 #[derive(Clone, Properties)]
 pub struct CalendarProps {
     pub agenda_link: Scope<Agenda>,
+    /// Incremented by the agenda to open the calendar
+    #[prop_or_default]
+    pub open_requests: usize,
     pub day: u32,
     pub month: u32,
     pub year: i32,
+    /// Changes when another tab changed the settings, which are read when rendering.
+    #[prop_or_default]
+    pub settings_generation: usize,
 }
 
 impl PartialEq for CalendarProps {
     fn eq(&self, other: &Self) -> bool {
-        self.day == other.day && self.month == other.month && self.year == other.year
+        self.day == other.day && self.month == other.month && self.year == other.year && self.open_requests == other.open_requests && self.settings_generation == other.settings_generation
     }
 }
 
@@ -52,6 +58,13 @@ impl Component for Calendar {
         }
     }
 
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if ctx.props().open_requests != old_props.open_requests && self.folded {
+            ctx.link().send_message(Msg::TriggerFold);
+        }
+        true
+    }
+
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::Next if !self.folded => match SETTINGS.calendar() {
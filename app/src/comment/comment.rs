@@ -1,17 +1,35 @@
 use crate::prelude::*;
 
+/// Replies deeper than this are only shown after clicking "continue thread".
+const MAX_THREAD_DEPTH: usize = 5;
+/// Number of replies displayed at once under a comment.
+const REPLIES_PAGE_SIZE: usize = 3;
+
 #[derive(Properties, Clone)]
 pub struct CommentProps {
     pub eid: Rc<String>,
     pub comments: Rc<Vec<Comment>>,
     pub cid: u64,
+    pub depth: usize,
+    /// Comments that only exist locally while they are being sent.
+    pub local_cids: Rc<Vec<u64>>,
+    /// Users that can be mentioned in replies.
+    pub mention_candidates: Rc<Vec<UserDesc>>,
+    /// Next session of the course, that homeworks can target.
+    pub next_session: Rc<Option<RawEvent>>,
     pub user_info: Rc<Option<UserInfo>>,
+    /// Drafts given back after their action failed, whose editor must be opened again.
+    #[prop_or_default]
+    pub restored_drafts: Rc<Vec<String>>,
+    /// Changes when another tab changed the settings, which are read when rendering.
+    #[prop_or_default]
+    pub settings_generation: usize,
     pub popup_link: PopupLink,
 }
 
 impl PartialEq for CommentProps {
     fn eq(&self, other: &Self) -> bool {
-        self.eid == other.eid && self.comments == other.comments && self.cid == other.cid && self.user_info == other.user_info
+        self.eid == other.eid && self.comments == other.comments && self.cid == other.cid && self.depth == other.depth && self.local_cids == other.local_cids && self.mention_candidates == other.mention_candidates && self.next_session == other.next_session && self.user_info == other.user_info && self.restored_drafts == other.restored_drafts && self.settings_generation == other.settings_generation
     }
 }
 
@@ -23,13 +41,35 @@ pub enum CommentMsg {
     StartEdit,
     SubmitEdit,
     Report,
+    SubmitReport,
     Delete,
+    ToggleReplyPreview,
+    ToggleEditPreview,
+    ToggleCollapse,
+    ShowMoreReplies,
+    ToggleRevisions,
+    ToggleHomeworkForm,
+    SetHomework(Option<Homework>),
+    SubmitHomeworkDate,
+}
+
+/// Reads the content of a textarea, identified by its id.
+pub fn textarea_value(id: &str) -> String {
+    let el = window().doc().get_element_by_id(id).unwrap();
+    let textarea = el.dyn_into::<web_sys::HtmlTextAreaElement>().unwrap();
+    textarea.value()
 }
 
 pub struct CommentComp {
-    vote: i8,
     replying: bool,
     editing: bool,
+    reply_preview: Option<String>,
+    edit_preview: Option<String>,
+    collapsed: bool,
+    shown_replies: usize,
+    reporting: bool,
+    showing_revisions: bool,
+    homework_form: bool,
 }
 
 impl Component for CommentComp {
@@ -37,94 +77,89 @@ impl Component for CommentComp {
     type Properties = CommentProps;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let comment = ctx.props().comments.iter().find(|comment| comment.cid == ctx.props().cid).unwrap();
         Self {
-            vote: comment.vote,
             replying: false,
-            editing: false,
+            // Resume an edit that was interrupted
+            editing: Storage::get(&edit_draft_key(&ctx.props().eid, ctx.props().cid)).is_some(),
+            reply_preview: None,
+            edit_preview: None,
+            collapsed: false,
+            shown_replies: REPLIES_PAGE_SIZE,
+            reporting: false,
+            showing_revisions: false,
+            homework_form: false,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        let (eid, cid) = (&ctx.props().eid, ctx.props().cid);
+        if ctx.props().restored_drafts.contains(&draft_key(eid, Some(cid))) {
+            self.replying = true;
+        }
+        let edit_draft_key = edit_draft_key(eid, cid);
+        if ctx.props().restored_drafts.contains(&edit_draft_key) {
+            self.editing = true;
+            if let (Some(textarea), Some(draft)) = (window().doc().get_element_by_id(&format!("comment-textarea-{cid}")), Storage::get(&edit_draft_key)) {
+                textarea.unchecked_into::<web_sys::HtmlTextAreaElement>().set_value(&draft);
+            }
         }
+        true
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            CommentMsg::Upvote => {
-                if self.vote == 1 {
-                    self.vote = 0;
-                } else {
-                    self.vote = 1;
-                }
-                let vote = self.vote;
-                let eid = ctx.props().eid.to_string();
-                let cid = ctx.props().cid;
-                spawn_local(async move {
-                    if let Err(e) = update_vote(eid, vote, cid).await {
-                        alert(e.to_string());
-                    }
-                });
-            }
-            CommentMsg::Downvote => {
-                if self.vote == -1 {
-                    self.vote = 0;
-                } else {
-                    self.vote = -1;
-                }
-                let vote = self.vote;
-                let eid = ctx.props().eid.to_string();
-                let cid = ctx.props().cid;
-                spawn_local(async move {
-                    if let Err(e) = update_vote(eid, vote, cid).await {
-                        alert(e.to_string());
-                    }
-                });
+            CommentMsg::Upvote | CommentMsg::Downvote => {
+                let Some(comment) = ctx.props().comments.iter().find(|comment| comment.cid == ctx.props().cid) else { return false };
+                let vote = match (msg, comment.vote) {
+                    (CommentMsg::Upvote, 1) | (CommentMsg::Downvote, -1) => 0,
+                    (CommentMsg::Upvote, _) => 1,
+                    _ => -1,
+                };
+                ctx.props().popup_link.send_message(PopupMsg::Action(CommentAction::Vote { cid: ctx.props().cid, vote }));
+                return false;
             }
             CommentMsg::StartReply => {
                 self.replying = !self.replying;
+                self.reply_preview = None;
             }
             CommentMsg::StartEdit => {
                 self.editing = !self.editing;
+                self.edit_preview = None;
+                let edit_draft_key = edit_draft_key(&ctx.props().eid, ctx.props().cid);
                 if self.editing {
                     let el = window().doc().get_element_by_id(&format!("comment-textarea-{}", ctx.props().cid)).unwrap();
                     let textarea = el.dyn_into::<web_sys::HtmlTextAreaElement>().unwrap();
-                    textarea.set_value(&ctx.props().comments.iter().find(|comment| comment.cid == ctx.props().cid).unwrap().content);
+                    let content = Storage::get(&edit_draft_key).unwrap_or_else(|| ctx.props().comments.iter().find(|comment| comment.cid == ctx.props().cid).unwrap().content.clone());
+                    textarea.set_value(&content);
+                } else {
+                    clear_draft(&edit_draft_key);
                 }
             }
             CommentMsg::SubmitReply => {
-                let el = window().doc().get_element_by_id(&format!("reply-textarea-{}", ctx.props().cid)).unwrap();
-                let textarea = el.dyn_into::<web_sys::HtmlTextAreaElement>().unwrap();
-                let content = textarea.value();
+                let content = textarea_value(&format!("reply-textarea-{}", ctx.props().cid));
+
+                if content.trim().is_empty() {
+                    return false;
+                }
 
                 self.replying = false;
+                self.reply_preview = None;
 
-                let eid = ctx.props().eid.to_string();
-                let parent_id = ctx.props().cid;
-                let popup_link = ctx.props().popup_link.clone();
-                spawn_local(async move {
-                    if let Err(e) = update_comment(eid, None, Some(parent_id), content).await {
-                        alert(e.to_string());
-                    }
-                    popup_link.send_message(PopupMsg::ReloadComments);
-                });
+                let mentions = resolve_mentions(&content, &ctx.props().mention_candidates);
+                ctx.props().popup_link.send_message(PopupMsg::Action(CommentAction::Create { cid: local_cid(), parent: Some(ctx.props().cid), content, mentions }));
             }
             CommentMsg::SubmitEdit => {
-                let el = window().doc().get_element_by_id(&format!("comment-textarea-{}", ctx.props().cid)).unwrap();
-                let textarea = el.dyn_into::<web_sys::HtmlTextAreaElement>().unwrap();
-                let content = textarea.value();
-                
+                let content = textarea_value(&format!("comment-textarea-{}", ctx.props().cid));
+
                 if content.is_empty() {
                     return false;
                 }
 
                 self.editing = false;
+                self.edit_preview = None;
 
-                let eid = ctx.props().eid.to_string();
-                let cid = ctx.props().cid;
-                let popup_link = ctx.props().popup_link.clone();
-                spawn_local(async move {
-                    if let Err(e) = update_comment(eid, Some(cid), None, content).await {
-                        alert(e.to_string());
-                    }
-                    popup_link.send_message(PopupMsg::ReloadComments);
-                });
+                let mentions = resolve_mentions(&content, &ctx.props().mention_candidates);
+                ctx.props().popup_link.send_message(PopupMsg::Action(CommentAction::Edit { cid: ctx.props().cid, content, mentions }));
             }
             CommentMsg::Report => {
                 let author_uid = ctx.props().comments.iter().find(|comment| comment.cid == ctx.props().cid).map(|c| c.author.uid).unwrap_or_default();
@@ -132,29 +167,77 @@ impl Component for CommentComp {
                     ctx.props().popup_link.send_message(PopupMsg::AppMsg(AppMsg::SetPage(Page::Rick)));
                     return false;
                 }
-                let url_to_open = format!("mailto:genda@dera.page?subject=Report%20de%20commentaire%20({})", ctx.props().cid);
-                web_sys::window().unwrap().open_with_url(&url_to_open).unwrap();
+                self.reporting = !self.reporting;
             }
-            CommentMsg::Delete => {
-                let eid = ctx.props().eid.to_string();
+            CommentMsg::SubmitReport => {
                 let cid = ctx.props().cid;
-                let popup_link = ctx.props().popup_link.clone();
+                let doc = window().doc();
+                let reason = doc.get_element_by_id(&format!("report-reason-{cid}"))
+                    .and_then(|el| el.dyn_into::<web_sys::HtmlSelectElement>().ok())
+                    .and_then(|select| select.value().parse::<usize>().ok())
+                    .and_then(|i| ReportReason::ALL.get(i).copied())
+                    .unwrap_or(ReportReason::Other);
+                let note = textarea_value(&format!("report-note-{cid}"));
+                let note = if note.trim().is_empty() { None } else { Some(note) };
+
+                self.reporting = false;
+
+                let report = ReportRequest { eid: ctx.props().eid.to_string(), cid, reason, note };
                 spawn_local(async move {
-                    match api_delete(format!("comment?eid={eid}&cid={cid}")).await {
-                        Ok(()) => (),
-                        Err(e) => alert(e.to_string()), 
+                    match report_comment(report).await {
+                        Ok(()) => alert("Merci, le commentaire a été signalé aux modérateurs."),
+                        Err(e) => e.report(Some("Le signalement n'a pas pu être envoyé")),
                     }
-                    popup_link.send_message(PopupMsg::ReloadComments);
                 });
             }
+            CommentMsg::Delete => {
+                ctx.props().popup_link.send_message(PopupMsg::Action(CommentAction::Delete { cid: ctx.props().cid }));
+                return false;
+            }
+            CommentMsg::ToggleReplyPreview => {
+                self.reply_preview = match self.reply_preview {
+                    Some(_) => None,
+                    None => Some(textarea_value(&format!("reply-textarea-{}", ctx.props().cid))),
+                };
+            }
+            CommentMsg::ToggleCollapse => {
+                self.collapsed = !self.collapsed;
+            }
+            CommentMsg::ShowMoreReplies => {
+                self.shown_replies += REPLIES_PAGE_SIZE;
+            }
+            CommentMsg::ToggleRevisions => {
+                self.showing_revisions = !self.showing_revisions;
+            }
+            CommentMsg::ToggleHomeworkForm => {
+                self.homework_form = !self.homework_form;
+            }
+            CommentMsg::SetHomework(homework) => {
+                self.homework_form = false;
+                ctx.props().popup_link.send_message(PopupMsg::Action(CommentAction::SetHomework { cid: ctx.props().cid, homework }));
+            }
+            CommentMsg::SubmitHomeworkDate => {
+                let el = window().doc().get_element_by_id(&format!("homework-date-{}", ctx.props().cid)).unwrap();
+                let value = el.dyn_into::<HtmlInputElement>().unwrap().value();
+                let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") else {
+                    alert("Veuillez choisir une date.");
+                    return false;
+                };
+                // Due at the end of the day
+                let Some(due) = Paris.from_local_datetime(&date.and_hms_opt(23, 59, 59).unwrap()).earliest() else { return false };
+                ctx.link().send_message(CommentMsg::SetHomework(Some(Homework { due: due.timestamp(), due_eid: None })));
+                return false;
+            }
+            CommentMsg::ToggleEditPreview => {
+                self.edit_preview = match self.edit_preview {
+                    Some(_) => None,
+                    None => Some(textarea_value(&format!("comment-textarea-{}", ctx.props().cid))),
+                };
+            }
         }
         true
     }
 
-    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
-        self.vote = ctx.props().comments.iter().find(|comment| comment.cid == ctx.props().cid).unwrap().vote;
-        true
-    }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let comments = Rc::clone(&ctx.props().comments);
@@ -164,13 +247,18 @@ impl Component for CommentComp {
         };
         
         let cid = comment.cid;
-        let author_avatar = format!("https://api.dicebear.com/5.x/identicon/svg?seed={}", comment.author.uid);
+        let author_avatar = html! { <Avatar class="comment-author-avatar" uid={comment.author.uid} picture={comment.author.picture.clone()} /> };
         let author_name = comment.author.get_username();
         let time_diff = now() - comment.creation_ts;
         let time = format_time_diff(time_diff);
         let modified = comment.last_edited_ts > comment.creation_ts;
-        let score = comment.upvotes as i64 - comment.downvotes as i64 - comment.vote as i64 + self.vote as i64;
-        let upvote_class = match self.vote {
+        let onclick_revisions = ctx.link().callback(|_| CommentMsg::ToggleRevisions);
+        let opt_revisions = match self.showing_revisions && modified {
+            true => Some(html! { <CommentRevisions eid={ctx.props().eid.to_string()} {cid} /> }),
+            false => None,
+        };
+        let score = comment.score();
+        let upvote_class = match comment.vote {
             1 => "comment-upvoted",
             -1 => "comment-downvoted",
             _ => "comment-not-voted",
@@ -188,26 +276,99 @@ impl Component for CommentComp {
         let onclick_edit_submit = ctx.link().callback(|_| CommentMsg::SubmitEdit);
         let onclick_report = ctx.link().callback(|_| CommentMsg::Report);
         let onclick_delete = ctx.link().callback(move |_| CommentMsg::Delete);
+        let onclick_reply_preview = ctx.link().callback(|_| CommentMsg::ToggleReplyPreview);
+        let onclick_edit_preview = ctx.link().callback(|_| CommentMsg::ToggleEditPreview);
 
-        let children = comments.iter().filter(|child| child.parent == Some(comment.cid)).map(|child| {
-            html! {
-                <CommentComp
-                    eid={Rc::clone(&ctx.props().eid)}
-                    comments={Rc::clone(&comments)}
-                    cid={child.cid}
-                    user_info={Rc::clone(&ctx.props().user_info)}
-                    popup_link={ctx.props().popup_link.clone()} />
-            }
-        }).collect::<Html>();
+        let candidates = &ctx.props().mention_candidates;
+        let render_preview = |content: &str| render_markdown_with_mentions(content, &resolve_mentions(content, candidates));
+        let opt_reply_preview = self.reply_preview.as_deref().map(render_preview);
+        let reply_editor_class = if opt_reply_preview.is_some() { "comment-editor previewing" } else { "comment-editor" };
+        let reply_preview_label = if opt_reply_preview.is_some() { "Écrire" } else { "Aperçu" };
+        let opt_edit_preview = self.edit_preview.as_deref().map(render_preview);
+        let edit_editor_class = if opt_edit_preview.is_some() { "comment-editor previewing" } else { "comment-editor" };
+        let edit_preview_label = if opt_edit_preview.is_some() { "Écrire" } else { "Aperçu" };
+
+        // Replies are hidden when collapsed, replaced by a link when too deep, and paginated otherwise
+        let collapsed = self.collapsed;
+        let reply_count = count_replies(&comments, cid);
+        let has_replies = reply_count > 0;
+        let collapse_label = if collapsed { "[+]" } else { "[−]" };
+        let onclick_collapse = ctx.link().callback(|_| CommentMsg::ToggleCollapse);
+        let direct_children = comments.iter().filter(|child| child.parent == Some(comment.cid)).collect::<Vec<_>>();
+        let too_deep = ctx.props().depth + 1 >= MAX_THREAD_DEPTH && !direct_children.is_empty();
+        let onclick_continue_thread = ctx.props().popup_link.callback(move |_| PopupMsg::FocusThread(Some(cid)));
+        let hidden_replies = match too_deep {
+            true => 0,
+            false => direct_children.len().saturating_sub(self.shown_replies),
+        };
+        let has_hidden_replies = hidden_replies > 0;
+        let onclick_more_replies = ctx.link().callback(|_| CommentMsg::ShowMoreReplies);
+        let children = match too_deep {
+            true => html!(),
+            false => direct_children.iter().take(self.shown_replies).map(|child| {
+                html! {
+                    <CommentComp
+                        eid={Rc::clone(&ctx.props().eid)}
+                        comments={Rc::clone(&comments)}
+                        cid={child.cid}
+                        depth={ctx.props().depth + 1}
+                        local_cids={Rc::clone(&ctx.props().local_cids)}
+                        mention_candidates={Rc::clone(&ctx.props().mention_candidates)}
+                        next_session={Rc::clone(&ctx.props().next_session)}
+                        user_info={Rc::clone(&ctx.props().user_info)}
+                        restored_drafts={Rc::clone(&ctx.props().restored_drafts)}
+                        settings_generation={ctx.props().settings_generation}
+                        popup_link={ctx.props().popup_link.clone()} />
+                }
+            }).collect::<Html>(),
+        };
 
         let self_uid = ctx.props().user_info.as_ref().as_ref().map(|u| u.uid).unwrap_or(0);
         let is_author = comment.author.uid == self_uid;
-        let self_avatar = format!("https://api.dicebear.com/5.x/identicon/svg?seed={self_uid}");
+        let is_contributor = ctx.props().user_info.as_ref().as_ref().map(|u| UserDesc::new(u.uid, u.email.0.clone()).is_contributor()).unwrap_or(false);
+
+        // Homework
+        let can_set_homework = is_author || is_contributor;
+        let homework_form = self.homework_form;
+        let is_homework = comment.homework.is_some();
+        let opt_homework_label = comment.homework.as_ref().and_then(|homework| Paris.timestamp_opt(homework.due, 0).single()).map(|due| {
+            format!("Devoir pour le {}", due.format("%d/%m/%Y"))
+        });
+        let homework_button_label = if is_homework { "Modifier le devoir" } else { "Devoir" };
+        let onclick_homework = ctx.link().callback(|_| CommentMsg::ToggleHomeworkForm);
+        let onclick_homework_cancel = onclick_homework.clone();
+        let onclick_homework_date = ctx.link().callback(|_| CommentMsg::SubmitHomeworkDate);
+        let onclick_homework_remove = ctx.link().callback(|_| CommentMsg::SetHomework(None));
+        let opt_next_session_label = ctx.props().next_session.as_ref().as_ref().and_then(|event| {
+            let start = Paris.timestamp_opt(event.start_unixtime as i64, 0).single()?;
+            Some(format!("Pour la prochaine séance ({})", start.format("%d/%m")))
+        });
+        let onclick_homework_next_session = {
+            let next_session = Rc::clone(&ctx.props().next_session);
+            ctx.link().batch_callback(move |_| {
+                next_session.as_ref().as_ref().map(|event| CommentMsg::SetHomework(Some(Homework { due: event.start_unixtime as i64, due_eid: Some(event.eid.clone()) })))
+            })
+        };
+        let local = ctx.props().local_cids.contains(&cid);
+        let hidden = comment.hidden;
+
+        let reporting = self.reporting;
+        let report_reason_value_iter = (0..ReportReason::ALL.len()).map(|i| i.to_string());
+        let report_reason_label_iter = ReportReason::ALL.iter().map(|reason| match SETTINGS.lang() {
+            Lang::French => reason.to_message().1,
+            Lang::English => reason.to_message().0,
+        });
+        let onclick_report_cancel = onclick_report.clone();
+        let onclick_report_submit = ctx.link().callback(|_| CommentMsg::SubmitReport);
+        let edit_textarea = html! { <MentionTextarea id={format!("comment-textarea-{cid}")} candidates={Rc::clone(candidates)} draft_key={edit_draft_key(&ctx.props().eid, cid)} /> };
+        let reply_textarea = html! { <MentionTextarea id={format!("reply-textarea-{cid}")} placeholder="Ecrivez une réponse" candidates={Rc::clone(candidates)} draft_key={draft_key(&ctx.props().eid, Some(cid))} /> };
+        let self_picture = ctx.props().user_info.as_ref().as_ref().and_then(|u| u.picture.clone());
+        let self_avatar = html! { <Avatar class="comment-author-avatar" uid={self_uid} picture={self_picture} /> };
         let self_name = ctx.props().user_info.as_ref().as_ref().map(|u| u.email.0.split('@').next().unwrap().to_string()).unwrap_or(String::from("inconnu"));
 
         template_html!(
             "src/comment/comment.html",
-            content = { comment.content.clone() },
+            content = { render_markdown_with_mentions(&comment.content, &comment.mentions) },
             ...
         )
     }
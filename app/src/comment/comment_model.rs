@@ -0,0 +1,227 @@
+use crate::prelude::*;
+
+/// A change made by the user to the comments of an event.
+#[derive(Clone, Debug)]
+pub enum CommentAction {
+    Vote { cid: u64, vote: i8 },
+    Create { cid: u64, parent: Option<u64>, content: String, mentions: Vec<UserDesc> },
+    Edit { cid: u64, content: String, mentions: Vec<UserDesc> },
+    Delete { cid: u64 },
+    SetHomework { cid: u64, homework: Option<Homework> },
+}
+
+impl CommentAction {
+    fn apply(&self, comments: &mut Vec<Comment>, author: &Option<UserDesc>) {
+        match self {
+            CommentAction::Vote { cid, vote } => {
+                let Some(comment) = comments.iter_mut().find(|c| c.cid == *cid) else { return };
+                match comment.vote {
+                    1 => comment.upvotes = comment.upvotes.saturating_sub(1),
+                    -1 => comment.downvotes = comment.downvotes.saturating_sub(1),
+                    _ => (),
+                }
+                match vote {
+                    1 => comment.upvotes += 1,
+                    -1 => comment.downvotes += 1,
+                    _ => (),
+                }
+                comment.vote = *vote;
+            }
+            CommentAction::Create { cid, parent, content, mentions } => {
+                let Some(author) = author.clone() else { return };
+                let now = now();
+                comments.push(Comment {
+                    cid: *cid,
+                    parent: *parent,
+                    author,
+                    content: content.clone(),
+                    creation_ts: now,
+                    last_edited_ts: now,
+                    upvotes: 0,
+                    downvotes: 0,
+                    vote: 0,
+                    hidden: false,
+                    mentions: mentions.clone(),
+                    homework: None,
+                });
+            }
+            CommentAction::Edit { cid, content, mentions } => {
+                let Some(comment) = comments.iter_mut().find(|c| c.cid == *cid) else { return };
+                comment.content = content.clone();
+                comment.mentions = mentions.clone();
+                comment.last_edited_ts = now();
+            }
+            CommentAction::Delete { cid } => comments.retain(|c| c.cid != *cid),
+            CommentAction::SetHomework { cid, homework } => {
+                let Some(comment) = comments.iter_mut().find(|c| c.cid == *cid) else { return };
+                comment.homework = homework.clone();
+            }
+        }
+    }
+
+    /// Sends the action to the server.
+    pub async fn send(&self, eid: String) -> Result<Delivery, ApiError> {
+        match self {
+            CommentAction::Vote { cid, vote } => update_vote(eid, *vote, *cid).await,
+            // The key is derived from the local cid, so that deleting the comment while its creation is queued cancels it
+            CommentAction::Create { cid, parent, content, mentions } => update_comment(eid, None, *parent, content.clone(), mentions.iter().map(|u| u.uid).collect(), Some(idempotency_key(*cid))).await,
+            CommentAction::Edit { cid, content, mentions } => update_comment(eid, Some(*cid), None, content.clone(), mentions.iter().map(|u| u.uid).collect(), None).await,
+            CommentAction::Delete { cid } => Outbox::submit(Mutation::DeleteComment(DeleteComment { eid, cid: *cid })).await,
+            CommentAction::SetHomework { cid, homework } => set_homework(HomeworkRequest { eid, cid: *cid, homework: homework.clone() }).await,
+        }
+    }
+
+    /// The textarea the text of the action was typed in, and the key of its draft, along with the text.
+    pub fn draft(&self, eid: &str) -> Option<(String, String, &String)> {
+        match self {
+            CommentAction::Create { parent: None, content, .. } => Some((String::from("comment-textarea-top"), draft_key(eid, None), content)),
+            CommentAction::Create { parent: Some(parent), content, .. } => Some((format!("reply-textarea-{parent}"), draft_key(eid, Some(*parent)), content)),
+            CommentAction::Edit { cid, content, .. } => Some((format!("comment-textarea-{cid}"), edit_draft_key(eid, *cid), content)),
+            _ => None,
+        }
+    }
+
+    /// Message displayed to the user, before the error, when the action is rolled back.
+    pub fn failure_message(&self) -> &'static str {
+        match self {
+            CommentAction::Vote { .. } => "Votre vote n'a pas pu être enregistré",
+            CommentAction::Create { .. } => "Votre commentaire n'a pas pu être publié",
+            CommentAction::Edit { .. } => "Votre modification n'a pas pu être enregistrée",
+            CommentAction::Delete { .. } => "Le commentaire n'a pas pu être supprimé",
+            CommentAction::SetHomework { .. } => "Le devoir n'a pas pu être enregistré",
+        }
+    }
+}
+
+/// Where an action stands between the user and the server.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ActionState {
+    Sending,
+    /// Waiting in the outbox, so the lists received meanwhile don't include it.
+    Queued,
+    Confirmed,
+}
+
+/// Local copy of the comments of an event.
+///
+/// Actions are displayed immediately, on top of the last list received from the server.
+/// They are removed once the server confirmed or rejected them, so that a failed action is rolled back without refetching anything.
+pub struct CommentModel {
+    confirmed: Vec<Comment>,
+    /// Actions sent to the server, with their id and state.
+    /// Confirmed creations are kept until the next list is received since we don't know their real cid.
+    /// Queued actions are kept until the outbox is empty.
+    pending: Vec<(usize, CommentAction, ActionState)>,
+    next_action_id: usize,
+    author: Option<UserDesc>,
+    sort: CommentSort,
+    displayed: Rc<Vec<Comment>>,
+}
+
+impl CommentModel {
+    pub fn new(comments: Vec<Comment>, author: Option<UserDesc>, sort: CommentSort) -> Self {
+        let mut model = CommentModel {
+            confirmed: comments,
+            pending: Vec::new(),
+            next_action_id: 0,
+            author,
+            sort,
+            displayed: Rc::new(Vec::new()),
+        };
+        model.refresh();
+        model
+    }
+
+    fn refresh(&mut self) {
+        let mut displayed = self.confirmed.clone();
+        for (_, action, _) in &self.pending {
+            action.apply(&mut displayed, &self.author);
+        }
+        sort_comments(&mut displayed, self.sort);
+        self.displayed = Rc::new(displayed);
+    }
+
+    /// The comments to display, including the ones that are still being sent.
+    pub fn comments(&self) -> Rc<Vec<Comment>> {
+        Rc::clone(&self.displayed)
+    }
+
+    pub fn set_sort(&mut self, sort: CommentSort) {
+        self.sort = sort;
+        self.refresh();
+    }
+
+    /// Replaces the list with the one from the server, keeping the actions that haven't been confirmed yet.
+    pub fn reconcile(&mut self, comments: Vec<Comment>) {
+        self.confirmed = comments;
+        self.pending.retain(|(_, _, state)| *state != ActionState::Confirmed);
+        self.refresh();
+    }
+
+    /// Applies an action locally and returns its id, to be used once the server replied.
+    pub fn apply(&mut self, action: CommentAction) -> usize {
+        let id = self.next_action_id;
+        self.next_action_id += 1;
+        self.pending.push((id, action, ActionState::Sending));
+        self.refresh();
+        id
+    }
+
+    /// The action with this id, if it hasn't been confirmed nor rolled back yet.
+    pub fn action(&self, id: usize) -> Option<&CommentAction> {
+        self.pending.iter().find(|(i, _, state)| *i == id && *state != ActionState::Confirmed).map(|(_, action, _)| action)
+    }
+
+    /// Returns whether the comment list should be fetched again.
+    pub fn confirm(&mut self, id: usize) -> bool {
+        let Some(idx) = self.pending.iter().position(|(i, _, _)| *i == id) else { return false };
+        if matches!(self.pending[idx].1, CommentAction::Create { .. }) {
+            self.pending[idx].2 = ActionState::Confirmed;
+            return true;
+        }
+        let (_, action, _) = self.pending.remove(idx);
+        action.apply(&mut self.confirmed, &self.author);
+        self.refresh();
+        false
+    }
+
+    /// Marks an action as waiting in the outbox, where it stays displayed until [CommentModel::flushed] is called.
+    pub fn queue(&mut self, id: usize) {
+        if let Some((_, _, state)) = self.pending.iter_mut().find(|(i, _, _)| *i == id) {
+            *state = ActionState::Queued;
+        }
+    }
+
+    /// To be called once the outbox is empty, when the server has received the queued actions.
+    /// Returns whether the comment list should be fetched again.
+    pub fn flushed(&mut self) -> bool {
+        let mut any = false;
+        for (_, _, state) in &mut self.pending {
+            if *state == ActionState::Queued {
+                *state = ActionState::Confirmed;
+                any = true;
+            }
+        }
+        any
+    }
+
+    /// Cancels an action that the server rejected, and returns it.
+    pub fn rollback(&mut self, id: usize) -> Option<CommentAction> {
+        let idx = self.pending.iter().position(|(i, _, _)| *i == id)?;
+        let (_, action, _) = self.pending.remove(idx);
+        self.refresh();
+        Some(action)
+    }
+
+    /// Returns whether the comment has only been created locally.
+    pub fn is_local(&self, cid: u64) -> bool {
+        self.pending.iter().any(|(_, action, _)| matches!(action, CommentAction::Create { cid: c, .. } if *c == cid))
+    }
+}
+
+/// Random identifier for a comment that hasn't been sent yet.
+pub fn local_cid() -> u64 {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).unwrap();
+    u64::from_le_bytes(bytes)
+}
@@ -0,0 +1,63 @@
+use crate::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct CommentRevisionsProps {
+    pub eid: String,
+    pub cid: u64,
+}
+
+fn render_diff(chunks: &[DiffChunk]) -> Html {
+    chunks.iter().map(|chunk| match chunk {
+        DiffChunk::Same(text) => html! { <span>{text}</span> },
+        DiffChunk::Added(text) => html! { <ins class="diff-added">{text}</ins> },
+        DiffChunk::Removed(text) => html! { <del class="diff-removed">{text}</del> },
+    }).collect()
+}
+
+/// Lists the successive versions of a comment, each one compared to the previous.
+#[function_component(CommentRevisions)]
+pub fn comment_revisions(props: &CommentRevisionsProps) -> Html {
+    let revisions = use_state(|| None::<Vec<CommentRevision>>);
+
+    {
+        let revisions = revisions.clone();
+        use_effect_with((props.eid.clone(), props.cid), move |(eid, cid)| {
+            let (eid, cid) = (eid.clone(), *cid);
+            spawn_local(async move {
+                match get_comment_revisions(eid, cid).await {
+                    Ok(loaded) => revisions.set(Some(loaded)),
+                    Err(e) => {
+                        e.report(Some("Impossible de charger l'historique"));
+                        revisions.set(Some(Vec::new()));
+                    }
+                }
+            });
+        });
+    }
+
+    let Some(revisions) = revisions.as_ref() else {
+        return html! { <div class="comment-revisions"><span class="comment-time">{"Chargement de l'historique..."}</span></div> };
+    };
+    if revisions.is_empty() {
+        return html! { <div class="comment-revisions"><span class="comment-time">{"Aucune modification enregistrée."}</span></div> };
+    }
+
+    // Most recent first
+    let items = revisions.iter().enumerate().rev().map(|(i, revision)| {
+        let label = if i == 0 { "Version originale" } else { "Modifié" };
+        let content = match i {
+            0 => html! { <span>{&revision.content}</span> },
+            _ => render_diff(&word_diff(&revisions[i - 1].content, &revision.content)),
+        };
+        html! {
+            <li class="comment-revision">
+                <span class="comment-time">{label}{" il y a "}{format_time_diff(now() - revision.ts)}</span>
+                <p class="comment-revision-content">{content}</p>
+            </li>
+        }
+    }).collect::<Html>();
+
+    html! {
+        <ul class="comment-revisions">{items}</ul>
+    }
+}
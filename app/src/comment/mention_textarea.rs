@@ -0,0 +1,180 @@
+use crate::prelude::*;
+use web_sys::HtmlTextAreaElement;
+
+/// Number of friends suggested at once.
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Properties, PartialEq)]
+pub struct MentionTextareaProps {
+    pub id: String,
+    #[prop_or_default]
+    pub placeholder: Option<String>,
+    /// Users that can be mentioned.
+    pub candidates: Rc<Vec<UserDesc>>,
+    /// Storage key under which the text is kept until it's sent, see [draft_key].
+    #[prop_or_default]
+    pub draft_key: Option<String>,
+}
+
+/// Key under which the text written in reply to an event or a comment is kept,
+/// so that it survives closing the event, reloading the app or logging in again.
+pub fn draft_key(eid: &str, parent: Option<u64>) -> String {
+    match parent {
+        Some(parent) => format!("draft_{eid}_{parent}"),
+        None => format!("draft_{eid}"),
+    }
+}
+
+/// Storage key of the new content of a comment being edited.
+pub fn edit_draft_key(eid: &str, cid: u64) -> String {
+    format!("draft_{eid}_edit_{cid}")
+}
+
+/// Forgets a draft once it has been sent.
+pub fn clear_draft(key: &str) {
+    Storage::remove(key);
+}
+
+/// Textarea positions are counted in UTF-16 code units on the JS side.
+fn utf16_to_byte_index(text: &str, index: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= index {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn caret(textarea: &HtmlTextAreaElement) -> usize {
+    let value = textarea.value();
+    let caret = textarea.selection_start().ok().flatten().unwrap_or(0) as usize;
+    utf16_to_byte_index(&value, caret)
+}
+
+fn suggestions_for(textarea: &HtmlTextAreaElement, candidates: &[UserDesc]) -> Vec<UserDesc> {
+    let value = textarea.value();
+    let Some((_, prefix)) = mention_query(&value, caret(textarea)) else { return Vec::new() };
+    let prefix = prefix.to_lowercase();
+    candidates.iter().filter(|c| c.as_username().to_lowercase().starts_with(&prefix)).take(MAX_SUGGESTIONS).cloned().collect()
+}
+
+/// Replaces the mention being typed by the full username.
+fn insert_mention(textarea: &HtmlTextAreaElement, user: &UserDesc) {
+    let value = textarea.value();
+    let caret = caret(textarea);
+    let Some((start, _)) = mention_query(&value, caret) else { return };
+    let inserted = format!("@{} ", user.as_username());
+    let new_value = format!("{}{inserted}{}", &value[..start], &value[caret..]);
+    let new_caret = new_value[..start + inserted.len()].encode_utf16().count() as u32;
+    textarea.set_value(&new_value);
+    let _ = textarea.set_selection_range(new_caret, new_caret);
+    let _ = textarea.focus();
+}
+
+/// A comment textarea suggesting friends when typing `@`.
+#[function_component(MentionTextarea)]
+pub fn mention_textarea(props: &MentionTextareaProps) -> Html {
+    let suggestions = use_state(Vec::<UserDesc>::new);
+    let selected = use_state(|| 0usize);
+    let textarea_ref = use_node_ref();
+
+    // Restore the draft
+    {
+        let textarea_ref = textarea_ref.clone();
+        use_effect_with(props.draft_key.clone(), move |draft_key| {
+            let draft = draft_key.as_deref().and_then(Storage::get);
+            if let (Some(textarea), Some(draft)) = (textarea_ref.cast::<HtmlTextAreaElement>(), draft) {
+                if textarea.value().is_empty() {
+                    textarea.set_value(&draft);
+                }
+            }
+        });
+    }
+
+    let oninput = {
+        let (suggestions, selected, textarea_ref) = (suggestions.clone(), selected.clone(), textarea_ref.clone());
+        let candidates = Rc::clone(&props.candidates);
+        let draft_key = props.draft_key.clone();
+        Callback::from(move |_: InputEvent| {
+            let Some(textarea) = textarea_ref.cast::<HtmlTextAreaElement>() else { return };
+            selected.set(0);
+            suggestions.set(suggestions_for(&textarea, &candidates));
+            if let Some(draft_key) = &draft_key {
+                match textarea.value() {
+                    value if value.trim().is_empty() => clear_draft(draft_key),
+                    value => Storage::set(draft_key, value),
+                }
+            }
+        })
+    };
+
+    let onkeydown = {
+        let (suggestions, selected, textarea_ref) = (suggestions.clone(), selected.clone(), textarea_ref.clone());
+        Callback::from(move |e: KeyboardEvent| {
+            if suggestions.is_empty() {
+                return;
+            }
+            match e.key().as_str() {
+                "ArrowDown" => selected.set((*selected + 1) % suggestions.len()),
+                "ArrowUp" => selected.set((*selected + suggestions.len() - 1) % suggestions.len()),
+                "Enter" | "Tab" => {
+                    if let (Some(textarea), Some(user)) = (textarea_ref.cast::<HtmlTextAreaElement>(), suggestions.get(*selected)) {
+                        insert_mention(&textarea, user);
+                    }
+                    suggestions.set(Vec::new());
+                }
+                "Escape" => suggestions.set(Vec::new()),
+                _ => return,
+            }
+            e.prevent_default();
+        })
+    };
+
+    let onblur = {
+        let suggestions = suggestions.clone();
+        Callback::from(move |_: FocusEvent| suggestions.set(Vec::new()))
+    };
+
+    let items = suggestions.iter().enumerate().map(|(i, user)| {
+        let onmousedown = {
+            let (suggestions, textarea_ref, user) = (suggestions.clone(), textarea_ref.clone(), user.clone());
+            Callback::from(move |e: MouseEvent| {
+                e.prevent_default(); // Keep the focus on the textarea
+                if let Some(textarea) = textarea_ref.cast::<HtmlTextAreaElement>() {
+                    insert_mention(&textarea, &user);
+                }
+                suggestions.set(Vec::new());
+            })
+        };
+        let class = if i == *selected { "mention-suggestion selected" } else { "mention-suggestion" };
+        html! {
+            <li {class} {onmousedown}>
+                {user.avatar()}
+                <span>{"@"}{user.as_username()}</span>
+            </li>
+        }
+    }).collect::<Html>();
+
+    html! {
+        <div class="mention-textarea">
+            <textarea
+                ref={textarea_ref}
+                class="comment-textarea"
+                id={props.id.clone()}
+                placeholder={props.placeholder.clone()}
+                {oninput}
+                {onkeydown}
+                {onblur}></textarea>
+            if !suggestions.is_empty() {
+                <ul class="mention-suggestions">{items}</ul>
+            }
+        </div>
+    }
+}
+
+/// Users that can be mentioned in comments.
+pub fn mention_candidates(friends: &Option<FriendLists>) -> Vec<UserDesc> {
+    friends.as_ref().map(|friends| friends.friends.iter().map(|(friend, _)| friend.clone()).collect()).unwrap_or_default()
+}
@@ -48,13 +48,7 @@ impl HackTraitEventFormat for RawEvent {
         self.location.as_ref().map(|location| {
             match location {
                 Location::Parsed { building, building_area, level, room_number } => {
-                    let building = match building {
-                        Building::Magellan => "Ma",
-                        Building::DumontDurville => "Du",
-                        Building::Bougainville => "Bo",
-                        Building::Darwin => "Da",
-                    };
-                    format!("{} - {} - {} - {}", building, building_area, level, room_number)
+                    format!("{} - {} - {} - {}", building.code(), building_area, level, room_number)
                 }
                 Location::Unparsed(location) => location.clone(),
             }
@@ -68,6 +62,25 @@ impl HackTraitEventFormat for RawEvent {
     }
 }
 
+/// Names of teachers, each opening the schedule of the teacher.
+pub fn teacher_links(teachers: &[String], open: Callback<Page>) -> Html {
+    teachers.iter().enumerate().map(|(i, teacher)| {
+        let page = Page::TeacherAgenda { teacher: teacher.clone(), date: None };
+        let open = open.clone();
+        let onclick = Callback::from(move |e: MouseEvent| {
+            // The name may be displayed in a clickable event
+            e.stop_propagation();
+            open.emit(page.clone());
+        });
+        html! {
+            <>
+                if i > 0 { {", "} }
+                <a class="teacher-link" {onclick}>{ teacher }</a>
+            </>
+        }
+    }).collect()
+}
+
 pub struct EventComp {}
 
 impl Component for EventComp {
@@ -105,11 +118,16 @@ impl Component for EventComp {
         let seen = seen_comment_count >= comment_count;
 
         // Render
-        let eid = ctx.props().event.eid.clone(); // FIXME: what if eid contains slashes and stuff?
+        let eid = ctx.props().event.eid.clone();
         let onclick = ctx.props().agenda_link.callback(move |_| AgendaMsg::AppMsg(Box::new(AppMsg::SetPage(Page::Event { eid: eid.clone() } ))));
+        // Events are focused with Tab and opened with Enter
+        let eid = ctx.props().event.eid.clone();
+        let onkeydown = ctx.props().agenda_link.batch_callback(move |e: KeyboardEvent| {
+            (e.key() == "Enter").then(|| AgendaMsg::AppMsg(Box::new(AppMsg::SetPage(Page::Event { eid: eid.clone() }))))
+        });
         template_html!(
             "src/event/event.html",
-            teachers = { ctx.props().event.teachers.join(", ")},
+            teachers = { teacher_links(&ctx.props().event.teachers, ctx.props().agenda_link.callback(|page| AgendaMsg::AppMsg(Box::new(AppMsg::SetPage(page))))) },
             opt_location = location,
             bg_color = {bg_color.clone()},
             ...
@@ -70,8 +70,8 @@ impl Component for FriendsPage {
                             link2.send_message(FriendsMsg::RequestSuccess);
                             app_link2.send_message(AppMsg::UpdateFriends(new_friends));
                         }
-                        Err(ApiError::Known(e)) => link2.send_message(FriendsMsg::RequestError(e.to_string())),
-                        Err(error) => alert(error.to_string()),
+                        Err(ApiError::Known(e)) if e.kind.recovery() == Recovery::Surface => link2.send_message(FriendsMsg::RequestError(e.message())),
+                        Err(error) => error.handle_api_error(),
                     }
                 });
 
@@ -107,7 +107,7 @@ impl Component for FriendsPage {
                             };
                             app_link2.send_message(AppMsg::UpdateFriends(new_friends));
                         }
-                        Err(error) => alert(error.to_string()),
+                        Err(error) => error.handle_api_error(),
                     }
                 });
 
@@ -135,7 +135,7 @@ impl Component for FriendsPage {
                             };
                             app_link2.send_message(AppMsg::UpdateFriends(new_friends));
                         }
-                        Err(error) => alert(error.to_string()),
+                        Err(error) => error.handle_api_error(),
                     }
                 });
 
@@ -159,7 +159,7 @@ impl Component for FriendsPage {
                             };
                             app_link2.send_message(AppMsg::UpdateFriends(new_friends));
                         }
-                        Err(error) => alert(error.to_string()),
+                        Err(error) => error.handle_api_error(),
                     }
                 });
 
@@ -169,7 +169,7 @@ impl Component for FriendsPage {
                 let target = event.target().unwrap();
                 let el = target.dyn_into::<web_sys::Element>().unwrap();
                 let pseudo = el.get_attribute("data-pseudo").unwrap();
-                ctx.props().app_link.send_message(AppMsg::SetPage(Page::FriendAgenda { pseudo }));
+                ctx.props().app_link.send_message(AppMsg::SetPage(Page::FriendAgenda { pseudo, date: None }));
                 false
             },
             FriendsMsg::Remove => {
@@ -201,7 +201,7 @@ impl Component for FriendsPage {
                             };
                             app_link2.send_message(AppMsg::UpdateFriends(new_friends));
                         }
-                        Err(error) => alert(error.to_string()),
+                        Err(error) => error.handle_api_error(),
                     }
                 });
                 
@@ -219,22 +219,19 @@ impl Component for FriendsPage {
 
         let has_friends = !friends.friends.is_empty();
         let names = friends.friends.iter().map(|friend| friend.0.email.trim_end_matches("@insa-rouen.fr")).collect::<Vec<_>>();
-        let picture_iter = friends.friends.iter().map(|friend| friend.0.profile_url());
-        let alt_iter = names.iter().map(|name| format!("Avatar of {name}"));
+        let avatar_iter = friends.friends.iter().map(|friend| friend.0.avatar());
         let name_iter = names.iter();
         let friend_pseudo_iter = friends.friends.iter().map(|friend| friend.0.email.trim_end_matches("@insa-rouen.fr").to_string());
 
         let has_incoming = !friends.incoming.is_empty();
         let in_names = friends.incoming.iter().map(|req| req.from.0.email.trim_end_matches("@insa-rouen.fr")).collect::<Vec<_>>();
-        let in_picture_iter = friends.incoming.iter().map(|req| req.from.0.profile_url());
-        let in_alt_iter = in_names.iter().map(|name| format!("Avatar of {name}"));
+        let in_avatar_iter = friends.incoming.iter().map(|req| req.from.0.avatar());
         let in_name_iter = in_names.iter();
         let in_uid_iter = friends.incoming.iter().map(|req| req.from.0.uid.to_string());
 
         let has_outgoing = !friends.outgoing.is_empty();
         let out_names = friends.outgoing.iter().map(|friend| friend.to.0.email.trim_end_matches("@insa-rouen.fr")).collect::<Vec<_>>();
-        let out_picture_iter = friends.outgoing.iter().map(|req| req.to.0.profile_url());
-        let out_alt_iter = out_names.iter().map(|name| format!("Avatar of {name}"));
+        let out_avatar_iter = friends.outgoing.iter().map(|req| req.to.0.avatar());
         let out_name_iter = out_names.iter();
         let out_uid_iter = friends.outgoing.iter().map(|req| req.to.0.uid.to_string());
 
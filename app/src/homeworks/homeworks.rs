@@ -0,0 +1,58 @@
+use crate::prelude::*;
+
+#[derive(Properties, Clone)]
+pub struct HomeworksProps {
+    pub app_link: AppLink,
+    pub homeworks: Rc<Vec<HomeworkItem>>,
+}
+
+impl PartialEq for HomeworksProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.homeworks == other.homeworks
+    }
+}
+
+fn format_due(due: i64) -> String {
+    let Some(due) = Paris.timestamp_opt(due, 0).single() else { return String::new() };
+    let weekday = t(match due.weekday() {
+        Weekday::Mon => "Lundi",
+        Weekday::Tue => "Mardi",
+        Weekday::Wed => "Mercredi",
+        Weekday::Thu => "Jeudi",
+        Weekday::Fri => "Vendredi",
+        Weekday::Sat => "Samedi",
+        Weekday::Sun => "Dimanche",
+    });
+    format!("{weekday} {}", due.format("%d/%m"))
+}
+
+/// Lists the homeworks announced in the comments of the user's events, soonest first.
+#[function_component(HomeworksPage)]
+pub fn homeworks_page(props: &HomeworksProps) -> Html {
+    let now = now();
+    let render = |homework: &HomeworkItem| {
+        let (eid, cid) = (homework.eid.clone(), homework.cid);
+        let onclick = props.app_link.callback(move |_| AppMsg::OpenComment { eid: eid.clone(), cid });
+        html! {
+            <div class="homework" {onclick}>
+                <div class="homework-header">
+                    <span class="homework-summary">{&homework.summary}</span>
+                    <span class="homework-due">{format_due(homework.homework.due)}</span>
+                </div>
+                {render_markdown(&homework.content)}
+                <span class="homework-author">{homework.author.get_username()}</span>
+            </div>
+        }
+    };
+
+    let upcoming = props.homeworks.iter().filter(|h| h.homework.due >= now).map(render).collect::<Html>();
+    let has_upcoming = props.homeworks.iter().any(|h| h.homework.due >= now);
+    let past = props.homeworks.iter().rev().filter(|h| h.homework.due < now).map(render).collect::<Html>();
+    let has_past = props.homeworks.iter().any(|h| h.homework.due < now);
+
+    template_html!(
+        "src/homeworks/homeworks.html",
+        onclick_rick = {props.app_link.callback(|_| AppMsg::SetPage(Page::Rick))},
+        ...
+    )
+}
@@ -1,11 +1,15 @@
 #[path = "alert/alert.rs"]
 mod alert;
+#[path = "avatar/avatar.rs"]
+mod avatar;
 #[path = "event/event.rs"]
 mod event;
 #[path = "settings/settings.rs"]
 mod settings;
 #[path = "agenda/agenda.rs"]
 mod agenda;
+#[path = "agenda/agenda_views.rs"]
+mod agenda_views;
 #[path = "glider_selector/glider_selector.rs"]
 mod glider_selector;
 #[path = "calendar/calendar.rs"]
@@ -20,20 +24,39 @@ mod tabbar;
 mod friends;
 #[path = "comment/comment.rs"]
 mod comment;
+#[path = "comment/comment_revisions.rs"]
+mod comment_revisions;
+#[path = "comment/comment_model.rs"]
+mod comment_model;
+#[path = "comment/mention_textarea.rs"]
+mod mention_textarea;
 #[path = "stotra/stotra.rs"]
 mod stotra;
 #[path = "flag/flag.rs"]
 mod flag;
 #[path = "onboarding/onboarding.rs"]
 mod onboarding;
+#[path = "homeworks/homeworks.rs"]
+mod homeworks;
+#[path = "moderation/moderation.rs"]
+mod moderation;
+#[path = "not_found/not_found.rs"]
+mod not_found;
+#[path = "search/search.rs"]
+mod search;
+#[path = "shortcuts/shortcuts.rs"]
+mod shortcuts;
 
 mod app;
 mod util;
 mod slider;
 mod api;
+mod markdown;
 mod prelude;
 mod translation;
 mod pages;
+mod storage;
+mod sync;
 
 use prelude::*;
 
@@ -57,5 +80,9 @@ fn main() {
     stop_bots(&window);
     let doc = window.doc();
     let element = doc.get_element_by_id("render").unwrap();
-    yew::Renderer::<App>::with_root(element).render();
+    spawn_local(async move {
+        Storage::init().await;
+        Counter::init();
+        yew::Renderer::<App>::with_root(element).render();
+    });
 }
@@ -0,0 +1,61 @@
+use crate::prelude::*;
+
+/// Highlights the mentions of known users in a piece of text.
+fn render_text(text: &str, mentions: &[UserDesc]) -> Html {
+    if mentions.is_empty() {
+        return html! { {text} };
+    }
+    let mut nodes = Vec::new();
+    let mut last = 0;
+    for (range, username) in find_mentions(text) {
+        if !mentions.iter().any(|user| user.as_username().eq_ignore_ascii_case(username)) {
+            continue;
+        }
+        nodes.push(html! { {&text[last..range.start]} });
+        nodes.push(html! { <span class="mention">{&text[range.clone()]}</span> });
+        last = range.end;
+    }
+    nodes.push(html! { {&text[last..]} });
+    nodes.into_iter().collect()
+}
+
+fn render_inlines(inlines: &[MarkdownInline], mentions: &[UserDesc]) -> Html {
+    inlines.iter().map(|inline| match inline {
+        MarkdownInline::Text(text) => render_text(text, mentions),
+        MarkdownInline::Emphasis(inner) => html! { <em>{render_inlines(inner, mentions)}</em> },
+        MarkdownInline::Strong(inner) => html! { <strong>{render_inlines(inner, mentions)}</strong> },
+        MarkdownInline::Code(code) => html! { <code>{code}</code> },
+        MarkdownInline::Link { text, url } => html! {
+            <a href={url.clone()} target="_blank" rel="noopener noreferrer nofollow">{render_inlines(text, mentions)}</a>
+        },
+        MarkdownInline::LineBreak => html! { <br/> },
+    }).collect()
+}
+
+fn render_blocks(blocks: &[MarkdownBlock], mentions: &[UserDesc]) -> Html {
+    blocks.iter().map(|block| match block {
+        MarkdownBlock::Paragraph(inlines) => html! { <p>{render_inlines(inlines, mentions)}</p> },
+        MarkdownBlock::Quote(blocks) => html! { <blockquote>{render_blocks(blocks, mentions)}</blockquote> },
+        MarkdownBlock::List { ordered: false, items } => html! {
+            <ul>{for items.iter().map(|item| html! { <li>{render_inlines(item, mentions)}</li> })}</ul>
+        },
+        MarkdownBlock::List { ordered: true, items } => html! {
+            <ol>{for items.iter().map(|item| html! { <li>{render_inlines(item, mentions)}</li> })}</ol>
+        },
+        MarkdownBlock::CodeBlock(code) => html! { <pre><code>{code}</code></pre> },
+    }).collect()
+}
+
+/// Renders a comment written in Markdown.
+/// Every piece of text goes through yew's text nodes, so that the content can never inject markup.
+pub fn render_markdown(content: &str) -> Html {
+    render_markdown_with_mentions(content, &[])
+}
+
+/// Same as [render_markdown], but also highlights the mentions of the given users.
+pub fn render_markdown_with_mentions(content: &str, mentions: &[UserDesc]) -> Html {
+    let blocks = parse_markdown(content);
+    html! {
+        <div class="markdown">{render_blocks(&blocks, mentions)}</div>
+    }
+}
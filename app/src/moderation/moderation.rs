@@ -0,0 +1,188 @@
+use crate::prelude::*;
+
+#[derive(Properties, Clone)]
+pub struct ModerationProps {
+    pub app_link: AppLink,
+    pub user_info: Rc<Option<UserInfo>>,
+    /// Changes when another tab changed the settings.
+    #[prop_or_default]
+    pub settings_generation: usize,
+}
+
+impl PartialEq for ModerationProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.user_info == other.user_info && self.settings_generation == other.settings_generation
+    }
+}
+
+pub enum ModerationMsg {
+    Reload,
+    ReportsLoaded(Vec<ReportedComment>),
+    LogLoaded(Vec<ModerationLogEntry>),
+    Act { eid: String, cid: u64, action: ModerationAction },
+}
+
+pub struct ModerationPage {
+    reports: Option<Vec<ReportedComment>>,
+    log: Option<Vec<ModerationLogEntry>>,
+}
+
+fn action_label(action: ModerationAction) -> &'static str {
+    match action {
+        ModerationAction::Dismiss => "Ignoré",
+        ModerationAction::Hide => "Masqué",
+        ModerationAction::Delete => "Supprimé",
+    }
+}
+
+fn reason_label(reason: ReportReason) -> &'static str {
+    match SETTINGS.lang() {
+        Lang::French => reason.to_message().1,
+        Lang::English => reason.to_message().0,
+    }
+}
+
+impl ModerationPage {
+    fn view_report(&self, ctx: &Context<Self>, reported: &ReportedComment) -> Html {
+        let eid = reported.eid.clone();
+        let cid = reported.comment.cid;
+        let act = |action: ModerationAction| {
+            let eid = eid.clone();
+            ctx.link().callback(move |_| ModerationMsg::Act { eid: eid.clone(), cid, action })
+        };
+
+        html! {
+            <div class="moderation-report">
+                <div class="moderation-report-event">{&reported.event_summary}</div>
+                { for reported.context.iter().map(|parent| html! {
+                    <div class="moderation-context">
+                        <span class="moderation-author">{parent.author.get_username()}</span>
+                        {render_markdown(&parent.content)}
+                    </div>
+                }) }
+                <div class="moderation-comment">
+                    <span class="moderation-author">{reported.comment.author.get_username()}</span>
+                    if reported.comment.hidden {
+                        <span class="moderation-time">{" (masqué)"}</span>
+                    }
+                    {render_markdown(&reported.comment.content)}
+                    if reported.comment.last_edited_ts > reported.comment.creation_ts {
+                        <details class="moderation-revisions">
+                            <summary>{"Voir les modifications"}</summary>
+                            <CommentRevisions eid={reported.eid.clone()} {cid} />
+                        </details>
+                    }
+                </div>
+                <ul class="moderation-reasons">
+                    { for reported.reports.iter().map(|report| html! {
+                        <li>
+                            <strong>{reason_label(report.reason)}</strong>
+                            {" — "}{report.reporter.get_username()}
+                            <span class="moderation-time">{" il y a "}{format_time_diff(now() - report.ts)}</span>
+                            if let Some(note) = &report.note {
+                                <p class="moderation-note">{note}</p>
+                            }
+                        </li>
+                    }) }
+                </ul>
+                <div class="moderation-actions">
+                    <button class="comment-button" onclick={act(ModerationAction::Dismiss)}>{"Ignorer"}</button>
+                    <button class="comment-button" onclick={act(ModerationAction::Hide)}>{"Masquer"}</button>
+                    <button class="comment-button moderation-delete" onclick={act(ModerationAction::Delete)}>{"Supprimer"}</button>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_log_entry(entry: &ModerationLogEntry) -> Html {
+        html! {
+            <li>
+                <strong>{action_label(entry.action)}</strong>
+                {" par "}{entry.moderator.get_username()}
+                <span class="moderation-time">{" il y a "}{format_time_diff(now() - entry.ts)}</span>
+                <p class="moderation-note">{&entry.content}</p>
+                if let Some(note) = &entry.note {
+                    <p class="moderation-note">{"Note : "}{note}</p>
+                }
+            </li>
+        }
+    }
+}
+
+impl Component for ModerationPage {
+    type Message = ModerationMsg;
+    type Properties = ModerationProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(ModerationMsg::Reload);
+        Self {
+            reports: None,
+            log: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ModerationMsg::Reload => {
+                let link = ctx.link().clone();
+                let app_link = ctx.props().app_link.clone();
+                spawn_local(async move {
+                    match get_reported_comments().await {
+                        Ok(reports) => link.send_message(ModerationMsg::ReportsLoaded(reports)),
+                        Err(e) => app_link.send_message(AppMsg::ApiFailure(e)),
+                    }
+                    match get_moderation_log().await {
+                        Ok(log) => link.send_message(ModerationMsg::LogLoaded(log)),
+                        Err(e) => app_link.send_message(AppMsg::ApiFailure(e)),
+                    }
+                });
+                false
+            }
+            ModerationMsg::ReportsLoaded(reports) => {
+                self.reports = Some(reports);
+                true
+            }
+            ModerationMsg::LogLoaded(log) => {
+                self.log = Some(log);
+                true
+            }
+            ModerationMsg::Act { eid, cid, action } => {
+                if action == ModerationAction::Delete && !window().confirm_with_message("Supprimer définitivement ce commentaire ?").unwrap_or(false) {
+                    return false;
+                }
+                let note = window().prompt_with_message("Note pour le journal de modération (facultatif)").ok().flatten().filter(|note| !note.trim().is_empty());
+
+                // Remove the comment from the queue right away, it will come back on reload if the action failed
+                if let Some(reports) = &mut self.reports {
+                    reports.retain(|r| !(r.eid == eid && r.comment.cid == cid));
+                }
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    if let Err(e) = moderate_comment(ModerationRequest { eid, cid, action, note }).await {
+                        e.report(Some("L'action de modération a échoué"));
+                    }
+                    link.send_message(ModerationMsg::Reload);
+                });
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let allowed = ctx.props().user_info.as_ref().as_ref().map(|u| UserDesc::new(u.uid, u.email.0.clone()).is_contributor()).unwrap_or(false);
+        let loading = self.reports.is_none();
+        let reports = self.reports.as_deref().unwrap_or_default();
+        let empty = !loading && reports.is_empty();
+        let report_count = reports.len();
+        let reports = reports.iter().map(|r| self.view_report(ctx, r)).collect::<Html>();
+        let log = self.log.as_deref().unwrap_or_default().iter().map(Self::view_log_entry).collect::<Html>();
+
+        template_html!(
+            "src/moderation/moderation.html",
+            onclick_rick = {ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Rick))},
+            onclick_back = {ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Settings))},
+            ...
+        )
+    }
+}
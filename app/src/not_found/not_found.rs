@@ -0,0 +1,25 @@
+use crate::prelude::*;
+
+#[derive(Properties, Clone)]
+pub struct NotFoundProps {
+    pub app_link: AppLink,
+    pub path: String,
+}
+
+impl PartialEq for NotFoundProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+/// Shown for urls that don't lead to any page, or to something that doesn't exist anymore.
+#[function_component(NotFoundPage)]
+pub fn not_found_page(props: &NotFoundProps) -> Html {
+    template_html!(
+        "src/not_found/not_found.html",
+        path = {props.path.clone()},
+        onclick_rick = {props.app_link.callback(|_| AppMsg::SetPage(Page::Rick))},
+        onclick_agenda = {props.app_link.callback(|_| AppMsg::SetPage(Page::Agenda { target: None }))},
+        ...
+    )
+}
@@ -86,12 +86,12 @@ impl Component for OnboardingPage {
                     
                     wasm_bindgen_futures::spawn_local(async move {
                         // Update groups first
-                        match api_post(groups.clone(), "set-groups").await {
-                            Ok(()) => {
+                        match Outbox::submit(Mutation::SetGroups(SetGroups(groups.clone()))).await {
+                            Ok(_) => {
                                 app_link.send_message(AppMsg::UserInfoSuccess(updated_user_info));
-                                app_link.send_message(AppMsg::SetPage(Page::Agenda));
+                                app_link.send_message(AppMsg::SetPage(Page::Agenda { target: None }));
                             },
-                            Err(e) => alert(format!("Impossible de mettre à jour les groupes : {e}")),
+                            Err(e) => e.report(Some("Impossible de mettre à jour les groupes")),
                         }
                     });
                 }
@@ -105,7 +105,7 @@ impl Component for OnboardingPage {
                     updated_user_info.onboarded = true;
                     
                     app_link.send_message(AppMsg::UserInfoSuccess(updated_user_info));
-                    app_link.send_message(AppMsg::SetPage(Page::Agenda));
+                    app_link.send_message(AppMsg::SetPage(Page::Agenda { target: None }));
                 }
                 false
             }
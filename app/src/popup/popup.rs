@@ -1,10 +1,44 @@
 use crate::prelude::*;
 
+/// Number of threads revealed at a time. Every comment is fetched at once, only their display is progressive.
+const THREADS_SHOWN_STEP: usize = 10;
+
+const COMMENT_SORTS: [CommentSort; 3] = [CommentSort::Top, CommentSort::Newest, CommentSort::Controversial];
+
+/// Fetches the comments of an event and sends them to the popup, or reports why they couldn't be fetched.
+fn load_comments(eid: String, link: PopupLink) {
+    spawn_local(async move {
+        match api_call(GetComments { eid }).await {
+            Ok(new_comments) => link.send_message(PopupMsg::CommentsLoaded(new_comments)),
+            // No comment has been written on this course yet
+            Err(ApiError::Known(e)) if e.kind == ApiErrorKind::TextbookNotFound => link.send_message(PopupMsg::CommentsLoaded(Vec::new())),
+            // The comments on screen, if any, are kept rather than replaced by an empty list
+            Err(e) => e.report(Some("Impossible de charger les commentaires")),
+        }
+    });
+}
+
+/// Forgets the draft of an action once its text is safe, unless something else was typed meanwhile.
+fn forget_draft(model: &CommentModel, id: usize, eid: &str) {
+    if let Some((_, key, content)) = model.action(id).and_then(|action| action.draft(eid)) {
+        if Storage::get(&key).as_ref() == Some(content) {
+            clear_draft(&key);
+        }
+    }
+}
+
 pub struct Popup {
-    comments: Option<Vec<Comment>>,
+    comments: Option<CommentModel>,
     friend_counter_folded: bool,
     current_color: String,
     color_changed: bool,
+    comment_preview: Option<String>,
+    sort: CommentSort,
+    focused_thread: Option<u64>,
+    shown_threads: usize,
+    /// Drafts given back after their action failed, passed to the comments once so that they reopen their editor.
+    restored_drafts: Rc<Vec<String>>,
+    _shortcuts: Shortcuts,
 }
 
 pub enum PopupMsg {
@@ -12,7 +46,18 @@ pub enum PopupMsg {
     ColorInput,
     ReloadComments,
     Comment,
+    ToggleCommentPreview,
     CommentsLoaded(Vec<Comment>),
+    /// Apply an action locally and send it to the server.
+    Action(CommentAction),
+    ActionSucceeded(usize),
+    /// The action waits in the outbox, to be sent once the network is back.
+    ActionQueued(usize),
+    ActionFailed(usize),
+    SetSort(CommentSort),
+    /// Display a single thread, used when replies are too deep to be displayed inline.
+    FocusThread(Option<u64>),
+    ShowMoreThreads,
     AppMsg(AppMsg),
 }
 
@@ -23,6 +68,20 @@ pub struct PopupProps {
     pub user_info: Rc<Option<UserInfo>>,
     pub friends: Rc<Option<FriendLists>>,
     pub colors: Rc<Colors>,
+    /// Used to find the next session of the course.
+    pub events: Rc<Vec<RawEvent>>,
+    /// Comment to open the popup on.
+    #[prop_or_default]
+    pub focused_comment: Option<u64>,
+    /// Changes when the server tells that the comments changed.
+    #[prop_or_default]
+    pub comments_version: usize,
+    /// Changes when another tab changed the settings, such as the sort of comments.
+    #[prop_or_default]
+    pub settings_generation: usize,
+    /// Number of mutations waiting in the outbox.
+    #[prop_or_default]
+    pub pending_mutations: usize,
 }
 
 impl PartialEq for PopupProps {
@@ -30,6 +89,11 @@ impl PartialEq for PopupProps {
         self.event == other.event
             && self.user_info == other.user_info
             && self.friends == other.friends
+            && self.events == other.events
+            && self.focused_comment == other.focused_comment
+            && self.comments_version == other.comments_version
+            && self.settings_generation == other.settings_generation
+            && self.pending_mutations == other.pending_mutations
             && self.colors.get(&self.event.summary) == other.colors.get(&self.event.summary)
     }
 }
@@ -39,28 +103,62 @@ impl Component for Popup {
     type Properties = PopupProps;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let eid = ctx.props().event.eid.clone();
-        let link = ctx.link().clone();
-        spawn_local(async move {
-            match api_get(format!("comments?eid={eid}")).await {
-                Ok(new_comments) => link.send_message(PopupMsg::CommentsLoaded(new_comments)),
-                Err(ApiError::Known(e)) if e.kind == "textbook_not_found" => link.send_message(PopupMsg::CommentsLoaded(Vec::new())),
-                Err(e) => {
-                    alert(e.to_string());
-                    link.send_message(PopupMsg::CommentsLoaded(Vec::new()));
-                },
-            }
-        });
+        load_comments(ctx.props().event.eid.clone(), ctx.link().clone());
 
         let summary = &ctx.props().event.summary;
         let bg_color = ctx.props().colors.get(summary).map(|c| c.to_string()).unwrap_or_else(|| String::from("#CB6CE6"));
 
+        let app_link = ctx.props().app_link.clone();
+        let shortcuts = Shortcuts::register(move |key| match key {
+            "Escape" => {
+                app_link.send_message(AppMsg::SetPage(Page::Agenda { target: None }));
+                true
+            }
+            "?" => {
+                app_link.send_message(AppMsg::ShowShortcutHelp(true));
+                true
+            }
+            _ => false,
+        });
+
         Self {
             comments: None,
             friend_counter_folded: true,
             current_color: bg_color,
             color_changed: false,
+            comment_preview: None,
+            sort: SETTINGS.comment_sort(),
+            focused_thread: ctx.props().focused_comment,
+            shown_threads: THREADS_SHOWN_STEP,
+            restored_drafts: Rc::new(Vec::new()),
+            _shortcuts: shortcuts,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if ctx.props().focused_comment != old_props.focused_comment {
+            self.focused_thread = ctx.props().focused_comment;
+        }
+        if ctx.props().comments_version != old_props.comments_version {
+            load_comments(ctx.props().event.eid.clone(), ctx.link().clone());
+        }
+        if ctx.props().settings_generation != old_props.settings_generation && self.sort != SETTINGS.comment_sort() {
+            self.sort = SETTINGS.comment_sort();
+            self.shown_threads = THREADS_SHOWN_STEP;
+            if let Some(model) = self.comments.as_mut() {
+                model.set_sort(self.sort);
+            }
+        }
+        // The server now knows about the queued actions, which the next list includes
+        if ctx.props().pending_mutations == 0 && old_props.pending_mutations != 0 {
+            if let Some(model) = self.comments.as_mut() {
+                if model.flushed() {
+                    <Vec<HomeworkItem>>::refresh(ctx.props().app_link.clone());
+                    load_comments(ctx.props().event.eid.clone(), ctx.link().clone());
+                }
+            }
         }
+        true
     }
 
     fn destroy(&mut self, ctx: &Context<Self>) {
@@ -71,11 +169,94 @@ impl Component for Popup {
         ctx.props().app_link.send_message(AppMsg::MarkCommentsAsSeen(ctx.props().event.eid.clone()));
     }
 
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        // The comments have reopened their editor
+        if !self.restored_drafts.is_empty() {
+            self.restored_drafts = Rc::new(Vec::new());
+        }
+    }
+
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            PopupMsg::CommentsLoaded(mut new_comments) => {
-                new_comments.sort_by_key(|c| c.downvotes as isize - c.upvotes as isize);
-                self.comments = Some(new_comments);
+            PopupMsg::CommentsLoaded(new_comments) => {
+                match self.comments.as_mut() {
+                    Some(model) => model.reconcile(new_comments),
+                    None => {
+                        let author = ctx.props().user_info.as_ref().as_ref().map(|u| UserDesc { picture: u.picture.clone(), ..UserDesc::new(u.uid, u.email.0.clone()) });
+                        self.comments = Some(CommentModel::new(new_comments, author, self.sort));
+                    }
+                }
+                true
+            }
+            PopupMsg::Action(action) => {
+                let Some(model) = self.comments.as_mut() else { return false };
+                let id = model.apply(action.clone());
+                let eid = ctx.props().event.eid.clone();
+                let link = ctx.link().clone();
+                let app_link = ctx.props().app_link.clone();
+                spawn_local(async move {
+                    match action.send(eid).await {
+                        Ok(Delivery::Queued) => link.send_message(PopupMsg::ActionQueued(id)),
+                        Ok(Delivery::Sent) => {
+                            if matches!(action, CommentAction::SetHomework { .. } | CommentAction::Edit { .. } | CommentAction::Delete { .. }) {
+                                <Vec<HomeworkItem>>::refresh(app_link);
+                            }
+                            link.send_message(PopupMsg::ActionSucceeded(id))
+                        },
+                        Err(e) => {
+                            e.report(Some(action.failure_message()));
+                            link.send_message(PopupMsg::ActionFailed(id));
+                        }
+                    }
+                });
+                true
+            }
+            PopupMsg::ActionSucceeded(id) => {
+                let Some(model) = self.comments.as_mut() else { return false };
+                forget_draft(model, id, &ctx.props().event.eid);
+                if model.confirm(id) {
+                    load_comments(ctx.props().event.eid.clone(), ctx.link().clone());
+                }
+                true
+            }
+            PopupMsg::ActionQueued(id) => {
+                let Some(model) = self.comments.as_mut() else { return false };
+                // The outbox keeps the text from now on
+                forget_draft(model, id, &ctx.props().event.eid);
+                model.queue(id);
+                true
+            }
+            PopupMsg::ActionFailed(id) => {
+                let Some(model) = self.comments.as_mut() else { return false };
+
+                // Give the text back to the user so that it isn't lost
+                let Some(action) = model.rollback(id) else { return true };
+                let Some((textarea_id, key, content)) = action.draft(&ctx.props().event.eid) else { return true };
+                // Text typed since then is kept
+                if Storage::get(&key).map(|draft| &draft == content).unwrap_or(true) {
+                    if let Some(textarea) = window().doc().get_element_by_id(&textarea_id) {
+                        textarea.unchecked_into::<web_sys::HtmlTextAreaElement>().set_value(content);
+                    }
+                    Storage::set(&key, content.clone());
+                }
+                self.restored_drafts = Rc::new(vec![key]);
+                true
+            }
+            PopupMsg::SetSort(sort) => {
+                self.sort = sort;
+                self.shown_threads = THREADS_SHOWN_STEP;
+                SETTINGS.set_comment_sort(sort);
+                if let Some(model) = self.comments.as_mut() {
+                    model.set_sort(sort);
+                }
+                true
+            }
+            PopupMsg::FocusThread(cid) => {
+                self.focused_thread = cid;
+                true
+            }
+            PopupMsg::ShowMoreThreads => {
+                self.shown_threads += THREADS_SHOWN_STEP;
                 true
             }
             PopupMsg::ColorInput => {
@@ -87,23 +268,29 @@ impl Component for Popup {
                 true
             },
             PopupMsg::ReloadComments => {
-                *self = Component::create(ctx);
+                load_comments(ctx.props().event.eid.clone(), ctx.link().clone());
                 false
             }
             PopupMsg::Comment => {
                 let el = window().doc().get_element_by_id("comment-textarea-top").unwrap();
                 let textarea = el.dyn_into::<web_sys::HtmlTextAreaElement>().unwrap();
                 let content = textarea.value();
+                if content.trim().is_empty() {
+                    return false;
+                }
+                // The draft is kept until the server accepted the comment
                 textarea.set_value("");
+                self.comment_preview = None;
 
-                let eid = ctx.props().event.eid.clone();
-                let link = ctx.link().clone();
-                spawn_local(async move {
-                    if let Err(e) = update_comment(eid, None, None, content).await {
-                        alert(e.to_string());
-                    }
-                    link.send_message(PopupMsg::ReloadComments);
-                });
+                let mentions = resolve_mentions(&content, &mention_candidates(&ctx.props().friends));
+                ctx.link().send_message(PopupMsg::Action(CommentAction::Create { cid: local_cid(), parent: None, content, mentions }));
+                true
+            }
+            PopupMsg::ToggleCommentPreview => {
+                self.comment_preview = match self.comment_preview {
+                    Some(_) => None,
+                    None => Some(textarea_value("comment-textarea-top")),
+                };
                 true
             }
             PopupMsg::TriggerFriendCounter => {
@@ -118,7 +305,7 @@ impl Component for Popup {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let onclick_close = ctx.props().app_link.callback(move |_| AppMsg::SetPage(Page::Agenda));
+        let onclick_close = ctx.props().app_link.callback(move |_| AppMsg::SetPage(Page::Agenda { target: None }));
 
         // Friend counter
         let friends: Vec<_> = ctx.props().friends.deref().as_ref().map(|friends| {
@@ -139,24 +326,58 @@ impl Component for Popup {
         let bg_color = &self.current_color;
         let name = ctx.props().event.format_name();
         let opt_location = ctx.props().event.format_location();
+        let room = ctx.props().event.location.as_ref().map(Location::room_id).unwrap_or_default();
+        let onclick_room = ctx.props().app_link.callback(move |_| AppMsg::SetPage(Page::RoomAgenda { room: room.clone(), date: None }));
+        let has_teachers = !ctx.props().event.teachers.is_empty();
+        let teacher_links = teacher_links(&ctx.props().event.teachers, ctx.props().app_link.callback(AppMsg::SetPage));
 
         let comments_loading = self.comments.is_none();
-        let comments = Rc::new(self.comments.clone().unwrap_or_default());
+        let comments = self.comments.as_ref().map(|model| model.comments()).unwrap_or_default();
+        let local_cids = Rc::new(comments.iter().map(|c| c.cid).filter(|cid| self.comments.as_ref().map(|m| m.is_local(*cid)).unwrap_or(false)).collect::<Vec<_>>());
         let eid = Rc::new(ctx.props().event.eid.clone());
-        let comment_iter = comments.iter().filter(|c| c.parent.is_none()).map(|c| {
+        let candidates = Rc::new(mention_candidates(&ctx.props().friends));
+        let next_session = Rc::new(next_session(&ctx.props().events, &ctx.props().event).cloned());
+        let roots = comments.iter().filter(|c| match self.focused_thread {
+            Some(focused) => c.cid == focused,
+            None => c.parent.is_none(),
+        }).collect::<Vec<_>>();
+        let hidden_threads = roots.len().saturating_sub(self.shown_threads);
+        let has_hidden_threads = hidden_threads > 0;
+        let comment_iter = roots.into_iter().take(self.shown_threads).map(|c| {
             html! {
                 <CommentComp
                     eid={Rc::clone(&eid)}
                     comments={Rc::clone(&comments)}
                     cid={c.cid}
+                    depth={0}
+                    local_cids={Rc::clone(&local_cids)}
+                    mention_candidates={Rc::clone(&candidates)}
+                    next_session={Rc::clone(&next_session)}
                     user_info={Rc::clone(&ctx.props().user_info)}
+                    restored_drafts={Rc::clone(&self.restored_drafts)}
+                    settings_generation={ctx.props().settings_generation}
                     popup_link={ctx.link().clone()} />
             }
         });
+        let thread_focused = self.focused_thread.is_some();
+        let onclick_unfocus_thread = ctx.link().callback(|_| PopupMsg::FocusThread(None));
+        let onclick_more_threads = ctx.link().callback(|_| PopupMsg::ShowMoreThreads);
+        let sort_glider_selector = html! {
+            <GliderSelector
+                values = { vec!["Populaires", "Récents", "Controversés"] }
+                on_change = { ctx.link().callback(|i: usize| PopupMsg::SetSort(COMMENT_SORTS[i])) }
+                selected = { COMMENT_SORTS.iter().position(|s| *s == self.sort).unwrap_or(0) } />
+        };
 
-        let user_avatar = format!("https://api.dicebear.com/5.x/identicon/svg?seed={}", ctx.props().user_info.as_ref().as_ref().map(|u| u.uid).unwrap_or(0));
+        let user_info = ctx.props().user_info.as_ref().as_ref();
+        let user_avatar = html! { <Avatar class="comment-author-avatar" uid={user_info.map(|u| u.uid).unwrap_or(0)} picture={user_info.and_then(|u| u.picture.clone())} /> };
         let user_name = ctx.props().user_info.as_ref().as_ref().map(|u| u.email.0.split('@').next().unwrap().to_string()).unwrap_or(String::from("inconnu"));
         let onclick_comment = ctx.link().callback(|_| PopupMsg::Comment);
+        let onclick_comment_preview = ctx.link().callback(|_| PopupMsg::ToggleCommentPreview);
+        let opt_comment_preview = self.comment_preview.as_deref().map(|content| render_markdown_with_mentions(content, &resolve_mentions(content, &candidates)));
+        let comment_textarea = html! { <MentionTextarea id="comment-textarea-top" placeholder="Ecrivez un commentaire" candidates={Rc::clone(&candidates)} draft_key={draft_key(&ctx.props().event.eid, None)} /> };
+        let comment_editor_class = if opt_comment_preview.is_some() { "comment-editor previewing" } else { "comment-editor" };
+        let comment_preview_label = if opt_comment_preview.is_some() { "Écrire" } else { "Aperçu" };
 
         template_html!(
             "src/popup/popup.html",
@@ -169,8 +390,7 @@ impl Component for Popup {
             input_color = {ctx.link().callback(|_| PopupMsg::ColorInput)},
             opt_location = {&opt_location},
             event_color = {event_color.clone()},
-            alt_iter = { names.iter().map(|name| format!("Avatar of {}", name)) },
-            picture_iter = { friends.iter().map(|friend| friend.profile_url()) },
+            avatar_iter = { friends.iter().map(|friend| friend.avatar()) },
             ...
         )
     }
@@ -1,6 +1,6 @@
 pub use crate::{
-    agenda::*, alert::*, api::*, app::*, calendar::*, flag::*, comment::*, event::*,
-    friends::*, glider_selector::*, log, pages::*, popup::Popup, popup::*, settings::*, tabbar::*,
+    agenda::*, agenda_views::*, alert::*, api::*, avatar::*, app::*, calendar::*, flag::*, comment::*, comment_model::*, comment_revisions::*, event::*,
+    friends::*, glider_selector::*, homeworks::*, log, markdown::*, mention_textarea::*, moderation::*, not_found::*, pages::*, popup::Popup, popup::*, search::*, settings::*, shortcuts::*, storage::*, sync::*, tabbar::*,
     translation::*, util::*,
 };
 pub use calendrier::{
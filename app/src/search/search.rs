@@ -0,0 +1,161 @@
+use crate::prelude::*;
+
+/// Results shown at most, the following ones being counted.
+const MAX_RESULTS: usize = 100;
+
+const KINDS: [(EventKind, &str); 4] = [
+    (EventKind::Cm, "CM"),
+    (EventKind::Td, "TD"),
+    (EventKind::Tp, "TP"),
+    (EventKind::Tut, "Tutorat"),
+];
+
+fn parse_date_input(e: &InputEvent) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&e.target_unchecked_into::<HtmlInputElement>().value(), "%Y-%m-%d").ok()
+}
+
+pub enum SearchMsg {
+    Query(String),
+    From(Option<NaiveDate>),
+    Until(Option<NaiveDate>),
+    ToggleKind(EventKind),
+}
+
+#[derive(Properties, Clone)]
+pub struct SearchProps {
+    pub app_link: AppLink,
+    pub events: Rc<Vec<RawEvent>>,
+    pub colors: Rc<Colors>,
+}
+
+impl PartialEq for SearchProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.events == other.events && self.colors == other.colors
+    }
+}
+
+/// Finds events of the schedule by summary, teacher, room, kind or group.
+pub struct SearchPage {
+    index: SearchIndex,
+    query: String,
+    /// Upcoming events are searched by default
+    from: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    kinds: Vec<EventKind>,
+}
+
+impl Component for SearchPage {
+    type Message = SearchMsg;
+    type Properties = SearchProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            index: SearchIndex::new(&ctx.props().events),
+            query: String::new(),
+            from: Some(Local::now().date_naive()),
+            until: None,
+            kinds: Vec::new(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if !Rc::ptr_eq(&ctx.props().events, &old_props.events) {
+            self.index = SearchIndex::new(&ctx.props().events);
+        }
+        true
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            SearchMsg::Query(query) => self.query = query,
+            SearchMsg::From(from) => self.from = from,
+            SearchMsg::Until(until) => self.until = until,
+            SearchMsg::ToggleKind(kind) => match self.kinds.iter().position(|k| *k == kind) {
+                Some(i) => { self.kinds.remove(i); },
+                None => self.kinds.push(kind),
+            },
+        }
+        true
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            if let Some(input) = window().doc().get_element_by_id("search-input").and_then(|e| e.dyn_into::<HtmlElement>().ok()) {
+                let _ = input.focus();
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let filters = SearchFilters {
+            from: self.from.map(day_start),
+            until: self.until.map(|until| day_start(until) + 24 * 3600 - 1),
+            kinds: self.kinds.clone(),
+        };
+        let searching = !self.query.trim().is_empty() || !self.kinds.is_empty();
+        let results = match searching {
+            true => self.index.search(&ctx.props().events, &self.query, &filters),
+            false => Vec::new(),
+        };
+        let has_results = !results.is_empty();
+        let no_results = searching && !has_results;
+        let hidden_count = results.len().saturating_sub(MAX_RESULTS);
+        let has_hidden = hidden_count > 0;
+
+        // Group results by day
+        let mut days: Vec<(NaiveDate, Vec<&RawEvent>)> = Vec::new();
+        for event in results.into_iter().take(MAX_RESULTS) {
+            let day = Paris.timestamp_opt(event.start_unixtime as i64, 0).unwrap().date_naive();
+            match days.last_mut() {
+                Some((last_day, events)) if *last_day == day => events.push(event),
+                _ => days.push((day, vec![event])),
+            }
+        }
+        let result_days = days.into_iter().map(|(day, events)| {
+            let onclick_day = ctx.props().app_link.callback(move |_| AppMsg::SetPage(Page::Agenda { target: Some(AgendaTarget::Day(day)) }));
+            let rows = events.into_iter().map(|e| {
+                let eid = e.eid.clone();
+                let onclick = ctx.props().app_link.callback(move |_| AppMsg::SetPage(Page::Event { eid: eid.clone() }));
+                let color = ctx.props().colors.get(&e.summary).cloned().unwrap_or_else(|| String::from("#CB6CE6"));
+                html! {
+                    <div class="search-result" {onclick}>
+                        <span class="search-result-color" style={format!("background-color: {color}")}></span>
+                        <span class="search-result-time">{ e.format_time() }</span>
+                        <div>
+                            <span class="search-result-name">{ e.format_name() }</span>
+                            <span class="search-result-details">
+                                { [e.teachers.join(", "), e.format_location().unwrap_or_default()].into_iter().filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" · ") }
+                            </span>
+                        </div>
+                    </div>
+                }
+            }).collect::<Html>();
+            html! {
+                <section class="search-day">
+                    <h3 onclick={onclick_day} title="Voir dans l'agenda">{ format!("{} {}", day_name(day), month_name(day)) }</h3>
+                    { rows }
+                </section>
+            }
+        }).collect::<Html>();
+
+        let kind_buttons = KINDS.into_iter().map(|(kind, label)| {
+            let class = classes!("search-kind", self.kinds.contains(&kind).then_some("selected"));
+            let onclick = ctx.link().callback(move |_| SearchMsg::ToggleKind(kind));
+            html! { <button {class} {onclick}>{ label }</button> }
+        }).collect::<Html>();
+
+        let format_input = |date: Option<NaiveDate>| date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default();
+
+        template_html!(
+            "src/search/search.html",
+            onclick_rick = {ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Rick))},
+            oninput_query = {ctx.link().callback(|e: InputEvent| SearchMsg::Query(e.target_unchecked_into::<HtmlInputElement>().value()))},
+            oninput_from = {ctx.link().callback(|e: InputEvent| SearchMsg::From(parse_date_input(&e)))},
+            oninput_until = {ctx.link().callback(|e: InputEvent| SearchMsg::Until(parse_date_input(&e)))},
+            query = {self.query.clone()},
+            from = {format_input(self.from)},
+            until = {format_input(self.until)},
+            ...
+        )
+    }
+}
@@ -11,17 +11,74 @@ fn random_theme() -> Theme {
     }
 }
 
+/// Returns the stored theme, and whether it was randomly selected.
+fn stored_theme(local_storage: &web_sys::Storage) -> (Theme, bool) {
+    match local_storage.get_item("setting-theme").unwrap() {
+        Some(theme) if theme == "dark" => (Theme::Dark, false),
+        Some(theme) if theme == "light" => (Theme::Light, false),
+        Some(theme) if theme == "insarcade" => (Theme::Insarcade, false),
+        Some(theme) if theme == "moyeninsage" => (Theme::MoyenInsage, false),
+        Some(theme) if theme == "random" => (random_theme(), true),
+        _ => (Theme::System, false),
+    }
+}
+
+fn stored_lang(local_storage: &web_sys::Storage) -> usize {
+    match local_storage.get_item("setting-lang").unwrap() {
+        Some(lang) if lang == "french" => 0,
+        Some(lang) if lang == "english" => 1,
+        Some(lang) => {alert(format!("Invalid language {lang}")); 0},
+        None => {
+            let languages = window().navigator().languages();
+            let mut lang = None;
+            for language in languages.iter() {
+                if let Some(language) = language.as_string() {
+                    if language == "fr" || language.starts_with("fr-") {
+                        lang = Some(0);
+                        break;
+                    } else if language == "en" || language.starts_with("en-") {
+                        lang = Some(1);
+                        break;
+                    }
+                }
+            }
+
+            lang.unwrap_or(0)
+        },
+    }
+}
+
+fn stored_calendar(local_storage: &web_sys::Storage) -> usize {
+    match local_storage.get_item("setting-calendar").unwrap() {
+        Some(calendar) if calendar == "gregorian" => 0,
+        Some(calendar) if calendar == "republican" => 1,
+        _ => 0,
+    }
+}
+
+fn stored_agenda_view(local_storage: &web_sys::Storage) -> usize {
+    match local_storage.get_item("setting-agenda-view").unwrap() {
+        Some(view) if view == "week" => 0,
+        Some(view) if view == "day" => 1,
+        Some(view) if view == "month" => 2,
+        Some(view) if view == "list" => 3,
+        _ => 0,
+    }
+}
+
+fn stored_comment_sort(local_storage: &web_sys::Storage) -> usize {
+    match local_storage.get_item("setting-comment-sort").unwrap() {
+        Some(sort) if sort == "top" => 0,
+        Some(sort) if sort == "newest" => 1,
+        Some(sort) if sort == "controversial" => 2,
+        _ => 0,
+    }
+}
+
 lazy_static::lazy_static!{
     pub static ref SETTINGS: SettingStore = {
         let local_storage = window().local_storage().unwrap().unwrap();
-        let (mut theme, randomly_selected) = match local_storage.get_item("setting-theme").unwrap() {
-            Some(theme) if theme == "dark" => (Theme::Dark, false),
-            Some(theme) if theme == "light" => (Theme::Light, false),
-            Some(theme) if theme == "insarcade" => (Theme::Insarcade, false),
-            Some(theme) if theme == "moyeninsage" => (Theme::MoyenInsage, false),
-            Some(theme) if theme == "random" => (random_theme(), true),
-            _ => (Theme::System, false),
-        };
+        let (mut theme, randomly_selected) = stored_theme(&local_storage);
         let mut update_theme = randomly_selected;
         if (theme == Theme::Insarcade || theme == Theme::MoyenInsage) && (1712268000..=1712268000+86400).contains(&now()) {
             theme = Theme::Light;
@@ -35,39 +92,18 @@ lazy_static::lazy_static!{
             html.set_attribute("data-theme", theme.as_ref()).unwrap();
         }
 
-        let lang = match local_storage.get_item("setting-lang").unwrap() {
-            Some(lang) if lang == "french" => 0,
-            Some(lang) if lang == "english" => 1,
-            Some(lang) => {alert(format!("Invalid language {lang}")); 0},
-            None => {
-                let languages = window().navigator().languages();
-                let mut lang = None;
-                for language in languages.iter() {
-                    if let Some(language) = language.as_string() {
-                        if language == "fr" || language.starts_with("fr-") {
-                            lang = Some(0);
-                            break;
-                        } else if language == "en" || language.starts_with("en-") {
-                            lang = Some(1);
-                            break;
-                        }
-                    }
-                }
-
-                lang.unwrap_or(0)
-            },
-        };
-        let calendar = match local_storage.get_item("setting-calendar").unwrap() {
-            Some(calendar) if calendar == "gregorian" => 0,
-            Some(calendar) if calendar == "republican" => 1,
-            _ => 0,
-        };
+        let lang = stored_lang(&local_storage);
+        let calendar = stored_calendar(&local_storage);
+        let agenda_view = stored_agenda_view(&local_storage);
+        let comment_sort = stored_comment_sort(&local_storage);
 
         SettingStore {
             theme: AtomicUsize::new(theme as usize),
             randomly_selected: AtomicBool::new(randomly_selected),
             lang: AtomicUsize::new(lang),
             calendar: AtomicUsize::new(calendar),
+            agenda_view: AtomicUsize::new(agenda_view),
+            comment_sort: AtomicUsize::new(comment_sort),
         }
     };
 }
@@ -107,11 +143,24 @@ pub enum CalendarKind {
     Republican,
 }
 
+/// How the agenda lays out events.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AgendaView {
+    /// Six days side by side
+    Week = 0,
+    Day,
+    Month,
+    /// Events of the next days, one after the other
+    List,
+}
+
 pub struct SettingStore {
     theme: AtomicUsize,
     randomly_selected: AtomicBool,
     lang: AtomicUsize,
     calendar: AtomicUsize,
+    agenda_view: AtomicUsize,
+    comment_sort: AtomicUsize,
 }
 
 impl SettingStore {
@@ -207,6 +256,77 @@ impl SettingStore {
         let storage = window().local_storage().unwrap().unwrap();
         storage.set_item("setting-calendar", calendar).unwrap();
     }
+
+    pub fn agenda_view(&self) -> AgendaView {
+        match self.agenda_view.load(Ordering::Relaxed) {
+            0 => AgendaView::Week,
+            1 => AgendaView::Day,
+            2 => AgendaView::Month,
+            3 => AgendaView::List,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn set_agenda_view(&self, view: usize) {
+        self.agenda_view.store(view, Ordering::Relaxed);
+
+        let view = match view {
+            0 => "week",
+            1 => "day",
+            2 => "month",
+            3 => "list",
+            _ => unreachable!(),
+        };
+
+        let storage = window().local_storage().unwrap().unwrap();
+        storage.set_item("setting-agenda-view", view).unwrap();
+    }
+
+    pub fn comment_sort(&self) -> CommentSort {
+        match self.comment_sort.load(Ordering::Relaxed) {
+            0 => CommentSort::Top,
+            1 => CommentSort::Newest,
+            2 => CommentSort::Controversial,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn set_comment_sort(&self, sort: CommentSort) {
+        self.comment_sort.store(sort as usize, Ordering::Relaxed);
+
+        let sort = match sort {
+            CommentSort::Top => "top",
+            CommentSort::Newest => "newest",
+            CommentSort::Controversial => "controversial",
+        };
+
+        let storage = window().local_storage().unwrap().unwrap();
+        storage.set_item("setting-comment-sort", sort).unwrap();
+    }
+
+    /// Loads the settings again after another tab changed them, and applies the theme.
+    pub fn reload(&self) {
+        let local_storage = window().local_storage().unwrap().unwrap();
+        let (theme, randomly_selected) = stored_theme(&local_storage);
+        self.theme.store(theme as usize, Ordering::Relaxed);
+        self.set_randomly_selected(randomly_selected);
+        self.lang.store(stored_lang(&local_storage), Ordering::Relaxed);
+        self.calendar.store(stored_calendar(&local_storage), Ordering::Relaxed);
+        self.agenda_view.store(stored_agenda_view(&local_storage), Ordering::Relaxed);
+        self.comment_sort.store(stored_comment_sort(&local_storage), Ordering::Relaxed);
+
+        let document = window().doc();
+        let html = document.first_element_child().unwrap();
+        if randomly_selected {
+            html.set_attribute("data-theme", self.theme().as_ref()).unwrap();
+        } else {
+            // Let theme-toggle.js pick between the stored theme and the one of the system
+            let _ = html.remove_attribute("data-theme");
+            if let Ok(reflect_theme) = Reflect::get(&document, &JsValue::from_str("reflectTheme")).and_then(|f| f.dyn_into::<Function>().map_err(JsValue::from)) {
+                let _ = reflect_theme.call0(&document);
+            }
+        }
+    }
 }
 
 pub enum Msg {
@@ -216,20 +336,25 @@ pub enum Msg {
     LogOut,
     LanguageChange(usize),
     CalendarChange(usize),
+    AgendaViewChange(usize),
     RegenerateToken,
     CopyIcs,
     OpenOnboarding,
+    OpenModeration,
 }
 
 #[derive(Properties, Clone)]
 pub struct SettingsProps {
     pub app_link: Scope<App>,
     pub user_info: Rc<Option<UserInfo>>,
+    /// Changes when another tab changed the settings.
+    #[prop_or_default]
+    pub settings_generation: usize,
 }
 
 impl PartialEq for SettingsProps {
     fn eq(&self, other: &Self) -> bool { 
-        self.user_info == other.user_info
+        self.user_info == other.user_info && self.settings_generation == other.settings_generation
     }
 }
 
@@ -248,6 +373,8 @@ impl Component for SettingsPage {
                 randomly_selected: AtomicBool::new(SETTINGS.randomly_selected()),
                 lang: AtomicUsize::new(SETTINGS.lang.load(Ordering::Relaxed)),
                 calendar: AtomicUsize::new(SETTINGS.calendar.load(Ordering::Relaxed)),
+                agenda_view: AtomicUsize::new(SETTINGS.agenda_view.load(Ordering::Relaxed)),
+                comment_sort: AtomicUsize::new(SETTINGS.comment_sort.load(Ordering::Relaxed)),
             },
         }
     }
@@ -255,11 +382,11 @@ impl Component for SettingsPage {
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::Confirm => {
-                ctx.props().app_link.send_message(AppMsg::SetPage(Page::Agenda));
+                ctx.props().app_link.send_message(AppMsg::SetPage(Page::Agenda { target: None }));
                 false
             }
             Msg::Cancel => {
-                ctx.props().app_link.send_message(AppMsg::SetPage(Page::Agenda));
+                ctx.props().app_link.send_message(AppMsg::SetPage(Page::Agenda { target: None }));
                 SETTINGS.set_theme(self.clone_storage.theme.load(Ordering::Relaxed));
                 SETTINGS.set_lang(self.clone_storage.lang.load(Ordering::Relaxed));
                 false
@@ -331,11 +458,15 @@ impl Component for SettingsPage {
                 SETTINGS.set_calendar(v);
                 true
             }
+            Msg::AgendaViewChange(v) => {
+                SETTINGS.set_agenda_view(v);
+                true
+            }
             Msg::RegenerateToken => {
                 let app_link = ctx.props().app_link.clone();
                 wasm_bindgen_futures::spawn_local(async move {
-                    if let Err(e) = api_post::<()>((), "regenerate-token").await {
-                        alert(format!("Impossible de régénérer le token : {e}"));
+                    if let Err(e) = api_call(RegenerateToken {}).await {
+                        e.report(Some("Impossible de régénérer le token"));
                     } else {
                         // Force refresh of user info to update token
                         <UserInfo as CachedData>::refresh(app_link);
@@ -374,6 +505,10 @@ impl Component for SettingsPage {
                 ctx.props().app_link.send_message(AppMsg::SetPage(Page::Onboarding));
                 false
             }
+            Msg::OpenModeration => {
+                ctx.props().app_link.send_message(AppMsg::SetPage(Page::Moderation));
+                false
+            }
         }
     }
 
@@ -396,6 +531,13 @@ impl Component for SettingsPage {
                 on_change = { ctx.link().callback(Msg::CalendarChange) }
                 selected = { SETTINGS.calendar() as usize } />
         };
+        let agenda_view_glider_selector = html! {
+            <GliderSelector
+                values = { vec![t("Semaine"), t("Jour"), t("Mois"), t("Liste")] }
+                on_change = { ctx.link().callback(Msg::AgendaViewChange) }
+                selected = { SETTINGS.agenda_view() as usize } />
+        };
+        let is_contributor = ctx.props().user_info.as_ref().as_ref().map(|u| UserDesc::new(u.uid, u.email.0.clone()).is_contributor()).unwrap_or(false);
         let token = if let Some(user_info) = ctx.props().user_info.as_ref() { user_info.token.clone() } else { String::new() };
 
         // Build ICS absolute URL
@@ -412,6 +554,7 @@ impl Component for SettingsPage {
             onclick_copy_ics = {ctx.link().callback(|_| Msg::CopyIcs)},
             onclick_regenerate_token = {ctx.link().callback(|_| Msg::RegenerateToken)},
             onclick_open_onboarding = {ctx.link().callback(|_| Msg::OpenOnboarding)},
+            onclick_open_moderation = {ctx.link().callback(|_| Msg::OpenModeration)},
             republican = {SETTINGS.calendar() == CalendarKind::Republican},
             ...
         )
@@ -0,0 +1,124 @@
+//! Keyboard shortcuts.
+//!
+//! Pages register their bindings when they are created and keep the returned [Shortcuts],
+//! so that the listener is removed once the page is left.
+
+use crate::prelude::*;
+use web_sys::KeyboardEvent;
+
+/// Every shortcut of the app, as `(keys, description)`, listed in the help overlay.
+pub const SHORTCUTS: &[(&str, &str)] = &[
+    ("← →", "Période précédente ou suivante de l'agenda"),
+    ("t", "Revenir à aujourd'hui"),
+    ("g", "Choisir une date"),
+    ("/", "Rechercher dans l'emploi du temps"),
+    ("Tab", "Passer d'un cours à l'autre, Entrée pour l'ouvrir"),
+    ("Échap", "Fermer le cours ouvert ou cette aide"),
+    ("?", "Afficher cette aide"),
+];
+
+/// Whether the user is typing in a field, where keys must keep their usual meaning.
+fn is_typing(e: &KeyboardEvent) -> bool {
+    let Some(target) = e.target().and_then(|target| target.dyn_into::<HtmlElement>().ok()) else { return false };
+    matches!(target.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT") || target.is_content_editable()
+}
+
+/// Whether the field the user is typing in holds some text, that `Escape` would throw away by leaving the page.
+fn has_typed_text(e: &KeyboardEvent) -> bool {
+    let Some(target) = e.target() else { return false };
+    if let Some(input) = target.dyn_ref::<HtmlInputElement>() {
+        return !input.value().is_empty();
+    }
+    if let Some(textarea) = target.dyn_ref::<web_sys::HtmlTextAreaElement>() {
+        return !textarea.value().is_empty();
+    }
+    target.dyn_ref::<HtmlElement>().is_some_and(|element| element.is_content_editable() && !element.text_content().unwrap_or_default().is_empty())
+}
+
+/// Bindings of a page, listening to the keys pressed until dropped.
+pub struct Shortcuts {
+    listener: Closure<dyn FnMut(KeyboardEvent)>,
+    capture: bool,
+}
+
+impl Shortcuts {
+    /// Calls `on_key` with the [key](KeyboardEvent::key) of each shortcut pressed, which returns whether it was handled.
+    /// Keys typed in fields and combined with modifiers are left alone, except `Escape` when the field is empty,
+    /// as well as keys already handled by the page, like `Escape` closing mention suggestions.
+    pub fn register(on_key: impl Fn(&str) -> bool + 'static) -> Shortcuts {
+        Self::listen(on_key, false)
+    }
+
+    /// Like [Shortcuts::register], but the bindings of pages are suspended until dropped, as for an overlay.
+    pub fn register_modal(on_key: impl Fn(&str) -> bool + 'static) -> Shortcuts {
+        Self::listen(on_key, true)
+    }
+
+    fn listen(on_key: impl Fn(&str) -> bool + 'static, modal: bool) -> Shortcuts {
+        let listener = Closure::<dyn FnMut(KeyboardEvent)>::new(move |e: KeyboardEvent| {
+            let key = e.key();
+            let ignored = e.default_prevented() || match key.as_str() {
+                "Escape" => is_typing(&e) && has_typed_text(&e),
+                _ => e.ctrl_key() || e.meta_key() || e.alt_key() || is_typing(&e),
+            };
+            if !ignored && on_key(&key) {
+                e.prevent_default();
+            }
+            if modal && !ignored {
+                e.stop_immediate_propagation();
+            }
+        });
+        // Listeners of overlays capture keys before those of pages receive them
+        window().add_event_listener_with_callback_and_bool("keydown", listener.as_ref().unchecked_ref(), modal).unwrap();
+        Shortcuts { listener, capture: modal }
+    }
+}
+
+impl Drop for Shortcuts {
+    fn drop(&mut self) {
+        let _ = window().remove_event_listener_with_callback_and_bool("keydown", self.listener.as_ref().unchecked_ref(), self.capture);
+    }
+}
+
+#[derive(Properties, Clone)]
+pub struct ShortcutHelpProps {
+    pub app_link: AppLink,
+}
+
+impl PartialEq for ShortcutHelpProps {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// Overlay listing the shortcuts, closed with `Escape` or `?`.
+#[function_component(ShortcutHelp)]
+pub fn shortcut_help(props: &ShortcutHelpProps) -> Html {
+    {
+        let app_link = props.app_link.clone();
+        use_effect_with((), move |_| {
+            let shortcuts = Shortcuts::register_modal(move |key| match key {
+                "Escape" | "?" => {
+                    app_link.send_message(AppMsg::ShowShortcutHelp(false));
+                    true
+                }
+                _ => false,
+            });
+            move || drop(shortcuts)
+        });
+    }
+
+    let rows = SHORTCUTS.iter().map(|(keys, description)| html! {
+        <tr>
+            <td>{ keys.split(' ').map(|key| html! { <kbd>{ key }</kbd> }).collect::<Html>() }</td>
+            <td>{ description }</td>
+        </tr>
+    }).collect::<Html>();
+    let onclick_close = props.app_link.callback(|_| AppMsg::ShowShortcutHelp(false));
+    let onclick_content = Callback::from(|e: MouseEvent| e.stop_propagation());
+
+    template_html!(
+        "src/shortcuts/shortcuts.html",
+        ...
+    )
+}
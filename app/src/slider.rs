@@ -192,6 +192,11 @@ impl SliderManager {
         self.update_right();
     }
 
+    /// Drops the cached day container, once it's no longer in the document.
+    pub fn forget_day_container(&mut self) {
+        self.day_container = None;
+    }
+
     fn get_cached_day_container(&mut self) -> Option<HtmlElement> {
         match &self.day_container {
             Some(day_container) => Some(day_container.clone()),
@@ -0,0 +1,302 @@
+//! Persistent storage for cached data.
+//!
+//! Data is stored in IndexedDB, which is asynchronous and has a much larger quota than localStorage.
+//! All records are loaded in memory by [Storage::init] before the app starts, so that reads stay synchronous.
+//! Writes update memory immediately and are persisted in the background.
+//! Other tabs are told about them through [TabSync], so that their copy stays up to date.
+//!
+//! When IndexedDB is unavailable (some private browsing modes), localStorage is used instead.
+
+use crate::prelude::*;
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransaction, IdbTransactionMode, IdbVersionChangeEvent};
+
+const DB_NAME: &str = "genda";
+const STORE: &str = "cache";
+/// Time after which the database is given up on once a tab running an older version blocks the upgrade.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(3);
+
+type Migration = fn(&IdbDatabase, &IdbTransaction) -> Result<(), JsValue>;
+
+/// Each migration upgrades the database from the previous version.
+/// Append a migration whenever the layout of the database changes: the version is the number of migrations.
+const MIGRATIONS: &[Migration] = &[
+    create_cache_store,
+    import_local_storage,
+];
+
+/// Keys of the data stored in localStorage before IndexedDB was used, by prefix.
+const LEGACY_PREFIXES: &[&str] = &["cached_", "last_updated_", "etag_", "last_modified_", "seen_comment_counts", "seen_mentions"];
+
+fn create_cache_store(db: &IdbDatabase, _transaction: &IdbTransaction) -> Result<(), JsValue> {
+    db.create_object_store(STORE)?;
+    Ok(())
+}
+
+thread_local! {
+    /// Keys of localStorage copied by [import_local_storage], deleted once the upgrade has committed.
+    static IMPORTED_KEYS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn import_local_storage(_db: &IdbDatabase, transaction: &IdbTransaction) -> Result<(), JsValue> {
+    let local_storage = window().local_storage()?.ok_or("localStorage unavailable")?;
+    let store = transaction.object_store(STORE)?;
+
+    // Events cached before the current format of the schedule are discarded
+    let outdated_events = local_storage.get_item("last_updated_events")?.and_then(|ts| ts.parse::<i64>().ok()).map(|ts| ts < 1684973740).unwrap_or(false);
+
+    let mut keys = Vec::new();
+    for i in 0..local_storage.length()? {
+        if let Some(key) = local_storage.key(i)? {
+            if LEGACY_PREFIXES.iter().any(|prefix| key.starts_with(prefix)) {
+                keys.push(key);
+            }
+        }
+    }
+    for key in &keys {
+        let Some(value) = local_storage.get_item(key)? else { continue };
+        if !(outdated_events && key.ends_with("_events") && key != "cached_friends_events") {
+            store.put_with_key(&JsValue::from_str(&value), &JsValue::from_str(key))?;
+        }
+    }
+    // The upgrade may still be aborted, so the keys are only deleted once it has committed
+    IMPORTED_KEYS.with(|imported| *imported.borrow_mut() = keys);
+    Ok(())
+}
+
+/// Deletes the keys of localStorage that the upgrade imported.
+fn forget_imported_keys() {
+    let keys = IMPORTED_KEYS.with(|imported| std::mem::take(&mut *imported.borrow_mut()));
+    let Ok(Some(local_storage)) = window().local_storage() else { return };
+    for key in keys {
+        let _ = local_storage.remove_item(&key);
+    }
+}
+
+/// Waits for the result of an IndexedDB request.
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    JsFuture::from(request_promise(request)).await
+}
+
+fn request_promise(request: &IdbRequest) -> js_sys::Promise {
+    js_sys::Promise::new(&mut |resolve: Function, reject: Function| {
+        let success_request = request.clone();
+        let onsuccess = Closure::once_into_js(move |_: JsValue| {
+            let _ = resolve.call1(&JsValue::NULL, &success_request.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let onerror = Closure::once_into_js(move |_: JsValue| {
+            let _ = reject.call1(&JsValue::NULL, &JsValue::from_str("IndexedDB request failed"));
+        });
+        request.set_onsuccess(Some(onsuccess.unchecked_ref()));
+        request.set_onerror(Some(onerror.unchecked_ref()));
+    })
+}
+
+fn migrate(request: &IdbOpenDbRequest, old_version: usize) -> Result<(), JsValue> {
+    let db: IdbDatabase = request.result()?.dyn_into()?;
+    let transaction = request.transaction().ok_or("No upgrade transaction")?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(old_version) {
+        log!("Migrating storage to version {}", version + 1);
+        migration(&db, &transaction)?;
+    }
+    Ok(())
+}
+
+async fn open_database() -> Result<IdbDatabase, JsValue> {
+    let factory = window().indexed_db()?.ok_or("IndexedDB unavailable")?;
+    let request = factory.open_with_u32(DB_NAME, MIGRATIONS.len() as u32)?;
+
+    let upgrade_request = request.clone();
+    let onupgradeneeded = Closure::<dyn FnMut(IdbVersionChangeEvent)>::new(move |e: IdbVersionChangeEvent| {
+        if let Err(error) = migrate(&upgrade_request, e.old_version() as usize) {
+            log!("Failed to migrate storage: {error:?}");
+            if let Some(transaction) = upgrade_request.transaction() {
+                let _ = transaction.abort();
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(onupgradeneeded.as_ref().unchecked_ref()));
+
+    // Another tab still has the previous version open, and has been asked to close it
+    let mut reject_blocked = None;
+    let blocked = js_sys::Promise::new(&mut |_, reject: Function| reject_blocked = Some(reject));
+    let reject_blocked = reject_blocked.unwrap();
+    // Once the database is open, nothing listens to `blocked` anymore, so it must not be rejected
+    let settled = Rc::new(Cell::new(false));
+    let timer_settled = Rc::clone(&settled);
+    let onblocked = Closure::<dyn FnMut()>::new(move || {
+        log!("Storage upgrade blocked by another tab");
+        let (reject_blocked, settled) = (reject_blocked.clone(), Rc::clone(&timer_settled));
+        let timeout = Closure::once_into_js(move || {
+            if !settled.get() {
+                let _ = reject_blocked.call1(&JsValue::NULL, &JsValue::from_str("Storage upgrade blocked by another tab"));
+            }
+        });
+        let _ = window().set_timeout_with_callback_and_timeout_and_arguments_0(timeout.unchecked_ref(), OPEN_TIMEOUT.as_millis() as i32);
+    });
+    request.set_onblocked(Some(onblocked.as_ref().unchecked_ref()));
+
+    let result = JsFuture::from(js_sys::Promise::race(&Array::of2(&request_promise(&request), &blocked))).await;
+    settled.set(true);
+    request.set_onblocked(None);
+    let db: IdbDatabase = match result {
+        Ok(db) => db.dyn_into()?,
+        Err(e) => {
+            // The upgrade may still happen once the other tab is closed: it must complete, then the database is closed so that it doesn't block other tabs.
+            // Imported keys are kept, since this tab now uses localStorage
+            onupgradeneeded.forget();
+            let late_request = request.clone();
+            let onsuccess = Closure::once_into_js(move |_: JsValue| {
+                if let Ok(db) = late_request.result().and_then(|db| db.dyn_into::<IdbDatabase>().map_err(JsValue::from)) {
+                    db.close();
+                }
+            });
+            request.set_onsuccess(Some(onsuccess.unchecked_ref()));
+            return Err(e);
+        }
+    };
+    request.set_onupgradeneeded(None);
+    forget_imported_keys();
+
+    // Let a newer version of the app upgrade the database, instead of blocking it until this tab is closed.
+    // Writes would fail once it's closed, so the tab is reloaded to run the newer version on the upgraded database.
+    // Pending writes are still committed, since closing waits for their transactions.
+    let closing_db = db.clone();
+    let onversionchange = Closure::<dyn FnMut()>::new(move || {
+        log!("Storage upgraded by another tab, reloading");
+        closing_db.close();
+        let _ = window().location().reload();
+    });
+    db.set_onversionchange(Some(onversionchange.as_ref().unchecked_ref()));
+    onversionchange.forget();
+
+    Ok(db)
+}
+
+/// Loads every record of the database in memory.
+async fn load_records(db: &IdbDatabase) -> Result<HashMap<String, String>, JsValue> {
+    let store = db.transaction_with_str(STORE)?.object_store(STORE)?;
+    let keys_request = store.get_all_keys()?;
+    let values_request = store.get_all()?;
+    let keys: Array = request_result(&keys_request).await?.dyn_into()?;
+    let values: Array = request_result(&values_request).await?.dyn_into()?;
+    Ok(keys.iter().zip(values.iter()).filter_map(|(key, value)| Some((key.as_string()?, value.as_string()?))).collect())
+}
+
+enum Backend {
+    IndexedDb { db: IdbDatabase, records: HashMap<String, String> },
+    LocalStorage(web_sys::Storage),
+}
+
+thread_local! {
+    static BACKEND: RefCell<Option<Backend>> = const { RefCell::new(None) };
+}
+
+pub struct Storage;
+
+impl Storage {
+    /// Opens the database and loads its content. Must be awaited before anything is read.
+    pub async fn init() {
+        let backend = match open_database().await {
+            Ok(db) => match load_records(&db).await {
+                Ok(records) => Some(Backend::IndexedDb { db, records }),
+                Err(e) => {
+                    log!("Failed to load storage, using localStorage: {e:?}");
+                    None
+                }
+            },
+            Err(e) => {
+                log!("IndexedDB unavailable, using localStorage: {e:?}");
+                None
+            }
+        };
+        let backend = backend.unwrap_or_else(|| Backend::LocalStorage(window().local_storage().unwrap().unwrap()));
+        BACKEND.with(|b| *b.borrow_mut() = Some(backend));
+    }
+
+    pub fn get(key: &str) -> Option<String> {
+        BACKEND.with(|backend| match backend.borrow().as_ref()? {
+            Backend::IndexedDb { records, .. } => records.get(key).cloned(),
+            Backend::LocalStorage(local_storage) => local_storage.get_item(key).ok().flatten(),
+        })
+    }
+
+    pub fn set(key: &str, value: String) {
+        BACKEND.with(|backend| match backend.borrow_mut().as_mut() {
+            Some(Backend::IndexedDb { db, records }) => {
+                if records.get(key) == Some(&value) {
+                    return;
+                }
+                if let Err(e) = Self::write(db, key, Some(&value)) {
+                    log!("Failed to store {key}: {e:?}");
+                }
+                TabSync::broadcast(key, Some(&value));
+                records.insert(key.to_string(), value);
+            }
+            Some(Backend::LocalStorage(local_storage)) => {
+                let _ = local_storage.set_item(key, &value);
+            }
+            None => log!("Storage used before being initialized"),
+        })
+    }
+
+    pub fn remove(key: &str) {
+        BACKEND.with(|backend| match backend.borrow_mut().as_mut() {
+            Some(Backend::IndexedDb { db, records }) => {
+                if !records.contains_key(key) {
+                    return;
+                }
+                if let Err(e) = Self::write(db, key, None) {
+                    log!("Failed to remove {key}: {e:?}");
+                }
+                TabSync::broadcast(key, None);
+                records.remove(key);
+            }
+            Some(Backend::LocalStorage(local_storage)) => {
+                let _ = local_storage.remove_item(key);
+            }
+            None => (),
+        })
+    }
+
+    /// Applies a change made by another tab, which has already persisted it.
+    pub fn receive(key: &str, value: Option<String>) {
+        BACKEND.with(|backend| {
+            if let Some(Backend::IndexedDb { records, .. }) = backend.borrow_mut().as_mut() {
+                match value {
+                    Some(value) => records.insert(key.to_string(), value),
+                    None => records.remove(key),
+                };
+            }
+        })
+    }
+
+    /// Keys of the records starting with `prefix`, in no particular order.
+    pub fn keys_with_prefix(prefix: &str) -> Vec<String> {
+        BACKEND.with(|backend| match backend.borrow().as_ref() {
+            Some(Backend::IndexedDb { records, .. }) => records.keys().filter(|key| key.starts_with(prefix)).cloned().collect(),
+            Some(Backend::LocalStorage(local_storage)) => (0..local_storage.length().unwrap_or(0))
+                .filter_map(|i| local_storage.key(i).ok().flatten())
+                .filter(|key| key.starts_with(prefix))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    pub fn get_json<T: DeserializeOwned>(key: &str) -> Option<T> {
+        serde_json::from_str(&Self::get(key)?).ok()
+    }
+
+    pub fn set_json<T: Serialize>(key: &str, value: &T) {
+        Self::set(key, serde_json::to_string(value).unwrap());
+    }
+
+    /// Persists a change in the background. The transaction commits by itself once the request is done.
+    fn write(db: &IdbDatabase, key: &str, value: Option<&str>) -> Result<(), JsValue> {
+        let store = db.transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)?.object_store(STORE)?;
+        match value {
+            Some(value) => store.put_with_key(&JsValue::from_str(value), &JsValue::from_str(key))?,
+            None => store.delete(&JsValue::from_str(key))?,
+        };
+        Ok(())
+    }
+}
@@ -15,37 +15,22 @@ pub const STOTRA_GET_PORTFOLIO_URL: &str = "https://stotra.dera.page/api/user/po
 static STOTRA_RANK_CACHED: AtomicIsize = AtomicIsize::new(0);
 
 pub async fn get_stotra_rank() -> Result<Option<usize>, ApiError> {
-    let mut request_init = RequestInit::new();
-    request_init.method("GET");
+    let request = ApiRequest::new("GET", STOTRA_GET_PORTFOLIO_URL);
 
     #[cfg(not(debug_assertions))]
-    request_init.credentials(web_sys::RequestCredentials::Include);
-
-    let request = Request::new_with_str_and_init(STOTRA_GET_PORTFOLIO_URL, &request_init).unwrap();
+    let request = request.with_credentials();
 
     #[cfg(debug_assertions)]
-    request.headers().set("X-Username", "test")?;
-
-    request.headers().set("Content-Type", "application/json")?;
-
-    let response = JsFuture::from(window().fetch_with_request(&request)).await?;
-    let response: web_sys::Response = response.clone().dyn_into().unwrap();
+    let request = request.header("X-Username", "test");
 
-    match response.status() {
-        200 => {
-            let text = JsFuture::from(response.text()?).await?;
-            let text: String = text.as_string().unwrap();
-            let values: HashMap<String, Value> = serde_json::from_str(&text).map_err(|e| ApiError::Unknown(JsValue::from_str(&e.to_string())))?;
-            let rank = values.get("rank").ok_or(ApiError::Unknown(JsValue::from_str("Failed to get rank")))?;
-            let rank = rank.as_f64().ok_or(ApiError::Unknown(JsValue::from_str("Failed to convert rank to usize")))? as isize;
-            STOTRA_RANK_CACHED.store(rank, Ordering::Relaxed);
-            if rank <= 0 {
-                Ok(None)
-            } else {
-                Ok(Some(rank as usize))
-            }
-        },
-        _ => Err(ApiError::Unknown(response.into()))
+    let values: HashMap<String, Value> = request.fetch().await?;
+    let rank = values.get("rank").ok_or(ApiError::Unknown(JsValue::from_str("Failed to get rank")))?;
+    let rank = rank.as_f64().ok_or(ApiError::Unknown(JsValue::from_str("Failed to convert rank to usize")))? as isize;
+    STOTRA_RANK_CACHED.store(rank, Ordering::Relaxed);
+    if rank <= 0 {
+        Ok(None)
+    } else {
+        Ok(Some(rank as usize))
     }
 }
 
@@ -0,0 +1,68 @@
+//! Keeps the tabs of the app in sync.
+//!
+//! Each tab loads [Storage] in memory, so writes are relayed to the other tabs through a `BroadcastChannel`,
+//! which then update their copy and tell the [App] what changed.
+//! Settings live in localStorage, whose changes other tabs already receive as `storage` events.
+
+use crate::prelude::*;
+use web_sys::{BroadcastChannel, MessageEvent, StorageEvent};
+
+const CHANNEL_NAME: &str = "genda-sync";
+
+/// Keys of localStorage holding settings.
+const SETTING_KEYS: &[&str] = &["setting-theme", "setting-lang", "setting-calendar", "setting-agenda-view", "setting-comment-sort", "auto-theme"];
+
+/// A record of [Storage] written by another tab.
+#[derive(Serialize, Deserialize)]
+struct Change {
+    key: String,
+    value: Option<String>,
+}
+
+thread_local! {
+    static CHANNEL: Option<BroadcastChannel> = BroadcastChannel::new(CHANNEL_NAME).ok();
+}
+
+pub struct TabSync;
+
+impl TabSync {
+    /// Starts applying the changes made by other tabs.
+    pub fn init(app_link: AppLink) {
+        let link = app_link.clone();
+        let onmessage = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+            let Some(change) = e.data().as_string().and_then(|data| serde_json::from_str::<Change>(&data).ok()) else { return };
+            Storage::receive(&change.key, change.value);
+            link.send_message(AppMsg::StorageChanged(change.key));
+        });
+        CHANNEL.with(|channel| match channel {
+            Some(channel) => channel.set_onmessage(Some(onmessage.as_ref().unchecked_ref())),
+            None => log!("BroadcastChannel unavailable, tabs won't be kept in sync"),
+        });
+        onmessage.forget();
+
+        let onstorage = Closure::<dyn FnMut(StorageEvent)>::new(move |e: StorageEvent| {
+            let Some(key) = e.key() else { return };
+            if SETTING_KEYS.contains(&key.as_str()) {
+                SETTINGS.reload();
+                app_link.send_message(AppMsg::SettingsChanged);
+            } else {
+                // Records of Storage are in localStorage when IndexedDB is unavailable
+                app_link.send_message(AppMsg::StorageChanged(key));
+            }
+        });
+        window().add_event_listener_with_callback("storage", onstorage.as_ref().unchecked_ref()).unwrap();
+        onstorage.forget();
+    }
+
+    /// Tells the other tabs that a record of [Storage] was written, or removed if `value` is `None`.
+    pub fn broadcast(key: &str, value: Option<&str>) {
+        let change = Change { key: key.to_string(), value: value.map(String::from) };
+        CHANNEL.with(|channel| {
+            if let Some(channel) = channel {
+                if let Err(e) = channel.post_message(&JsValue::from_str(&serde_json::to_string(&change).unwrap())) {
+                    log!("Failed to broadcast {key}: {e:?}");
+                }
+            }
+        });
+    }
+}
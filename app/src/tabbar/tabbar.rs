@@ -6,12 +6,15 @@ pub struct TabBar {}
 #[derive(Clone, Properties)]
 pub struct TabBarProps {
     pub app_link: AppLink,
-    pub bait_points: (bool, bool, bool),
+    /// Bait points of the agenda, friends and settings tabs, and the event in which the user has been mentioned.
+    pub bait_points: (bool, bool, bool, Option<String>),
+    /// Number of changes waiting for the network to be sent.
+    pub pending_mutations: usize,
     pub page: Page,
 }
 
 impl PartialEq for TabBarProps {
-    fn eq(&self, other: &Self) -> bool { self.page.eq(&other.page) && self.bait_points.eq(&other.bait_points) }
+    fn eq(&self, other: &Self) -> bool { self.page.eq(&other.page) && self.bait_points.eq(&other.bait_points) && self.pending_mutations == other.pending_mutations }
 }
 
 pub enum TabBarMsg {}
@@ -27,10 +30,22 @@ impl Component for TabBar {
     fn view(&self, ctx: &Context<Self>) -> Html {
         let page = &ctx.props().page;
         
-        let onclick_home = ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Agenda));
-        let mut home_classes = String::from(if matches!(page, Page::Agenda) {"tabbar-selected"} else {"tabbar-not-selected"});
+        let onclick_home = ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Agenda { target: None }));
+        let mut home_classes = String::from(if matches!(page, Page::Agenda { .. } | Page::Homeworks | Page::Search | Page::TeacherAgenda { .. } | Page::RoomAgenda { .. }) {"tabbar-selected"} else {"tabbar-not-selected"});
         if ctx.props().bait_points.0 { home_classes.push_str(" tabbar-with-bait"); }
 
+        let has_mention = ctx.props().bait_points.3.is_some();
+        let onclick_mention = {
+            let app_link = ctx.props().app_link.clone();
+            let eid = ctx.props().bait_points.3.clone();
+            Callback::from(move |e: MouseEvent| {
+                e.stop_propagation();
+                if let Some(eid) = eid.clone() {
+                    app_link.send_message(AppMsg::SetPage(Page::Event { eid }));
+                }
+            })
+        };
+
         let onclick_friends = ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Friends));
         let mut friends_classes = String::from(if matches!(page, Page::Friends | Page::FriendAgenda { .. }) {"tabbar-selected"} else {"tabbar-not-selected"});
         if ctx.props().bait_points.1 { friends_classes.push_str(" tabbar-with-bait"); }
@@ -39,9 +54,16 @@ impl Component for TabBar {
         let stotra_classes = String::from(if matches!(page, Page::Stotra) {"tabbar-selected"} else {"tabbar-not-selected"});
 
         let onclick_settings = ctx.props().app_link.callback(|_| AppMsg::SetPage(Page::Settings));
-        let mut settings_classes = String::from(if matches!(page, Page::Settings) {"tabbar-selected"} else {"tabbar-not-selected"});
+        let mut settings_classes = String::from(if matches!(page, Page::Settings | Page::Moderation) {"tabbar-selected"} else {"tabbar-not-selected"});
         if ctx.props().bait_points.2 { settings_classes.push_str(" tabbar-with-bait"); }
 
+        let pending_mutations = ctx.props().pending_mutations;
+        let has_pending = pending_mutations > 0;
+        let pending_text = match pending_mutations {
+            1 => String::from("1 modification en attente de connexion"),
+            n => format!("{n} modifications en attente de connexion"),
+        };
+
         template_html!("src/tabbar/tabbar.html", ...)
     }
 }
@@ -25,6 +25,11 @@ pub fn t(s: &'static str) -> &'static str {
         "Vendredi" => "Friday",
         "Samedi" => "Saturday",
         "Dimanche" => "Sunday",
+        "Semaine" => "Week",
+        "Jour" => "Day",
+        "Mois" => "Month",
+        "Liste" => "List",
+        "Aucun cours" => "No class",
         
         s => {
             log!("Untranslated string: {}", s);
@@ -91,16 +91,6 @@ pub fn has_event_on_day(events: &[RawEvent], current_day: NaiveDate, day_to_look
     }
 }
 
-pub trait HackTraitProfileUrl {
-    fn profile_url(&self) -> String;
-}
-
-impl HackTraitProfileUrl for UserDesc {
-    fn profile_url(&self) -> String {
-        format!("https://api.dicebear.com/5.x/identicon/svg?seed={}", self.uid)
-    }
-}
-
 pub fn now() -> i64 {
     (js_sys::Date::new_0().get_time() / 1000.0) as i64
 }
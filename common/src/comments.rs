@@ -0,0 +1,165 @@
+use crate::prelude::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Random number identifying the comment.
+    pub cid: u64,
+    /// Id of the parent comment, if any.
+    pub parent: Option<u64>,
+    /// Author of the comment.
+    pub author: UserDesc,
+    /// Content of the comment.
+    /// Written in the Markdown subset parsed by [parse_markdown].
+    pub content: String,
+    /// Timestamp of the comment creation.
+    pub creation_ts: i64,
+    /// Equal to `creation_ts` if the comment has never been edited.
+    pub last_edited_ts: i64,
+    /// Number of upvotes 
+    pub upvotes: u32,
+    /// Number of downvotes.
+    pub downvotes: u32,
+    /// The vote of the current user.
+    /// -1, 0 or 1.
+    pub vote: i8,
+    /// Whether a moderator hid the comment.
+    /// Hidden comments are only sent to their author and to contributors.
+    #[serde(default)]
+    pub hidden: bool,
+    /// Users mentioned with `@username` in the content.
+    #[serde(default)]
+    pub mentions: Vec<UserDesc>,
+    /// Set when the comment announces a homework.
+    #[serde(default)]
+    pub homework: Option<Homework>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CommentRequest {
+    /// Event id
+    pub eid: String,
+    /// Random number identifying the comment.
+    pub cid: Option<i64>,
+    /// Id of the parent comment, if any.
+    pub parent: Option<i64>,
+    /// Content of the comment.
+    /// Written in the Markdown subset parsed by [parse_markdown].
+    pub content: String,
+    /// Uids of the users mentioned in the content, as resolved by [resolve_mentions].
+    #[serde(default)]
+    pub mentions: Vec<i64>,
+    /// Random key generated by the client when creating a comment.
+    /// A creation sent again with the same key, because the first answer never arrived, is ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VoteRequest {
+    /// Event id
+    pub eid: String,
+    /// -1, 0 or 1.
+    pub vote: i8,
+    pub cid: u64,
+}
+
+/// A past version of a comment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentRevision {
+    /// Content of the comment at that time.
+    pub content: String,
+    /// Timestamp at which this content was written.
+    /// Equal to `creation_ts` for the first revision.
+    pub ts: i64,
+}
+
+/// How comments of a thread are ordered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommentSort {
+    /// Highest score first.
+    #[default]
+    Top,
+    /// Most recent first.
+    Newest,
+    /// Comments with many votes in both directions first.
+    Controversial,
+}
+
+impl Comment {
+    pub fn score(&self) -> i64 {
+        self.upvotes as i64 - self.downvotes as i64
+    }
+
+    /// Grows with the number of votes, but only if they are balanced.
+    pub fn controversy(&self) -> f64 {
+        if self.upvotes == 0 || self.downvotes == 0 {
+            return 0.0;
+        }
+        let magnitude = (self.upvotes + self.downvotes) as f64;
+        let balance = self.upvotes.min(self.downvotes) as f64 / self.upvotes.max(self.downvotes) as f64;
+        magnitude.powf(balance)
+    }
+}
+
+/// Sorts comments in place. Replies are sorted along their siblings, since threads are rebuilt from the order of the list.
+pub fn sort_comments(comments: &mut [Comment], sort: CommentSort) {
+    match sort {
+        CommentSort::Top => comments.sort_by(|a, b| b.score().cmp(&a.score()).then(b.creation_ts.cmp(&a.creation_ts))),
+        CommentSort::Newest => comments.sort_by_key(|c| std::cmp::Reverse(c.creation_ts)),
+        CommentSort::Controversial => comments.sort_by(|a, b| b.controversy().total_cmp(&a.controversy()).then(b.score().cmp(&a.score()))),
+    }
+}
+
+/// Counts all the replies of a comment, including replies to replies.
+pub fn count_replies(comments: &[Comment], cid: u64) -> usize {
+    let mut count = 0;
+    let mut to_visit = vec![cid];
+    while let Some(parent) = to_visit.pop() {
+        for child in comments.iter().filter(|c| c.parent == Some(parent)) {
+            count += 1;
+            to_visit.push(child.cid);
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(cid: u64, parent: Option<u64>, creation_ts: i64, upvotes: u32, downvotes: u32) -> Comment {
+        Comment {
+            cid,
+            parent,
+            author: UserDesc::new(1, String::from("edouard.foobar@insa-rouen.fr")),
+            content: String::new(),
+            creation_ts,
+            last_edited_ts: creation_ts,
+            upvotes,
+            downvotes,
+            vote: 0,
+            hidden: false,
+            mentions: Vec::new(),
+            homework: None,
+        }
+    }
+
+    #[test]
+    fn test_sort_comments() {
+        let mut comments = vec![comment(1, None, 10, 5, 0), comment(2, None, 30, 0, 0), comment(3, None, 20, 6, 5)];
+
+        sort_comments(&mut comments, CommentSort::Top);
+        assert_eq!(comments.iter().map(|c| c.cid).collect::<Vec<_>>(), vec![1, 3, 2]);
+        sort_comments(&mut comments, CommentSort::Newest);
+        assert_eq!(comments.iter().map(|c| c.cid).collect::<Vec<_>>(), vec![2, 3, 1]);
+        sort_comments(&mut comments, CommentSort::Controversial);
+        assert_eq!(comments.iter().map(|c| c.cid).collect::<Vec<_>>(), vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn test_count_replies() {
+        let comments = vec![comment(1, None, 0, 0, 0), comment(2, Some(1), 0, 0, 0), comment(3, Some(2), 0, 0, 0), comment(4, None, 0, 0, 0)];
+        assert_eq!(count_replies(&comments, 1), 2);
+        assert_eq!(count_replies(&comments, 4), 0);
+    }
+}
@@ -0,0 +1,133 @@
+/// A piece of a diff between two texts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffChunk {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Splits a text into words and runs of whitespace, so that joining the tokens gives the text back.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous_is_space = None;
+    for (i, c) in text.char_indices() {
+        let is_space = c.is_whitespace();
+        if previous_is_space.is_some_and(|previous| previous != is_space) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        previous_is_space = Some(is_space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+fn push_chunk(chunks: &mut Vec<DiffChunk>, chunk: DiffChunk) {
+    match (chunks.last_mut(), chunk) {
+        (Some(DiffChunk::Same(last)), DiffChunk::Same(text)) => last.push_str(&text),
+        (Some(DiffChunk::Added(last)), DiffChunk::Added(text)) => last.push_str(&text),
+        (Some(DiffChunk::Removed(last)), DiffChunk::Removed(text)) => last.push_str(&text),
+        (_, chunk) => chunks.push(chunk),
+    }
+}
+
+/// Largest LCS table computed by [word_diff], in cells.
+/// Beyond that, the changed part of the texts is shown as replaced as a whole, to keep memory bounded.
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// Computes a word-level diff between two texts, based on their longest common subsequence of words.
+/// Consecutive chunks of the same kind are merged.
+pub fn word_diff(old: &str, new: &str) -> Vec<DiffChunk> {
+    let old = tokenize(old);
+    let new = tokenize(new);
+
+    // Unchanged beginnings and ends are common, and don't need to be in the table
+    let prefix = old.iter().zip(&new).take_while(|(o, n)| o == n).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(o, n)| o == n).count();
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut chunks = Vec::new();
+    for token in &old[..prefix] {
+        push_chunk(&mut chunks, DiffChunk::Same(token.to_string()));
+    }
+    match (old_middle.len() + 1).saturating_mul(new_middle.len() + 1) <= MAX_LCS_CELLS {
+        true => lcs_diff(&mut chunks, old_middle, new_middle),
+        false => {
+            push_chunk(&mut chunks, DiffChunk::Removed(old_middle.concat()));
+            push_chunk(&mut chunks, DiffChunk::Added(new_middle.concat()));
+        }
+    }
+    for token in &old[old.len() - suffix..] {
+        push_chunk(&mut chunks, DiffChunk::Same(token.to_string()));
+    }
+    chunks
+}
+
+fn lcs_diff(chunks: &mut Vec<DiffChunk>, old: &[&str], new: &[&str]) {
+    // lcs[i * width + j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lcs = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = match old[i] == new[j] {
+                true => lcs[(i + 1) * width + j + 1] + 1,
+                false => lcs[(i + 1) * width + j].max(lcs[i * width + j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            push_chunk(chunks, DiffChunk::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            push_chunk(chunks, DiffChunk::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            push_chunk(chunks, DiffChunk::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    for token in &old[i..] {
+        push_chunk(chunks, DiffChunk::Removed(token.to_string()));
+    }
+    for token in &new[j..] {
+        push_chunk(chunks, DiffChunk::Added(token.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_diff() {
+        assert_eq!(word_diff("le cours est annulé", "le TD est reporté à demain"), vec![
+            DiffChunk::Same(String::from("le ")),
+            DiffChunk::Removed(String::from("cours")),
+            DiffChunk::Added(String::from("TD")),
+            DiffChunk::Same(String::from(" est ")),
+            DiffChunk::Removed(String::from("annulé")),
+            DiffChunk::Added(String::from("reporté à demain")),
+        ]);
+        assert_eq!(word_diff("", "salut"), vec![DiffChunk::Added(String::from("salut"))]);
+        assert_eq!(word_diff("same  text", "same  text"), vec![DiffChunk::Same(String::from("same  text"))]);
+    }
+
+    #[test]
+    fn test_word_diff_long_texts() {
+        let removed = vec!["a"; 2000].join(" ");
+        let added = vec!["b"; 2000].join(" ");
+        assert_eq!(word_diff(&format!("début {removed} fin"), &format!("début {added} fin")), vec![
+            DiffChunk::Same(String::from("début ")),
+            DiffChunk::Removed(removed),
+            DiffChunk::Added(added),
+            DiffChunk::Same(String::from(" fin")),
+        ]);
+    }
+}
@@ -0,0 +1,371 @@
+//! The contract between the app and the backend.
+//!
+//! Each endpoint is a type holding the parameters of the request, implementing [Endpoint].
+//! The app sends it with `api_call`, and the backend decodes it with [Endpoint::from_params] or as JSON,
+//! so that both sides agree on paths, methods and payloads at compile time.
+
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+impl std::str::FromStr for Method {
+    type Err = String;
+
+    fn from_str(method: &str) -> Result<Method, String> {
+        match method.to_uppercase().as_str() {
+            "GET" => Ok(Method::Get),
+            "POST" => Ok(Method::Post),
+            "DELETE" => Ok(Method::Delete),
+            _ => Err(format!("Unsupported method {method}")),
+        }
+    }
+}
+
+/// How the parameters of a request are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// In the query string of the url.
+    Query,
+    /// As an `application/x-www-form-urlencoded` body.
+    Form,
+    /// As a JSON body.
+    Json,
+}
+
+/// Header carrying the replay-protection counter of a request.
+///
+/// Each request must carry a counter greater than the last one the server accepted, or it is rejected with `counter_too_low`.
+/// The rejection carries the same header, set to the last accepted counter, so that the client can resync.
+pub const COUNTER_HEADER: &str = "X-Insa-Counter";
+
+/// Header holding the timestamp at which the session of the user expires, sent with the responses of the API.
+///
+/// This header and [SILENT_LOGIN_RETURN] are a contract proposed by this app, implemented by the mock server.
+/// Servers that don't send the header are assumed not to support silent logins, which the app then never tries.
+pub const SESSION_EXPIRES_HEADER: &str = "X-Insa-Session-Expires";
+
+/// Page the login flow redirects to when given `silent=true`, once it is done.
+/// It is loaded in a hidden frame and tells the app whether the session was renewed without any interaction.
+/// The login flow adds `error=login_required` to its query when the user has to log in again.
+/// Like [SESSION_EXPIRES_HEADER], it is only relied upon by servers sending that header.
+pub const SILENT_LOGIN_RETURN: &str = "/silent-login.html";
+
+/// Errors any endpoint can return, on top of the ones listed in [Endpoint::ERRORS].
+pub const COMMON_ERRORS: &[&str] = &[
+    "counter_too_low",
+    "firewall_ban",
+    "invalid_api_key",
+    "api_key_does_not_exist",
+    "api_key_expired",
+    "authentification_required",
+];
+
+pub trait Endpoint: Serialize + DeserializeOwned {
+    /// Path relative to `/api/`.
+    const PATH: &'static str;
+    const METHOD: Method;
+    const ENCODING: Encoding;
+    /// Kinds of the errors specific to this endpoint.
+    const ERRORS: &'static [&'static str] = &[];
+    type Response: Serialize + DeserializeOwned;
+
+    /// Parameters sent in the query string or as a form, depending on [Endpoint::ENCODING].
+    fn params(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Decodes the parameters sent in the query string or as a form.
+    fn from_params(_params: &HashMap<String, String>) -> Option<Self> {
+        None
+    }
+
+    /// The response of a request answered with an empty body.
+    /// It is only valid for endpoints answering nothing, whose response deserializes from a unit, like `()`.
+    fn empty_response() -> Option<Self::Response> {
+        Self::Response::deserialize(serde::de::value::UnitDeserializer::<serde::de::value::Error>::new()).ok()
+    }
+
+    /// Absolute url of the request, including the query string.
+    fn url(&self) -> String {
+        let params = self.params();
+        match Self::ENCODING {
+            Encoding::Query if !params.is_empty() => format!("/api/{}?{}", Self::PATH, encode_params(&params)),
+            _ => format!("/api/{}", Self::PATH),
+        }
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, like `encodeURIComponent`.
+pub fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Reverses [percent_encode]. A `+` is decoded as a space, as in forms.
+pub fn percent_decode(s: &str) -> String {
+    decode(s, true)
+}
+
+/// Reverses [percent_encode] for a segment of a path, where a `+` is kept as is.
+pub fn percent_decode_segment(s: &str) -> String {
+    decode(s, false)
+}
+
+fn decode(s: &str, plus_as_space: bool) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Encodes parameters as `a=1&b=2`, for a query string or a form body.
+pub fn encode_params(params: &[(&str, String)]) -> String {
+    params.iter().map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value))).collect::<Vec<_>>().join("&")
+}
+
+/// Decodes `a=1&b=2`, from a query string or a form body.
+pub fn decode_params(params: &str) -> HashMap<String, String> {
+    params
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (percent_decode(key), percent_decode(value)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Declares an endpoint.
+///
+/// Endpoints sending their parameters in the query string or as a form get a struct whose fields are the parameters.
+/// Fields must implement `Display` and `FromStr`.
+/// JSON endpoints reuse an existing type as the body.
+macro_rules! endpoint {
+    ($(#[$meta:meta])* $name:ident { $($field:ident: $ty:ty),* $(,)? }, $method:ident $path:literal, $encoding:ident -> $response:ty $(, errors: [$($error:literal),*])?) => {
+        $(#[$meta])*
+        #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+        pub struct $name {
+            $(pub $field: $ty),*
+        }
+
+        impl Endpoint for $name {
+            const PATH: &'static str = $path;
+            const METHOD: Method = Method::$method;
+            const ENCODING: Encoding = Encoding::$encoding;
+            const ERRORS: &'static [&'static str] = &[$($($error),*)?];
+            type Response = $response;
+
+            fn params(&self) -> Vec<(&'static str, String)> {
+                vec![$((stringify!($field), self.$field.to_string())),*]
+            }
+
+            #[allow(unused_variables)]
+            fn from_params(params: &HashMap<String, String>) -> Option<Self> {
+                Some($name { $($field: params.get(stringify!($field))?.parse().ok()?),* })
+            }
+        }
+    };
+    ($name:ty, $method:ident $path:literal, Json -> $response:ty $(, errors: [$($error:literal),*])?) => {
+        impl Endpoint for $name {
+            const PATH: &'static str = $path;
+            const METHOD: Method = Method::$method;
+            const ENCODING: Encoding = Encoding::Json;
+            const ERRORS: &'static [&'static str] = &[$($($error),*)?];
+            type Response = $response;
+        }
+    };
+}
+
+/// The schedule of the current user, or of a friend.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GetSchedule {
+    /// Uid of the friend whose schedule is requested.
+    pub uid: Option<i64>,
+}
+
+impl Endpoint for GetSchedule {
+    const PATH: &'static str = "schedule";
+    const METHOD: Method = Method::Get;
+    const ENCODING: Encoding = Encoding::Query;
+    const ERRORS: &'static [&'static str] = &["not_friend"];
+    type Response = Vec<Event>;
+
+    fn params(&self) -> Vec<(&'static str, String)> {
+        self.uid.map(|uid| vec![("uid", uid.to_string())]).unwrap_or_default()
+    }
+
+    fn from_params(params: &HashMap<String, String>) -> Option<Self> {
+        let uid = match params.get("uid") {
+            Some(uid) => Some(uid.parse().ok()?),
+            None => None,
+        };
+        Some(GetSchedule { uid })
+    }
+}
+
+endpoint!(
+    /// Changes to the schedule of the current user since a timestamp.
+    /// Fails with `changes_unavailable` when the server no longer knows what changed, the full schedule must then be fetched.
+    GetScheduleChanges { since: i64 }, Get "schedule/changes", Query -> ScheduleChanges, errors: ["changes_unavailable"]
+);
+
+endpoint!(
+    /// Events of a teacher, across every group.
+    /// Servers may not support it, the app then shows the events of the teacher it already knows.
+    GetTeacherSchedule { teacher: String }, Get "schedule/teacher", Query -> Vec<Event>
+);
+endpoint!(
+    /// Events taking place in a room, identified by [Location::room_id], across every group.
+    /// Servers may not support it, the app then shows the events of the room it already knows.
+    GetRoomSchedule { room: String }, Get "schedule/room", Query -> Vec<Event>
+);
+
+/// Colors chosen by the user, as `(summary, color)` pairs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SetColors(pub Vec<(String, String)>);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SetGroups(pub Groups);
+
+endpoint!(GetUserInfo {}, Get "user-info", Query -> UserInfo);
+endpoint!(RegenerateToken {}, Post "regenerate-token", Query -> ());
+endpoint!(SetGroups, Post "set-groups", Json -> ());
+endpoint!(GetColors {}, Get "colors", Query -> HashMap<String, String>);
+endpoint!(SetColors, Post "colors", Json -> ());
+
+endpoint!(GetFriends {}, Get "friends/", Query -> FriendLists);
+endpoint!(RequestFriend { email: String }, Post "friends/request", Form -> (), errors: ["user_not_found"]);
+endpoint!(AcceptFriend { uid: i64 }, Post "friends/accept", Form -> (), errors: ["not_found"]);
+endpoint!(DeclineFriend { uid: i64 }, Post "friends/decline", Form -> (), errors: ["not_found"]);
+endpoint!(RemoveFriend { uid: i64 }, Post "friends/remove", Form -> ());
+
+endpoint!(
+    /// Number of comments of each event.
+    GetCommentCounts {}, Get "textbook-course-ids", Query -> HashMap<String, usize>
+);
+endpoint!(GetComments { eid: String }, Get "comments", Query -> Vec<Comment>, errors: ["textbook_not_found"]);
+endpoint!(CommentRequest, Post "comment", Json -> (), errors: ["not_found", "forbidden"]);
+endpoint!(DeleteComment { eid: String, cid: u64 }, Delete "comment", Query -> (), errors: ["not_found", "forbidden"]);
+endpoint!(VoteRequest, Post "vote", Json -> (), errors: ["not_found"]);
+endpoint!(GetCommentRevisions { eid: String, cid: u64 }, Get "comment/revisions", Query -> Vec<CommentRevision>, errors: ["not_found"]);
+endpoint!(GetMentions {}, Get "mentions", Query -> Vec<Mention>);
+endpoint!(GetHomeworks {}, Get "homeworks", Query -> Vec<HomeworkItem>);
+endpoint!(HomeworkRequest, Post "comment/homework", Json -> (), errors: ["not_found"]);
+
+endpoint!(ReportRequest, Post "report", Json -> (), errors: ["not_found"]);
+endpoint!(GetReports {}, Get "moderation/reports", Query -> Vec<ReportedComment>);
+endpoint!(GetModerationLog {}, Get "moderation/log", Query -> Vec<ModerationLogEntry>);
+endpoint!(ModerationRequest, Post "moderation/action", Json -> (), errors: ["not_found"]);
+
+/// Description of an endpoint, without its types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointInfo {
+    pub path: &'static str,
+    pub method: Method,
+    pub encoding: Encoding,
+    pub errors: &'static [&'static str],
+}
+
+const fn info<E: Endpoint>() -> EndpointInfo {
+    EndpointInfo { path: E::PATH, method: E::METHOD, encoding: E::ENCODING, errors: E::ERRORS }
+}
+
+/// Every endpoint of the API.
+pub const ENDPOINTS: &[EndpointInfo] = &[
+    info::<GetSchedule>(),
+    info::<GetScheduleChanges>(),
+    info::<GetTeacherSchedule>(),
+    info::<GetRoomSchedule>(),
+    info::<GetUserInfo>(),
+    info::<RegenerateToken>(),
+    info::<SetGroups>(),
+    info::<GetColors>(),
+    info::<SetColors>(),
+    info::<GetFriends>(),
+    info::<RequestFriend>(),
+    info::<AcceptFriend>(),
+    info::<DeclineFriend>(),
+    info::<RemoveFriend>(),
+    info::<GetCommentCounts>(),
+    info::<GetComments>(),
+    info::<CommentRequest>(),
+    info::<DeleteComment>(),
+    info::<VoteRequest>(),
+    info::<GetCommentRevisions>(),
+    info::<GetMentions>(),
+    info::<GetHomeworks>(),
+    info::<HomeworkRequest>(),
+    info::<ReportRequest>(),
+    info::<GetReports>(),
+    info::<GetModerationLog>(),
+    info::<ModerationRequest>(),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints_are_unique() {
+        for (i, a) in ENDPOINTS.iter().enumerate() {
+            for b in &ENDPOINTS[i + 1..] {
+                assert!((a.method, a.path) != (b.method, b.path), "{} /api/{} is declared twice", a.method.as_str(), a.path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_params() {
+        let request = GetComments { eid: String::from("analyse cm/1&2") };
+        assert_eq!(request.url(), "/api/comments?eid=analyse%20cm%2F1%262");
+        let params = decode_params(request.url().split_once('?').unwrap().1);
+        assert_eq!(GetComments::from_params(&params), Some(request));
+
+        assert_eq!(GetSchedule::default().url(), "/api/schedule");
+        assert_eq!(GetSchedule::from_params(&decode_params("uid=4")), Some(GetSchedule { uid: Some(4) }));
+        assert_eq!(DeleteComment::from_params(&decode_params("eid=a&cid=nope")), None);
+        assert_eq!(encode_params(&RequestFriend { email: String::from("a.b@insa-rouen.fr") }.params()), "email=a.b%40insa-rouen.fr");
+    }
+
+    #[test]
+    fn test_empty_response() {
+        assert_eq!(RequestFriend::empty_response(), Some(()));
+        assert_eq!(GetComments::empty_response(), None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Errors returned by the backend.
+//!
+//! Errors are sent as JSON objects whose `kind` field identifies them.
+//! [ApiErrorKind] lists the kinds the app knows about, and what it should do when receiving them.
+
+use crate::prelude::*;
+
+/// What the app should do when a request fails with a given error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The request can be sent again once the cause of the error has been fixed locally.
+    Retry,
+    /// The credentials of the user are no longer valid, they must log in again.
+    Reauthenticate,
+    /// Too many requests were sent, the app must wait before sending more.
+    BackOff,
+    /// Nothing can be done automatically, the message is shown to the user.
+    Surface,
+}
+
+macro_rules! api_error_kinds {
+    ($($variant:ident $kind:literal => $recovery:ident, $message_fr:literal, $message_en:literal;)*) => {
+        /// Every error the backend is known to return.
+        /// Errors with an unknown `kind` are kept as [ApiErrorKind::Other].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        pub enum ApiErrorKind {
+            $($variant,)*
+            Other(String),
+        }
+
+        impl ApiErrorKind {
+            /// Every known kind, in declaration order.
+            pub const ALL: &'static [ApiErrorKind] = &[$(ApiErrorKind::$variant),*];
+
+            /// The `kind` field of the error, as sent by the backend.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(ApiErrorKind::$variant => $kind,)*
+                    ApiErrorKind::Other(kind) => kind,
+                }
+            }
+
+            pub fn recovery(&self) -> Recovery {
+                match self {
+                    $(ApiErrorKind::$variant => Recovery::$recovery,)*
+                    ApiErrorKind::Other(_) => Recovery::Surface,
+                }
+            }
+
+            /// Message describing the error, in French if `locale` is `"fr"` and in English otherwise.
+            pub fn message(&self, locale: &str) -> &'static str {
+                match (self, locale) {
+                    $((ApiErrorKind::$variant, "fr") => $message_fr,
+                    (ApiErrorKind::$variant, _) => $message_en,)*
+                    (ApiErrorKind::Other(_), "fr") => "Une erreur inattendue est survenue",
+                    (ApiErrorKind::Other(_), _) => "An unexpected error occurred",
+                }
+            }
+        }
+
+        impl From<&str> for ApiErrorKind {
+            fn from(kind: &str) -> Self {
+                match kind {
+                    $($kind => ApiErrorKind::$variant,)*
+                    other => ApiErrorKind::Other(other.to_string()),
+                }
+            }
+        }
+    };
+}
+
+api_error_kinds! {
+    CounterTooLow "counter_too_low" => Retry, "Le compteur de requêtes est trop bas", "The request counter is too low";
+    FirewallBan "firewall_ban" => BackOff, "Vous envoyez trop de requêtes ! Veuillez réessayer plus tard.", "You are sending too many requests! Please try again later.";
+    InvalidApiKey "invalid_api_key" => Reauthenticate, "Clé d'API invalide", "Invalid API key";
+    ApiKeyDoesNotExist "api_key_does_not_exist" => Reauthenticate, "Cette clé d'API n'existe pas", "This API key does not exist";
+    ApiKeyExpired "api_key_expired" => Reauthenticate, "Cette clé d'API a expiré", "This API key has expired";
+    AuthentificationRequired "authentification_required" => Reauthenticate, "Authentification requise", "Authentication required";
+    BadRequest "bad_request" => Surface, "Requête invalide", "Invalid request";
+    MethodNotAllowed "method_not_allowed" => Surface, "Méthode non autorisée", "Method not allowed";
+    NotFound "not_found" => Surface, "Élément introuvable", "Not found";
+    Forbidden "forbidden" => Surface, "Action interdite", "Forbidden";
+    UserNotFound "user_not_found" => Surface, "Utilisateur introuvable", "User not found";
+    NotFriend "not_friend" => Surface, "Cet utilisateur n'est pas votre ami", "This user is not your friend";
+    TextbookNotFound "textbook_not_found" => Surface, "Aucun commentaire pour cet événement", "No comments for this event";
+    ChangesUnavailable "changes_unavailable" => Surface, "Les modifications ne sont plus disponibles", "Changes are no longer available";
+    InternalError "internal_error" => Surface, "Erreur interne du serveur", "Internal server error";
+}
+
+impl From<String> for ApiErrorKind {
+    fn from(kind: String) -> Self {
+        ApiErrorKind::from(kind.as_str())
+    }
+}
+
+impl From<ApiErrorKind> for String {
+    fn from(kind: ApiErrorKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kinds() {
+        for kind in ApiErrorKind::ALL {
+            assert_eq!(&ApiErrorKind::from(kind.as_str()), kind);
+        }
+        assert_eq!(ApiErrorKind::from("nope"), ApiErrorKind::Other(String::from("nope")));
+
+        // Every error declared by the endpoints must be known
+        let declared = ENDPOINTS.iter().flat_map(|e| e.errors.iter()).chain(COMMON_ERRORS.iter());
+        for kind in declared {
+            assert!(!matches!(ApiErrorKind::from(*kind), ApiErrorKind::Other(_)), "{kind} is not an ApiErrorKind");
+        }
+    }
+}
@@ -24,7 +24,7 @@ pub struct Event {
 
 impl PartialOrd for Event {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.eid.cmp(&other.eid))
+        Some(self.cmp(other))
     }
 }
 
@@ -33,3 +33,54 @@ impl Ord for Event {
         self.eid.cmp(&other.eid)
     }
 }
+
+/// Changes made to a schedule since a given time, sent instead of the whole schedule when the client is up to date enough.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ScheduleChanges {
+    /// New and modified events.
+    pub updated: Vec<Event>,
+    /// Ids of the deleted events.
+    pub removed: Vec<String>,
+}
+
+impl ScheduleChanges {
+    /// Merges the changes into a schedule, by event id. Events are kept sorted by start time.
+    /// Applying the same changes twice has no effect.
+    pub fn apply(self, events: &mut Vec<Event>) {
+        events.retain(|e| !self.removed.contains(&e.eid) && !self.updated.iter().any(|u| u.eid == e.eid));
+        events.extend(self.updated);
+        events.sort_by_key(|e| e.start_unixtime);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(eid: &str, start_unixtime: u64) -> Event {
+        Event {
+            summary: String::from("Analyse"),
+            kind: None,
+            number: None,
+            teachers: Vec::new(),
+            groups: Groups::new(),
+            location: None,
+            start_unixtime,
+            end_unixtime: start_unixtime + 5400,
+            eid: eid.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let mut events = vec![event("a", 10), event("b", 20), event("c", 30)];
+        let changes = ScheduleChanges {
+            updated: vec![event("b", 40), event("d", 5)],
+            removed: vec![String::from("c")],
+        };
+        changes.clone().apply(&mut events);
+        changes.apply(&mut events);
+        let order: Vec<(&str, u64)> = events.iter().map(|e| (e.eid.as_str(), e.start_unixtime)).collect();
+        assert_eq!(order, vec![("d", 5), ("a", 10), ("b", 40)]);
+    }
+}
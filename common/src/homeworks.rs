@@ -1,38 +1,65 @@
 use crate::prelude::*;
 
+/// A homework announced in a comment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Homework {
+    /// Timestamp at which the homework is due.
+    pub due: i64,
+    /// Event at which the homework is due, when it targets a session of the course.
+    pub due_eid: Option<String>,
+}
+
+/// Sent to mark a comment as homework, or to unmark it when `homework` is `None`.
+/// Only the author of the comment and contributors are allowed to do so.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct Comment {
-    /// Random number identifying the comment.
+pub struct HomeworkRequest {
+    /// Event id
+    pub eid: String,
     pub cid: u64,
-    /// Id of the parent comment, if any.
-    pub parent: Option<u64>,
-    /// Author of the comment.
-    pub author: UserDesc,
-    /// Content of the comment.
-    /// Markdown isn't supported but will be eventually.
-    pub content: String,
-    /// Timestamp of the comment creation.
-    pub creation_ts: i64,
-    /// Equal to `creation_ts` if the comment has never been edited.
-    pub last_edited_ts: i64,
-    /// Number of upvotes 
-    pub upvotes: u32,
-    /// Number of downvotes.
-    pub downvotes: u32,
-    /// The vote of the current user.
-    /// -1, 0 or 1.
-    pub vote: i8,
+    pub homework: Option<Homework>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct CommentRequest {
-    /// Event id
+/// A homework of the user, as listed by the homework page.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HomeworkItem {
+    /// Event in which the homework was announced.
     pub eid: String,
-    /// Random number identifying the comment.
-    pub cid: Option<i64>,
-    /// Id of the parent comment, if any.
-    pub parent: Option<i64>,
+    /// Comment announcing the homework.
+    pub cid: u64,
+    /// Summary of the course.
+    pub summary: String,
     /// Content of the comment.
-    /// Markdown isn't supported but will be eventually.
     pub content: String,
+    pub author: UserDesc,
+    pub homework: Homework,
+}
+
+/// Finds the next session of the same course, starting after the end of the given event.
+pub fn next_session<'a>(events: &'a [Event], event: &Event) -> Option<&'a Event> {
+    events.iter()
+        .filter(|e| e.summary == event.summary && e.eid != event.eid && e.start_unixtime >= event.end_unixtime)
+        .min_by_key(|e| e.start_unixtime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_session() {
+        let event = |eid: &str, summary: &str, start: u64| Event {
+            summary: summary.to_string(),
+            kind: None,
+            number: None,
+            teachers: Vec::new(),
+            groups: Groups::default(),
+            location: None,
+            start_unixtime: start,
+            end_unixtime: start + 3600,
+            eid: eid.to_string(),
+        };
+        let events = vec![event("a", "Maths", 0), event("b", "Physique", 7200), event("c", "Maths", 86400), event("d", "Maths", 172800)];
+        assert_eq!(next_session(&events, &events[0]).map(|e| e.eid.as_str()), Some("c"));
+        assert_eq!(next_session(&events, &events[3]), None);
+    }
 }
@@ -1,11 +1,21 @@
 mod announcements;
+mod comments;
+mod diff;
+mod endpoints;
+mod errors;
 mod homeworks;
 mod events;
 mod friends;
 mod groups;
 mod location;
+mod markdown;
+mod mentions;
+mod moderation;
+mod pages;
 mod password;
 mod prelude;
+mod push;
+mod search;
 mod surveys;
 mod user_desc;
 mod user_info;
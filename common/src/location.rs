@@ -8,6 +8,18 @@ pub enum Building {
     Darwin,
 }
 
+impl Building {
+    /// Short name, as written on room doors.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Building::Magellan => "Ma",
+            Building::DumontDurville => "Du",
+            Building::Bougainville => "Bo",
+            Building::Darwin => "Da",
+        }
+    }
+}
+
 impl std::fmt::Display for Building {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -50,6 +62,18 @@ pub enum Location {
     Unparsed(String),
 }
 
+impl Location {
+    /// Compact name of the room, such as `Ma-B-R1-12`, identifying it in urls.
+    pub fn room_id(&self) -> String {
+        match self {
+            Location::Parsed { building, building_area, level, room_number } => {
+                format!("{}-{building_area}-{level}-{room_number}", building.code())
+            }
+            Location::Unparsed(s) => s.clone(),
+        }
+    }
+}
+
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
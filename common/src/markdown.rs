@@ -0,0 +1,379 @@
+//! A small and safe subset of Markdown, used for comments.
+//!
+//! Supported: emphasis, strong emphasis, inline code, code blocks, lists, links and quotes.
+//! Raw HTML is never interpreted: it is kept as text and must be escaped by the renderer.
+
+use std::collections::HashMap;
+
+/// Maximum nesting of quotes and emphasis. Deeper content is kept as text.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownInline {
+    Text(String),
+    Emphasis(Vec<MarkdownInline>),
+    Strong(Vec<MarkdownInline>),
+    Code(String),
+    /// The url is guaranteed to be safe (see [is_safe_url]).
+    Link { text: Vec<MarkdownInline>, url: String },
+    LineBreak,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownBlock {
+    Paragraph(Vec<MarkdownInline>),
+    Quote(Vec<MarkdownBlock>),
+    List { ordered: bool, items: Vec<Vec<MarkdownInline>> },
+    CodeBlock(String),
+}
+
+/// Only links to the web and to mail addresses are allowed.
+/// This prevents `javascript:` and `data:` urls from being rendered as links.
+pub fn is_safe_url(url: &str) -> bool {
+    let lowercase = url.trim().to_lowercase();
+    (lowercase.starts_with("https://") || lowercase.starts_with("http://") || lowercase.starts_with("mailto:"))
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '\'' || c == '<' || c == '>')
+}
+
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    let trimmed = line.trim_start();
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(bullet) {
+            return Some((false, rest));
+        }
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && digits <= 9 {
+        if let Some(rest) = trimmed[digits..].strip_prefix(". ") {
+            return Some((true, rest));
+        }
+    }
+    None
+}
+
+fn quote_line(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn parse_blocks(input: &str, depth: usize) -> Vec<MarkdownBlock> {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    fn flush(paragraph: &mut Vec<&str>, blocks: &mut Vec<MarkdownBlock>, depth: usize) {
+        if !paragraph.is_empty() {
+            blocks.push(MarkdownBlock::Paragraph(parse_inlines(&paragraph.join("\n"), depth)));
+            paragraph.clear();
+        }
+    }
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if line.trim().is_empty() {
+            flush(&mut paragraph, &mut blocks, depth);
+            i += 1;
+        } else if line.trim_start().starts_with("```") {
+            flush(&mut paragraph, &mut blocks, depth);
+            let mut code = Vec::new();
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                code.push(lines[i]);
+                i += 1;
+            }
+            i += 1; // Skip the closing fence
+            blocks.push(MarkdownBlock::CodeBlock(code.join("\n")));
+        } else if quote_line(line).is_some() && depth < MAX_DEPTH {
+            flush(&mut paragraph, &mut blocks, depth);
+            let mut quoted = Vec::new();
+            while let Some(content) = lines.get(i).and_then(|l| quote_line(l)) {
+                quoted.push(content);
+                i += 1;
+            }
+            blocks.push(MarkdownBlock::Quote(parse_blocks(&quoted.join("\n"), depth + 1)));
+        } else if let Some((ordered, _)) = list_item(line) {
+            flush(&mut paragraph, &mut blocks, depth);
+            let mut items = Vec::new();
+            while let Some((item_ordered, content)) = lines.get(i).and_then(|l| list_item(l)) {
+                if item_ordered != ordered {
+                    break;
+                }
+                items.push(parse_inlines(content, depth));
+                i += 1;
+            }
+            blocks.push(MarkdownBlock::List { ordered, items });
+        } else {
+            paragraph.push(line);
+            i += 1;
+        }
+    }
+    flush(&mut paragraph, &mut blocks, depth);
+
+    blocks
+}
+
+fn push_text(inlines: &mut Vec<MarkdownInline>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(MarkdownInline::Text(last)) = inlines.last_mut() {
+        last.push_str(text);
+    } else {
+        inlines.push(MarkdownInline::Text(text.to_string()));
+    }
+}
+
+/// Underscores only delimit emphasis at word boundaries so that `snake_case_names` are left untouched.
+fn is_word_boundary(input: &str, idx: usize, opening: bool) -> bool {
+    if opening {
+        !input[..idx].chars().next_back().map(|c| c.is_alphanumeric()).unwrap_or(false)
+    } else {
+        !input[idx + 1..].chars().next().map(|c| c.is_alphanumeric()).unwrap_or(false)
+    }
+}
+
+fn find_closing(input: &str, from: usize, delimiter: &str) -> Option<usize> {
+    let mut search = from;
+    while let Some(pos) = input[search..].find(delimiter) {
+        let idx = search + pos;
+        let escaped = input[..idx].ends_with('\\');
+        let boundary = delimiter != "_" || is_word_boundary(input, idx, false);
+        if idx > from && !escaped && boundary {
+            return Some(idx);
+        }
+        search = idx + delimiter.len();
+    }
+    None
+}
+
+/// Positions of the `]` closing each `[`, in a single pass.
+/// Brackets escaped with a backslash are ignored, and brackets never match across lines.
+fn match_brackets(input: &str) -> HashMap<usize, usize> {
+    let mut matches = HashMap::new();
+    let mut open = Vec::new();
+    let mut escaped = false;
+    for (idx, c) in input.char_indices() {
+        match c {
+            _ if escaped => (),
+            '[' => open.push(idx),
+            ']' => if let Some(start) = open.pop() {
+                matches.insert(start, idx);
+            },
+            '\n' => open.clear(),
+            _ => (),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    matches
+}
+
+fn parse_inlines(input: &str, depth: usize) -> Vec<MarkdownInline> {
+    let mut inlines = Vec::new();
+    let mut i = 0;
+    let brackets = match_brackets(input);
+    let parentheses = input.match_indices(')').map(|(idx, _)| idx).collect::<Vec<_>>();
+
+    while i < input.len() {
+        let rest = &input[i..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        match c {
+            '\\' => {
+                let next = rest[1..].chars().next();
+                match next {
+                    Some(next) if next.is_ascii_punctuation() => {
+                        push_text(&mut inlines, &rest[1..1 + next.len_utf8()]);
+                        i += 1 + next.len_utf8();
+                    }
+                    _ => {
+                        push_text(&mut inlines, "\\");
+                        i += 1;
+                    }
+                }
+            }
+            '\n' => {
+                inlines.push(MarkdownInline::LineBreak);
+                i += 1;
+            }
+            '`' => match rest[1..].find('`') {
+                Some(end) if end > 0 => {
+                    inlines.push(MarkdownInline::Code(rest[1..1 + end].to_string()));
+                    i += end + 2;
+                }
+                _ => {
+                    push_text(&mut inlines, "`");
+                    i += 1;
+                }
+            },
+            '*' if rest.starts_with("**") && depth < MAX_DEPTH => match find_closing(rest, 2, "**") {
+                Some(end) => {
+                    inlines.push(MarkdownInline::Strong(parse_inlines(&rest[2..end], depth + 1)));
+                    i += end + 2;
+                }
+                None => {
+                    push_text(&mut inlines, "**");
+                    i += 2;
+                }
+            },
+            '*' | '_' if depth < MAX_DEPTH && (c == '*' || is_word_boundary(input, i, true)) => {
+                let delimiter = if c == '*' { "*" } else { "_" };
+                match find_closing(rest, 1, delimiter) {
+                    Some(end) if !rest[1..].starts_with(char::is_whitespace) => {
+                        inlines.push(MarkdownInline::Emphasis(parse_inlines(&rest[1..end], depth + 1)));
+                        i += end + 1;
+                    }
+                    _ => {
+                        push_text(&mut inlines, delimiter);
+                        i += 1;
+                    }
+                }
+            }
+            '[' => {
+                // The text of the link ends at the bracket matching this one, which must be followed by the url
+                let link = brackets.get(&i).filter(|text_end| input[**text_end..].starts_with("](")).and_then(|text_end| {
+                    let (text_end, url_start) = (text_end - i, text_end + 2);
+                    let url_end = parentheses[parentheses.partition_point(|idx| *idx < url_start)..].first()? - i;
+                    let url = rest[text_end + 2..url_end].trim();
+                    if !is_safe_url(url) {
+                        return None;
+                    }
+                    Some((text_end, url_end, url))
+                });
+                match link {
+                    Some((text_end, url_end, url)) => {
+                        let text = if depth < MAX_DEPTH {
+                            parse_inlines(&rest[1..text_end], depth + 1)
+                        } else {
+                            vec![MarkdownInline::Text(rest[1..text_end].to_string())]
+                        };
+                        inlines.push(MarkdownInline::Link { text, url: url.to_string() });
+                        i += url_end + 1;
+                    }
+                    None => {
+                        push_text(&mut inlines, "[");
+                        i += 1;
+                    }
+                }
+            }
+            _ => {
+                // Copy all characters that can't start a construct at once
+                let len = match rest[c.len_utf8()..].find(['\\', '\n', '`', '*', '_', '[']) {
+                    Some(len) => len + c.len_utf8(),
+                    None => rest.len(),
+                };
+                push_text(&mut inlines, &rest[..len]);
+                i += len;
+            }
+        }
+    }
+
+    inlines
+}
+
+/// Parses a comment written in our Markdown subset.
+pub fn parse_markdown(input: &str) -> Vec<MarkdownBlock> {
+    parse_blocks(input, 0)
+}
+
+/// Strips all formatting and returns the plain text, for notifications and previews.
+pub fn markdown_to_plain_text(input: &str) -> String {
+    fn inlines_to_text(inlines: &[MarkdownInline], text: &mut String) {
+        for inline in inlines {
+            match inline {
+                MarkdownInline::Text(s) | MarkdownInline::Code(s) => text.push_str(s),
+                MarkdownInline::Emphasis(inner) | MarkdownInline::Strong(inner) | MarkdownInline::Link { text: inner, .. } => inlines_to_text(inner, text),
+                MarkdownInline::LineBreak => text.push(' '),
+            }
+        }
+    }
+
+    fn blocks_to_text(blocks: &[MarkdownBlock], text: &mut String) {
+        for block in blocks {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            match block {
+                MarkdownBlock::Paragraph(inlines) => inlines_to_text(inlines, text),
+                MarkdownBlock::Quote(blocks) => blocks_to_text(blocks, text),
+                MarkdownBlock::List { items, .. } => for item in items {
+                    inlines_to_text(item, text);
+                    text.push(' ');
+                },
+                MarkdownBlock::CodeBlock(code) => text.push_str(code),
+            }
+        }
+    }
+
+    let mut text = String::new();
+    blocks_to_text(&parse_markdown(input), &mut text);
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> MarkdownInline {
+        MarkdownInline::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_inlines() {
+        assert_eq!(parse_markdown("a *b* **c** `d`"), vec![MarkdownBlock::Paragraph(vec![
+            text("a "),
+            MarkdownInline::Emphasis(vec![text("b")]),
+            text(" "),
+            MarkdownInline::Strong(vec![text("c")]),
+            text(" "),
+            MarkdownInline::Code(String::from("d")),
+        ])]);
+        assert_eq!(parse_markdown("snake_case_name"), vec![MarkdownBlock::Paragraph(vec![text("snake_case_name")])]);
+        assert_eq!(parse_markdown("\\*not emphasis\\*"), vec![MarkdownBlock::Paragraph(vec![text("*not emphasis*")])]);
+        assert_eq!(parse_markdown("2 * 3 * 4"), vec![MarkdownBlock::Paragraph(vec![text("2 * 3 * 4")])]);
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(parse_markdown("> quoted\n\n- one\n- two\n\n1. first\n```\n<b>code</b>\n```"), vec![
+            MarkdownBlock::Quote(vec![MarkdownBlock::Paragraph(vec![text("quoted")])]),
+            MarkdownBlock::List { ordered: false, items: vec![vec![text("one")], vec![text("two")]] },
+            MarkdownBlock::List { ordered: true, items: vec![vec![text("first")]] },
+            MarkdownBlock::CodeBlock(String::from("<b>code</b>")),
+        ]);
+    }
+
+    #[test]
+    fn test_links_are_sanitized() {
+        assert_eq!(parse_markdown("[site](https://insa-rouen.fr)"), vec![MarkdownBlock::Paragraph(vec![
+            MarkdownInline::Link { text: vec![text("site")], url: String::from("https://insa-rouen.fr") },
+        ])]);
+        assert_eq!(parse_markdown("[x](javascript:alert(1))"), vec![MarkdownBlock::Paragraph(vec![text("[x](javascript:alert(1))")])]);
+        assert_eq!(parse_markdown("[x](data:text/html,<script>)"), vec![MarkdownBlock::Paragraph(vec![text("[x](data:text/html,<script>)")])]);
+        assert!(!is_safe_url("https://a.fr\" onclick=\"alert(1)"));
+    }
+
+    #[test]
+    fn test_link_brackets() {
+        assert_eq!(parse_markdown("[a] b](https://insa-rouen.fr)"), vec![MarkdownBlock::Paragraph(vec![text("[a] b](https://insa-rouen.fr)")])]);
+        assert_eq!(parse_markdown("[[a] b](https://insa-rouen.fr)"), vec![MarkdownBlock::Paragraph(vec![
+            MarkdownInline::Link { text: vec![text("[a] b")], url: String::from("https://insa-rouen.fr") },
+        ])]);
+        assert_eq!(parse_markdown("[a\\](b](https://insa-rouen.fr)"), vec![MarkdownBlock::Paragraph(vec![
+            MarkdownInline::Link { text: vec![text("a](b")], url: String::from("https://insa-rouen.fr") },
+        ])]);
+        assert_eq!(parse_markdown("[a\nb](https://insa-rouen.fr)"), vec![MarkdownBlock::Paragraph(vec![
+            text("[a"), MarkdownInline::LineBreak, text("b](https://insa-rouen.fr)"),
+        ])]);
+
+        let brackets = "[".repeat(20_000) + &"](https://insa-rouen.fr)".repeat(20_000);
+        assert!(!parse_markdown(&brackets).is_empty());
+    }
+
+    #[test]
+    fn test_raw_html_is_text() {
+        assert_eq!(parse_markdown("<img src=x onerror=alert(1)>"), vec![MarkdownBlock::Paragraph(vec![text("<img src=x onerror=alert(1)>")])]);
+        assert_eq!(markdown_to_plain_text("**Hello** <i>world</i>"), "Hello <i>world</i>");
+    }
+}
@@ -0,0 +1,97 @@
+use crate::prelude::*;
+use std::ops::Range;
+
+/// A comment in which the current user has been mentioned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Mention {
+    /// Event id
+    pub eid: String,
+    /// Id of the comment containing the mention.
+    pub cid: u64,
+    pub author: UserDesc,
+    pub ts: i64,
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '.' || c == '-' || c == '_'
+}
+
+/// Finds every `@username` in a text.
+/// Returns the byte range of each mention (including the `@`) along with the username.
+///
+/// An `@` preceded by a username character is ignored, so that email addresses are not mistaken for mentions.
+/// A trailing dot is not part of the username, as it most likely ends the sentence.
+pub fn find_mentions(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut mentions = Vec::new();
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        if c == '@' && !previous.map(is_username_char).unwrap_or(false) {
+            let start = i + 1;
+            let end = text[start..].find(|c| !is_username_char(c)).map(|len| start + len).unwrap_or(text.len());
+            let username = text[start..end].trim_end_matches('.');
+            if !username.is_empty() {
+                mentions.push((i..start + username.len(), username));
+            }
+        }
+        previous = Some(c);
+    }
+    mentions
+}
+
+/// Returns the users mentioned in a comment, among the candidates, without duplicates.
+pub fn resolve_mentions(content: &str, candidates: &[UserDesc]) -> Vec<UserDesc> {
+    let mut mentioned: Vec<UserDesc> = Vec::new();
+    for (_, username) in find_mentions(content) {
+        let Some(user) = candidates.iter().find(|user| user.as_username().eq_ignore_ascii_case(username)) else { continue };
+        if !mentioned.iter().any(|m| m.uid == user.uid) {
+            mentioned.push(user.clone());
+        }
+    }
+    mentioned
+}
+
+/// Returns the mention being typed at the caret (a byte index), if any.
+/// The result is the byte index of the `@` and the part of the username typed so far.
+pub fn mention_query(text: &str, caret: usize) -> Option<(usize, &str)> {
+    let before = text.get(..caret)?;
+    let start = before.rfind(|c| !is_username_char(c))?;
+    if !before[start..].starts_with('@') {
+        return None;
+    }
+    if before[..start].chars().next_back().map(is_username_char).unwrap_or(false) {
+        return None;
+    }
+    Some((start, &before[start + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_mentions() {
+        let text = "Merci @edouard.foobar. Mail: simon.girard@insa-rouen.fr @ @alix-a";
+        let mentions = find_mentions(text);
+        assert_eq!(mentions.iter().map(|(_, u)| *u).collect::<Vec<_>>(), vec!["edouard.foobar", "alix-a"]);
+        assert_eq!(&text[mentions[0].0.clone()], "@edouard.foobar");
+    }
+
+    #[test]
+    fn test_resolve_mentions() {
+        let friends = vec![
+            UserDesc::new(1, String::from("edouard.foobar@insa-rouen.fr")),
+            UserDesc::new(2, String::from("simon.girard@insa-rouen.fr")),
+        ];
+        let mentioned = resolve_mentions("@Simon.Girard @unknown @simon.girard @edouard.foobar", &friends);
+        assert_eq!(mentioned.iter().map(|u| u.uid).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_mention_query() {
+        assert_eq!(mention_query("Salut @edo", 10), Some((6, "edo")));
+        assert_eq!(mention_query("@", 1), Some((0, "")));
+        assert_eq!(mention_query("Salut @edo", 5), None);
+        assert_eq!(mention_query("mail@insa", 9), None);
+        assert_eq!(mention_query("Salut @edo ", 11), None);
+    }
+}
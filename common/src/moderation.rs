@@ -0,0 +1,102 @@
+use crate::prelude::*;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Inappropriate,
+    PersonalData,
+    Misinformation,
+    Other,
+}
+
+impl ReportReason {
+    pub const ALL: [ReportReason; 6] = [
+        ReportReason::Spam,
+        ReportReason::Harassment,
+        ReportReason::Inappropriate,
+        ReportReason::PersonalData,
+        ReportReason::Misinformation,
+        ReportReason::Other,
+    ];
+
+    /// Get the description of the reason (en_message, fr_message)
+    pub fn to_message(&self) -> (&'static str, &'static str) {
+        match self {
+            ReportReason::Spam => ("Spam or advertising", "Spam ou publicité"),
+            ReportReason::Harassment => ("Harassment or hateful content", "Harcèlement ou contenu haineux"),
+            ReportReason::Inappropriate => ("Inappropriate content", "Contenu inapproprié"),
+            ReportReason::PersonalData => ("Personal data disclosure", "Divulgation de données personnelles"),
+            ReportReason::Misinformation => ("Wrong information about the course", "Information erronée sur le cours"),
+            ReportReason::Other => ("Other", "Autre"),
+        }
+    }
+}
+
+/// Sent by a user to report a comment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReportRequest {
+    /// Event id
+    pub eid: String,
+    /// Id of the reported comment.
+    pub cid: u64,
+    pub reason: ReportReason,
+    /// Optional explanation written by the reporter.
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Report {
+    pub reporter: UserDesc,
+    pub reason: ReportReason,
+    pub note: Option<String>,
+    pub ts: i64,
+}
+
+/// A reported comment, as displayed in the moderation queue.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReportedComment {
+    /// Event id
+    pub eid: String,
+    /// Summary of the event, to give some context to moderators.
+    pub event_summary: String,
+    pub comment: Comment,
+    /// Parents of the comment, from the root of the thread to the direct parent.
+    pub context: Vec<Comment>,
+    /// All reports made against the comment, oldest first.
+    pub reports: Vec<Report>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationAction {
+    /// Close the reports and keep the comment as is.
+    Dismiss,
+    /// Hide the comment from everyone but its author and moderators.
+    Hide,
+    /// Permanently delete the comment.
+    Delete,
+}
+
+/// Sent by a contributor to act on a reported comment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModerationRequest {
+    /// Event id
+    pub eid: String,
+    pub cid: u64,
+    pub action: ModerationAction,
+    pub note: Option<String>,
+}
+
+/// Every moderation action is recorded in a log readable by contributors.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModerationLogEntry {
+    pub moderator: UserDesc,
+    /// Event id
+    pub eid: String,
+    pub cid: u64,
+    pub action: ModerationAction,
+    pub note: Option<String>,
+    /// Content of the comment at the time of the action.
+    pub content: String,
+    pub ts: i64,
+}
@@ -0,0 +1,191 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use crate::prelude::*;
+
+/// Day of the agenda a url points to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AgendaTarget {
+    /// `/agenda/2025-10-14`
+    Day(NaiveDate),
+    /// `/agenda/week/2025-W42`, holding the Monday of the week
+    Week(NaiveDate),
+}
+
+impl AgendaTarget {
+    /// The day to select in the agenda.
+    pub fn day(&self) -> NaiveDate {
+        match self {
+            AgendaTarget::Day(day) | AgendaTarget::Week(day) => *day,
+        }
+    }
+
+    fn path(&self) -> String {
+        match self {
+            AgendaTarget::Day(day) => format_date(day),
+            AgendaTarget::Week(monday) => {
+                let week = monday.iso_week();
+                format!("week/{}-W{:02}", week.year(), week.week())
+            }
+        }
+    }
+}
+
+fn format_date(date: &NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Parses an ISO week such as `2025-W42`, returning its Monday.
+fn parse_week(week: &str) -> Option<NaiveDate> {
+    let (year, week) = week.split_once("-W")?;
+    NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)
+}
+
+/// The page that is currently displayed.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Page {
+    Agenda { target: Option<AgendaTarget> },
+    Event { eid: String },
+    Friends,
+    FriendAgenda { pseudo: String, date: Option<NaiveDate> },
+    TeacherAgenda { teacher: String, date: Option<NaiveDate> },
+    /// Schedule of a room, identified by [Location::room_id]
+    RoomAgenda { room: String, date: Option<NaiveDate> },
+    Stotra,
+    Settings,
+    Onboarding,
+    Moderation,
+    Homeworks,
+    Search,
+    Rick,
+    NotFound { path: String },
+}
+
+impl Page {
+    /// The path of the page, whose segments are percent-encoded.
+    pub fn path(&self) -> String {
+        match self {
+            Page::Agenda { target: None } => String::from("/agenda"),
+            Page::Agenda { target: Some(target) } => format!("/agenda/{}", target.path()),
+            Page::Event { eid } => format!("/event/{}", percent_encode(eid)),
+            Page::Friends => String::from("/friends"),
+            Page::FriendAgenda { pseudo, date: None } => format!("/friend-agenda/{}", percent_encode(pseudo)),
+            Page::FriendAgenda { pseudo, date: Some(date) } => format!("/friend-agenda/{}/{}", percent_encode(pseudo), format_date(date)),
+            Page::TeacherAgenda { teacher, date: None } => format!("/teacher/{}", percent_encode(teacher)),
+            Page::TeacherAgenda { teacher, date: Some(date) } => format!("/teacher/{}/{}", percent_encode(teacher), format_date(date)),
+            Page::RoomAgenda { room, date: None } => format!("/room/{}", percent_encode(room)),
+            Page::RoomAgenda { room, date: Some(date) } => format!("/room/{}/{}", percent_encode(room), format_date(date)),
+            Page::Stotra => String::from("/stotra"),
+            Page::Settings => String::from("/settings"),
+            Page::Onboarding => String::from("/onboarding"),
+            Page::Moderation => String::from("/moderation"),
+            Page::Homeworks => String::from("/homeworks"),
+            Page::Search => String::from("/search"),
+            Page::Rick => String::from("/r"),
+            Page::NotFound { path } => path.clone(),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Page::Agenda { .. } => "Agenda",
+            Page::Event { .. } => "Event",
+            Page::Friends => "Friends",
+            Page::FriendAgenda { .. } => "Friend agenda",
+            Page::TeacherAgenda { .. } => "Teacher agenda",
+            Page::RoomAgenda { .. } => "Room agenda",
+            Page::Stotra => "Stotra",
+            Page::Settings => "Settings",
+            Page::Onboarding => "Onboarding",
+            Page::Moderation => "Moderation",
+            Page::Homeworks => "Homeworks",
+            Page::Search => "Search",
+            Page::Rick => "Rick",
+            Page::NotFound { .. } => "Not found",
+        }
+    }
+
+    /// Parses the path of a page, as returned by [Page::path].
+    pub fn from_path(path: &str) -> Page {
+        let segments = path.trim_matches('/').split('/').map(percent_decode_segment).collect::<Vec<_>>();
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+        let page = match segments.as_slice() {
+            [""] | ["agenda"] => Some(Page::Agenda { target: None }),
+            ["agenda", "week", week] => parse_week(week).map(|monday| Page::Agenda { target: Some(AgendaTarget::Week(monday)) }),
+            ["agenda", day] => parse_date(day).map(|day| Page::Agenda { target: Some(AgendaTarget::Day(day)) }),
+            ["event", eid] if !eid.is_empty() => Some(Page::Event { eid: eid.to_string() }),
+            ["friends"] => Some(Page::Friends),
+            ["friend-agenda", pseudo] => Some(Page::FriendAgenda { pseudo: pseudo.to_string(), date: None }),
+            ["friend-agenda", pseudo, date] => parse_date(date).map(|date| Page::FriendAgenda { pseudo: pseudo.to_string(), date: Some(date) }),
+            ["teacher", teacher] if !teacher.is_empty() => Some(Page::TeacherAgenda { teacher: teacher.to_string(), date: None }),
+            ["teacher", teacher, date] => parse_date(date).map(|date| Page::TeacherAgenda { teacher: teacher.to_string(), date: Some(date) }),
+            ["room", room] if !room.is_empty() => Some(Page::RoomAgenda { room: room.to_string(), date: None }),
+            ["room", room, date] => parse_date(date).map(|date| Page::RoomAgenda { room: room.to_string(), date: Some(date) }),
+            ["stotra"] => Some(Page::Stotra),
+            ["settings"] => Some(Page::Settings),
+            ["onboarding"] => Some(Page::Onboarding),
+            ["moderation"] => Some(Page::Moderation),
+            ["homeworks"] => Some(Page::Homeworks),
+            ["search"] => Some(Page::Search),
+            ["r"] => Some(Page::Rick),
+            _ => None,
+        };
+        page.unwrap_or_else(|| Page::NotFound { path: path.to_string() })
+    }
+
+    /// The same page, pointing to another day of the agenda.
+    pub fn with_day(&self, day: NaiveDate) -> Option<Page> {
+        match self {
+            Page::Agenda { .. } => Some(Page::Agenda { target: Some(AgendaTarget::Day(day)) }),
+            Page::FriendAgenda { pseudo, .. } => Some(Page::FriendAgenda { pseudo: pseudo.clone(), date: Some(day) }),
+            Page::TeacherAgenda { teacher, .. } => Some(Page::TeacherAgenda { teacher: teacher.clone(), date: Some(day) }),
+            Page::RoomAgenda { room, .. } => Some(Page::RoomAgenda { room: room.clone(), date: Some(day) }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agenda_paths() {
+        let week = Page::from_path("/agenda/week/2025-W42");
+        assert_eq!(week, Page::Agenda { target: Some(AgendaTarget::Week(NaiveDate::from_ymd_opt(2025, 10, 13).unwrap())) });
+        assert_eq!(week.path(), "/agenda/week/2025-W42");
+
+        let day = Page::from_path("/agenda/2025-10-14");
+        assert_eq!(day, Page::Agenda { target: Some(AgendaTarget::Day(NaiveDate::from_ymd_opt(2025, 10, 14).unwrap())) });
+        assert_eq!(day.path(), "/agenda/2025-10-14");
+
+        assert_eq!(Page::from_path("/"), Page::Agenda { target: None });
+        assert_eq!(Page::from_path("/agenda/").path(), "/agenda");
+    }
+
+    #[test]
+    fn test_encoded_segments() {
+        for eid in ["CM+TD 12/3", "réunion", "a%2Fb", "+"] {
+            let page = Page::Event { eid: eid.to_string() };
+            assert_eq!(Page::from_path(&page.path()), page, "{eid}");
+        }
+        // A `+` in a path is not a space
+        assert_eq!(Page::from_path("/event/a+b"), Page::Event { eid: String::from("a+b") });
+        assert_eq!(Page::from_path("/event/a%20b"), Page::Event { eid: String::from("a b") });
+
+        let page = Page::FriendAgenda { pseudo: String::from("jean.dupont"), date: NaiveDate::from_ymd_opt(2025, 1, 31) };
+        assert_eq!(page.path(), "/friend-agenda/jean.dupont/2025-01-31");
+        assert_eq!(Page::from_path(&page.path()), page);
+    }
+
+    #[test]
+    fn test_not_found() {
+        for path in ["/unknown", "/agenda/2025-13-45", "/agenda/week/2025-W54", "/agenda/2025-W42", "/event/", "/teacher/Dupont/tomorrow", "/settings/more"] {
+            let page = Page::from_path(path);
+            assert_eq!(page, Page::NotFound { path: path.to_string() }, "{path}");
+            assert_eq!(page.path(), path);
+        }
+    }
+}
@@ -1,3 +1,3 @@
-pub use crate::{events::*, groups::*, location::*, user_info::*, password::*, announcements::*, surveys::*, friends::*, user_desc::*, homeworks::*};
+pub use crate::{events::*, groups::*, location::*, user_info::*, password::*, announcements::*, comments::*, diff::*, endpoints::*, errors::*, surveys::*, friends::*, user_desc::*, homeworks::*, markdown::*, mentions::*, moderation::*, pages::*, push::*, search::*};
 pub(crate) use serde::{Serialize, Deserialize, Serializer, Deserializer};
 pub(crate) use std::collections::{HashMap, HashSet};
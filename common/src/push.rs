@@ -0,0 +1,20 @@
+use crate::prelude::*;
+
+/// Stream of [PushEvent]s, as Server-Sent Events whose `data` is the event in JSON.
+///
+/// It doesn't replay what was sent while the app was disconnected: the app refreshes its data when it reconnects.
+pub const PUSH_PATH: &str = "/api/push";
+
+/// A change the server tells the app about as soon as it happens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PushEvent {
+    /// Comments of an event were posted, edited, deleted or voted on.
+    Comments {
+        eid: String,
+    },
+    /// The friend lists changed, for instance because of a new friend request.
+    Friends,
+    /// The schedule changed, and the changes can be fetched from [GetScheduleChanges].
+    Schedule,
+}
@@ -0,0 +1,163 @@
+use crate::prelude::*;
+
+/// Restricts the events returned by [SearchIndex::search].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilters {
+    /// Earliest start of the events, as a timestamp.
+    pub from: Option<u64>,
+    /// Latest start of the events, as a timestamp.
+    pub until: Option<u64>,
+    /// Kinds of the events, or any kind if empty.
+    pub kinds: Vec<EventKind>,
+}
+
+impl SearchFilters {
+    fn accept(&self, event: &Event) -> bool {
+        self.from.map(|from| event.start_unixtime >= from).unwrap_or(true)
+            && self.until.map(|until| event.start_unixtime <= until).unwrap_or(true)
+            && (self.kinds.is_empty() || event.kind.map(|kind| self.kinds.contains(&kind)).unwrap_or(false))
+    }
+}
+
+/// Lowercases text and drops accents and everything but letters and digits,
+/// so that `Ma-B-R1-12` matches `Ma - B - R1 - 12`.
+pub fn normalize_search(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'â' | 'ä' => normalized.push('a'),
+            'é' | 'è' | 'ê' | 'ë' => normalized.push('e'),
+            'î' | 'ï' => normalized.push('i'),
+            'ô' | 'ö' => normalized.push('o'),
+            'ù' | 'û' | 'ü' => normalized.push('u'),
+            'ç' => normalized.push('c'),
+            'œ' => normalized.push_str("oe"),
+            c if c.is_alphanumeric() => normalized.push(c),
+            _ => (),
+        }
+    }
+    normalized
+}
+
+fn kind_name(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::Td => "td",
+        EventKind::Tp => "tp",
+        EventKind::Cm => "cm",
+        EventKind::Tut => "tutorat",
+    }
+}
+
+/// Adds the terms of a text: the text as a whole and each of its words.
+fn push_terms(terms: &mut Vec<String>, text: &str) {
+    terms.push(normalize_search(text));
+    if text.split_whitespace().nth(1).is_some() {
+        terms.extend(text.split_whitespace().map(normalize_search).filter(|w| !w.is_empty()));
+    }
+}
+
+/// Searchable terms of a schedule, taken from summaries, kinds, teachers, locations and groups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchIndex {
+    /// Normalized terms of each event, in the order of the schedule.
+    terms: Vec<Vec<String>>,
+}
+
+impl SearchIndex {
+    pub fn new(events: &[Event]) -> SearchIndex {
+        let terms = events.iter().map(|event| {
+            let mut terms = Vec::new();
+            push_terms(&mut terms, &event.summary);
+            terms.extend(event.kind.map(|kind| kind_name(kind).to_string()));
+            for teacher in &event.teachers {
+                push_terms(&mut terms, teacher);
+            }
+            if let Some(location) = &event.location {
+                push_terms(&mut terms, &location.to_string());
+                terms.push(normalize_search(&location.room_id()));
+            }
+            // Group names contain kinds such as `tp`, so they are only matched as a whole
+            terms.extend(event.groups.groups().iter().map(|group| normalize_search(group)));
+            terms
+        }).collect();
+        SearchIndex { terms }
+    }
+
+    /// Events of the indexed schedule having a term starting with each word of the query, in the order of the schedule.
+    /// An empty query returns every event accepted by the filters.
+    pub fn search<'a>(&self, events: &'a [Event], query: &str, filters: &SearchFilters) -> Vec<&'a Event> {
+        debug_assert_eq!(events.len(), self.terms.len(), "index built for another schedule");
+        let words = query.split_whitespace().map(normalize_search).filter(|w| !w.is_empty()).collect::<Vec<_>>();
+        events.iter()
+            .zip(&self.terms)
+            .filter(|(event, _)| filters.accept(event))
+            .filter(|(_, terms)| words.iter().all(|word| terms.iter().any(|term| term.starts_with(word.as_str()))))
+            .map(|(event, _)| event)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(summary: &str, kind: Option<EventKind>, teachers: &[&str], location: Option<Location>, start_unixtime: u64) -> Event {
+        Event {
+            summary: summary.to_string(),
+            kind,
+            number: None,
+            teachers: teachers.iter().map(|t| t.to_string()).collect(),
+            groups: Groups::new_with_groups(vec![String::from("stpi21-tp-a1")]),
+            location,
+            start_unixtime,
+            end_unixtime: start_unixtime + 5400,
+            eid: summary.to_string(),
+        }
+    }
+
+    fn events() -> Vec<Event> {
+        let room = Location::Parsed { building: Building::Magellan, building_area: 'B', level: Level::Level1, room_number: 12 };
+        vec![
+            event("Analyse", Some(EventKind::Cm), &["Émilie Durand"], None, 10),
+            event("Physique", Some(EventKind::Tp), &["Jean Dupont"], Some(room), 20),
+            event("Anglais", None, &[], Some(Location::Unparsed(String::from("Amphi Curie"))), 30),
+            event("Physique", Some(EventKind::Td), &["Jean Dupont"], None, 40),
+        ]
+    }
+
+    fn search(query: &str, filters: &SearchFilters) -> Vec<u64> {
+        let events = events();
+        let index = SearchIndex::new(&events);
+        index.search(&events, query, filters).into_iter().map(|e| e.start_unixtime).collect()
+    }
+
+    #[test]
+    fn test_normalize_search() {
+        assert_eq!(normalize_search("Ma - B - R1 - 12"), "mabr112");
+        assert_eq!(normalize_search("Émilie Œuvre"), "emilieoeuvre");
+    }
+
+    #[test]
+    fn test_search_fields() {
+        let all = SearchFilters::default();
+        assert_eq!(search("dupont", &all), vec![20, 40]);
+        assert_eq!(search("emilie", &all), vec![10]);
+        assert_eq!(search("Ma-B-R1-12", &all), vec![20]);
+        assert_eq!(search("magellan", &all), vec![20]);
+        assert_eq!(search("curie", &all), vec![30]);
+        assert_eq!(search("phys", &all), vec![20, 40]);
+        assert_eq!(search("tp dupont", &all), vec![20]);
+        assert_eq!(search("stpi21", &all), vec![10, 20, 30, 40]);
+        assert_eq!(search("chimie", &all), Vec::<u64>::new());
+        assert_eq!(search("  ", &all), vec![10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn test_search_filters() {
+        let td_and_tp = SearchFilters { kinds: vec![EventKind::Td, EventKind::Tp], ..Default::default() };
+        assert_eq!(search("", &td_and_tp), vec![20, 40]);
+        let range = SearchFilters { from: Some(15), until: Some(30), ..Default::default() };
+        assert_eq!(search("", &range), vec![20, 30]);
+        assert_eq!(search("physique", &range), vec![20]);
+    }
+}
@@ -21,6 +21,9 @@ pub struct UserInfo {
     pub token: String,
     /// Uid
     pub uid: i64,
+    /// Profile picture, if the user uploaded one
+    #[serde(default)]
+    pub picture: Option<String>,
     /// Whether the user has onboarded
     #[serde(default)]
     pub onboarded: bool,
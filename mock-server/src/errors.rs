@@ -0,0 +1,84 @@
+use common::ApiErrorKind;
+use serde::Serialize;
+
+/// An error in the format of the real API, decoded as a `KnownApiError` by the app.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MockError {
+    #[serde(skip)]
+    pub status: u16,
+    pub kind: String,
+    pub message_en: String,
+    pub message_fr: String,
+    pub origin: String,
+}
+
+/// HTTP status sent along with each kind of error.
+pub fn status(kind: &ApiErrorKind) -> u16 {
+    match kind {
+        ApiErrorKind::InvalidApiKey | ApiErrorKind::ApiKeyDoesNotExist | ApiErrorKind::ApiKeyExpired | ApiErrorKind::AuthentificationRequired => 401,
+        ApiErrorKind::Forbidden | ApiErrorKind::NotFriend => 403,
+        ApiErrorKind::NotFound | ApiErrorKind::UserNotFound | ApiErrorKind::TextbookNotFound => 404,
+        ApiErrorKind::MethodNotAllowed => 405,
+        ApiErrorKind::ChangesUnavailable => 410,
+        ApiErrorKind::FirewallBan => 429,
+        ApiErrorKind::InternalError => 500,
+        ApiErrorKind::CounterTooLow | ApiErrorKind::BadRequest | ApiErrorKind::Other(_) => 400,
+    }
+}
+
+impl MockError {
+    pub fn new(status: u16, kind: &str, message_en: impl Into<String>, message_fr: impl Into<String>) -> Self {
+        MockError {
+            status,
+            kind: kind.to_string(),
+            message_en: message_en.into(),
+            message_fr: message_fr.into(),
+            origin: String::from("mock-server"),
+        }
+    }
+
+    /// Builds an error of the given kind, with the messages declared in `common`.
+    /// Unknown kinds are sent with a 400 status and generic messages.
+    pub fn known(kind: &str) -> Self {
+        match ApiErrorKind::from(kind) {
+            ApiErrorKind::Other(kind) => MockError::new(400, &kind, format!("Injected error {kind}"), format!("Erreur injectée {kind}")),
+            kind => MockError::new(status(&kind), kind.as_str(), kind.message("en"), kind.message("fr")),
+        }
+    }
+
+    pub fn bad_request(message_en: impl Into<String>, message_fr: impl Into<String>) -> Self {
+        MockError::new(400, "bad_request", message_en, message_fr)
+    }
+
+    pub fn not_found(what: &str) -> Self {
+        MockError::new(404, "not_found", format!("{what} not found"), format!("{what} introuvable"))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Errors to send instead of the next responses, set through `/mock/fail`.
+#[derive(Default)]
+pub struct ErrorInjection {
+    /// Kind of the error and number of requests it still applies to.
+    pending: Option<(String, u32)>,
+}
+
+impl ErrorInjection {
+    pub fn fail_next(&mut self, kind: String, count: u32) {
+        self.pending = if count == 0 { None } else { Some((kind, count)) };
+    }
+
+    /// Returns the error to send for the current request, if any.
+    pub fn take(&mut self) -> Option<MockError> {
+        let (kind, count) = self.pending.as_mut()?;
+        let error = MockError::known(kind);
+        *count -= 1;
+        if *count == 0 {
+            self.pending = None;
+        }
+        Some(error)
+    }
+}
@@ -0,0 +1,250 @@
+use common::*;
+use std::collections::HashMap;
+
+const DAY: i64 = 24 * 3600;
+/// Offset of Paris from UTC. Daylight saving time is ignored, courses are just an hour later in summer.
+const PARIS_OFFSET: i64 = 3600;
+
+/// A course given every week: weekday (0 is Monday), start hour and minute, duration in minutes, summary, kind, teacher and room.
+type CourseTemplate = (i64, i64, i64, i64, &'static str, EventKind, &'static str, (Building, char, Level, u8));
+
+const COURSES: &[CourseTemplate] = &[
+    (0, 8, 0, 90, "Analyse", EventKind::Cm, "Jean Dupont", (Building::Magellan, 'A', Level::Rc, 1)),
+    (0, 9, 45, 90, "Physique", EventKind::Td, "Marie Curie", (Building::DumontDurville, 'B', Level::Level1, 12)),
+    (0, 13, 30, 90, "Anglais", EventKind::Td, "John Smith", (Building::Bougainville, 'A', Level::Level2, 4)),
+    (1, 8, 0, 120, "Algorithmique", EventKind::Tp, "Ada Lovelace", (Building::Darwin, 'C', Level::Rj, 3)),
+    (1, 10, 15, 90, "Analyse", EventKind::Td, "Jean Dupont", (Building::Magellan, 'B', Level::Level1, 8)),
+    (2, 8, 0, 90, "Chimie", EventKind::Cm, "Louis Pasteur", (Building::Magellan, 'A', Level::Rc, 1)),
+    (2, 13, 30, 120, "Physique", EventKind::Tp, "Marie Curie", (Building::Darwin, 'A', Level::Rc, 10)),
+    (3, 9, 45, 90, "Algorithmique", EventKind::Cm, "Ada Lovelace", (Building::Magellan, 'A', Level::Rc, 1)),
+    (3, 13, 30, 90, "Communication", EventKind::Td, "Victor Hugo", (Building::Bougainville, 'B', Level::Level1, 6)),
+    (4, 8, 0, 90, "Analyse", EventKind::Td, "Jean Dupont", (Building::Magellan, 'B', Level::Level1, 8)),
+    (4, 9, 45, 90, "Chimie", EventKind::Td, "Louis Pasteur", (Building::DumontDurville, 'A', Level::Level2, 15)),
+];
+
+/// Monday 00:00 (Paris time) of the week containing `now`.
+fn week_start(now: i64) -> i64 {
+    let days = now.div_euclid(DAY);
+    // The 1st of January 1970 was a Thursday
+    let weekday = (days + 3).rem_euclid(7);
+    (days - weekday) * DAY - PARIS_OFFSET
+}
+
+/// Generates the courses of the previous week up to two weeks after the current one.
+/// Courses are only kept if `keep` returns true for their index in [COURSES].
+fn generate_events(now: i64, groups: &Groups, keep: impl Fn(usize) -> bool) -> Vec<Event> {
+    let mut events = Vec::new();
+    for week in -1..=2 {
+        let monday = week_start(now) + week * 7 * DAY;
+        for (i, (weekday, hour, minute, duration, summary, kind, teacher, (building, building_area, level, room_number))) in COURSES.iter().enumerate() {
+            if !keep(i) {
+                continue;
+            }
+            let start = monday + weekday * DAY + hour * 3600 + minute * 60;
+            events.push(Event {
+                summary: summary.to_string(),
+                kind: Some(*kind),
+                number: Some((week + 2) as u8),
+                teachers: vec![teacher.to_string()],
+                groups: groups.clone(),
+                location: Some(Location::Parsed {
+                    building: *building,
+                    building_area: *building_area,
+                    level: *level,
+                    room_number: *room_number,
+                }),
+                start_unixtime: start as u64,
+                end_unixtime: (start + duration * 60) as u64,
+                eid: format!("{}-{start}", summary.to_lowercase()),
+            });
+        }
+    }
+    events.sort_by_key(|e| e.start_unixtime);
+    events
+}
+
+/// Friends share the lectures but only half of the other courses.
+fn generate_friend_events(now: i64, uid: i64, groups: &Groups) -> Vec<Event> {
+    let parity = uid as usize % 2;
+    generate_events(now, groups, |i| i % 2 == parity || COURSES[i].5 == EventKind::Cm)
+}
+
+fn user(uid: i64, username: &str) -> UserDesc {
+    UserDesc::new(uid, format!("{username}@insa-rouen.fr"))
+}
+
+pub fn comment(cid: u64, parent: Option<u64>, author: &UserDesc, content: &str, ts: i64) -> Comment {
+    Comment {
+        cid,
+        parent,
+        author: author.clone(),
+        content: content.to_string(),
+        creation_ts: ts,
+        last_edited_ts: ts,
+        upvotes: 0,
+        downvotes: 0,
+        vote: 0,
+        hidden: false,
+        mentions: Vec::new(),
+        homework: None,
+    }
+}
+
+/// Everything the mock server knows, seeded with fixtures and modified by requests.
+pub struct MockState {
+    /// The user making the requests.
+    pub user: UserInfo,
+    pub me: UserDesc,
+    /// Other users, that can be sent friend requests.
+    pub users: Vec<(UserDesc, Groups)>,
+    pub friends: FriendLists,
+    pub events: Vec<Event>,
+    pub friend_events: HashMap<i64, Vec<Event>>,
+    pub comments: HashMap<String, Vec<Comment>>,
+    /// Every version of edited comments, oldest first.
+    pub revisions: HashMap<u64, Vec<CommentRevision>>,
+    /// Pending reports, with the event id and comment id they are about.
+    pub reports: Vec<(String, u64, Report)>,
+    pub moderation_log: Vec<ModerationLogEntry>,
+    pub colors: HashMap<String, String>,
+    pub next_cid: u64,
+    /// Idempotency keys of the comments created, with their cid.
+    pub comment_keys: HashMap<String, u64>,
+    /// When the fixtures were seeded. Schedule changes are unknown before.
+    pub seeded_at: i64,
+    /// Ids of the events changed through `/mock/*`, with the time of the change.
+    pub schedule_log: Vec<(i64, String)>,
+}
+
+impl MockState {
+    /// Seeds the fixtures around the current week.
+    pub fn seeded(now: i64) -> Self {
+        let me = user(1, "edouard.foobar");
+        let groups = Groups::default();
+        let other_groups = Groups::new_with_groups(vec![String::from("iti3"), String::from("stpi21-tp-a2")]);
+        let users = vec![
+            (user(2, "simon.girard"), groups.clone()),
+            (user(3, "alix.anneraud"), groups.clone()),
+            (user(4, "juline.emond"), other_groups.clone()),
+            (user(5, "dimitri.timoz"), other_groups.clone()),
+            (user(6, "camille.martin"), other_groups.clone()),
+            (user(7, "lucas.bernard"), groups.clone()),
+        ];
+        let friends = FriendLists {
+            friends: vec![users[0].clone(), users[1].clone()],
+            incoming: vec![FriendRequestIncoming { from: users[2].clone(), at_ts: now - 2 * 3600 }],
+            outgoing: vec![FriendRequestOutgoing { to: users[3].clone(), at_ts: now - DAY }],
+            declined: vec![users[4].clone()],
+        };
+
+        let events = generate_events(now, &groups, |_| true);
+        let mut friend_events = HashMap::new();
+        for (friend, friend_groups) in &friends.friends {
+            friend_events.insert(friend.uid, generate_friend_events(now, friend.uid, friend_groups));
+        }
+
+        // A discussion on the first course of the current week
+        let mut comments = HashMap::new();
+        let mut revisions = HashMap::new();
+        let mut reports = Vec::new();
+        let this_week = week_start(now) as u64;
+        if let Some(event) = events.iter().find(|e| e.start_unixtime >= this_week) {
+            let (simon, alix) = (&users[0].0, &users[1].0);
+            let mut homework = comment(1, None, simon, "Relire le **chapitre 3** et faire les exercices 1 à 4.", now - 3 * DAY);
+            homework.upvotes = 4;
+            homework.homework = next_session(&events, event).map(|next| Homework { due: next.start_unixtime as i64, due_eid: Some(next.eid.clone()) });
+
+            let mut thanks = comment(2, Some(1), alix, "Merci ! @edouard.foobar tu as les notes du dernier cours ?", now - 2 * DAY);
+            thanks.mentions = vec![me.clone()];
+
+            let mut notes = comment(3, Some(2), &me, "Oui, elles sont sur le drive de la promo (dossier Analyse).", now - 2 * DAY + 600);
+            notes.last_edited_ts = now - DAY;
+            revisions.insert(3, vec![
+                CommentRevision { content: String::from("Oui, elles sont sur le drive de la promo."), ts: notes.creation_ts },
+                CommentRevision { content: notes.content.clone(), ts: notes.last_edited_ts },
+            ]);
+
+            let spam = comment(4, None, &users[5].0, "Achetez mes annales sur www.annales-pas-cheres.fr !!!", now - 3600);
+            reports.push((event.eid.clone(), 4, Report { reporter: alix.clone(), reason: ReportReason::Spam, note: None, ts: now - 1800 }));
+
+            comments.insert(event.eid.clone(), vec![homework, thanks, notes, spam]);
+        }
+
+        let user = UserInfo {
+            api_key_count: 2,
+            last_password_mod: Some(now - 30 * DAY),
+            email: (me.email.clone(), true),
+            groups: groups.clone(),
+            official_groups: groups,
+            available_groups: Groups::new_with_groups(COURSES.iter().map(|c| c.4.to_lowercase()).chain(["iti3", "stpi21-tp-a1", "stpi21-tp-a2"].map(String::from)).collect()),
+            last_colors_mod: now - DAY,
+            token: String::from("mock-ics-token"),
+            uid: me.uid,
+            picture: me.picture.clone(),
+            onboarded: true,
+        };
+
+        let colors = [("Analyse", "#e74c3c"), ("Physique", "#3498db"), ("Algorithmique", "#27ae60")]
+            .into_iter()
+            .map(|(summary, color)| (summary.to_string(), color.to_string()))
+            .collect();
+
+        MockState {
+            user,
+            me,
+            users,
+            friends,
+            events,
+            friend_events,
+            comments,
+            revisions,
+            reports,
+            moderation_log: Vec::new(),
+            colors,
+            next_cid: 5,
+            comment_keys: HashMap::new(),
+            seeded_at: now,
+            schedule_log: Vec::new(),
+        }
+    }
+
+    /// Every user known to the server, including the current one.
+    pub fn all_users(&self) -> impl Iterator<Item = &UserDesc> {
+        std::iter::once(&self.me).chain(self.users.iter().map(|(user, _)| user))
+    }
+
+    pub fn event(&self, eid: &str) -> Option<&Event> {
+        self.events.iter().chain(self.friend_events.values().flatten()).find(|e| e.eid == eid)
+    }
+
+    /// Events of every schedule kept by `keep`, once each and sorted by start.
+    /// Courses shared by several users have the same id in each schedule.
+    pub fn events_across_schedules(&self, keep: impl Fn(&Event) -> bool) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+        for event in self.events.iter().chain(self.friend_events.values().flatten()) {
+            if keep(event) && !events.iter().any(|e| e.eid == event.eid) {
+                events.push(event.clone());
+            }
+        }
+        events.sort_by_key(|e| e.start_unixtime);
+        events
+    }
+
+    /// Generates the schedule of a user that just became a friend.
+    pub fn ensure_friend_events(&mut self, now: i64, uid: i64, groups: &Groups) {
+        self.friend_events.entry(uid).or_insert_with(|| generate_friend_events(now, uid, groups));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_week_start() {
+        // Wednesday 2024-03-13 12:00 UTC
+        let monday = week_start(1710331200);
+        // Monday 2024-03-11 00:00 Paris (CET)
+        assert_eq!(monday, 1710111600);
+    }
+}
@@ -0,0 +1,121 @@
+//! A local stand-in for the closed-source backend.
+//!
+//! Serves seeded fixtures on the `/api/*` endpoints used by the app, so that it can be developed and tested
+//! without access to the real API. See the README of this crate for error injection.
+
+mod errors;
+mod fixtures;
+mod routes;
+
+use std::{collections::HashMap, io::Write, sync::mpsc::{channel, RecvTimeoutError, Sender}, thread::{self, sleep}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use common::decode_params;
+use tiny_http::{Header, Request, Response, Server};
+
+/// Interval at which a comment is sent to event streams, so that closed connections are noticed.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+pub use crate::{errors::*, fixtures::*, routes::*};
+
+pub fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or_default()
+}
+
+struct Args {
+    port: u16,
+    latency: Duration,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args { port: 8080, latency: Duration::ZERO };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--port" => args.port = iter.next().and_then(|p| p.parse().ok()).expect("--port expects a number"),
+            "--latency" => args.latency = Duration::from_millis(iter.next().and_then(|l| l.parse().ok()).expect("--latency expects milliseconds")),
+            "--help" | "-h" => {
+                println!("Usage: mock-server [--port PORT] [--latency MILLISECONDS]");
+                std::process::exit(0);
+            }
+            other => panic!("Unknown argument {other:?}, see --help"),
+        }
+    }
+    args
+}
+
+/// Keeps the connection open and writes the events of the stream as they are sent to the returned channel.
+/// tiny_http buffers chunked responses, so the response is written by hand.
+fn stream(request: Request, reply: Reply) -> Sender<String> {
+    let (sender, receiver) = channel::<String>();
+    thread::spawn(move || {
+        let mut writer = request.into_writer();
+        let mut head = format!("HTTP/1.1 200 OK\r\nContent-Type: {}\r\nConnection: close\r\n", reply.content_type);
+        for (name, value) in &reply.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        let mut chunk = head + &reply.body;
+        loop {
+            if writer.write_all(chunk.as_bytes()).and_then(|()| writer.flush()).is_err() {
+                return;
+            }
+            chunk = match receiver.recv_timeout(KEEP_ALIVE) {
+                Ok(chunk) => chunk,
+                Err(RecvTimeoutError::Timeout) => String::from(": keep-alive\n\n"),
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+        }
+    });
+    sender
+}
+
+fn main() {
+    let args = parse_args();
+    let server = Server::http(("127.0.0.1", args.port)).expect("Failed to bind the mock server");
+    let mut mock = MockServer::new(now());
+    let mut subscribers: Vec<Sender<String>> = Vec::new();
+    println!("Mock server listening on http://127.0.0.1:{}/api/", args.port);
+
+    for mut request in server.incoming_requests() {
+        let (path, query) = match request.url().split_once('?') {
+            Some((path, query)) => (path.to_string(), decode_params(query)),
+            None => (request.url().to_string(), HashMap::new()),
+        };
+        let headers = request.headers().iter().map(|h| (h.field.as_str().as_str().to_lowercase(), h.value.as_str().to_string())).collect();
+        let mut body = String::new();
+        if let Err(e) = request.as_reader().read_to_string(&mut body) {
+            eprintln!("Failed to read request body: {e}");
+        }
+
+        let mock_request = MockRequest { method: request.method().as_str().to_uppercase(), path, query, headers, body };
+        let reply = mock.handle(&mock_request, now());
+        println!("{} {} -> {}", mock_request.method, request.url(), reply.status);
+
+        // Subscribers whose connection was closed are dropped on the first event they miss
+        for event in mock.take_pushed() {
+            let message = sse(&event);
+            subscribers.retain(|subscriber| subscriber.send(message.clone()).is_ok());
+        }
+
+        let delay = mock_request.header("x-mock-latency").and_then(|l| l.parse().ok()).map(Duration::from_millis).unwrap_or(args.latency);
+        sleep(delay);
+
+        if reply.status == 200 && reply.content_type == EVENT_STREAM {
+            subscribers.push(stream(request, reply));
+            continue;
+        }
+
+        let mut response = Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(Header::from_bytes("Content-Type", reply.content_type).unwrap());
+        for (name, value) in reply.headers {
+            // Values are only allowed to be ASCII, which a `Location` built from a user-supplied url may not be
+            match Header::from_bytes(name, value.as_bytes()) {
+                Ok(header) => response.add_header(header),
+                Err(()) => eprintln!("Skipping invalid header {name}: {value}"),
+            }
+        }
+        if let Err(e) = request.respond(response) {
+            eprintln!("Failed to respond: {e}");
+        }
+    }
+}
@@ -0,0 +1,745 @@
+use crate::*;
+use common::*;
+use serde::Serialize;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+const JSON: &str = "application/json";
+/// Content type of [PUSH_PATH], whose reply is only the start of a stream the HTTP loop keeps open.
+pub const EVENT_STREAM: &str = "text/event-stream";
+
+/// Formats a pushed event as a Server-Sent Events message.
+pub fn sse(event: &PushEvent) -> String {
+    format!("data: {}\n\n", serde_json::to_string(event).unwrap())
+}
+
+/// A request, decoupled from the HTTP library.
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn param(&self, name: &str) -> Result<&str, MockError> {
+        self.query.get(name).map(String::as_str).ok_or_else(|| MockError::bad_request(format!("Missing parameter {name}"), format!("Paramètre {name} manquant")))
+    }
+
+    /// Decodes the parameters of an endpoint, as the app encodes them.
+    fn decode<E: Endpoint>(&self) -> Result<E, MockError> {
+        let decoded = match E::ENCODING {
+            Encoding::Query => E::from_params(&self.query),
+            Encoding::Form => E::from_params(&decode_params(&self.body)),
+            Encoding::Json => serde_json::from_str(&self.body).ok(),
+        };
+        decoded.ok_or_else(|| MockError::bad_request(format!("Invalid request to /api/{}", E::PATH), format!("Requête invalide vers /api/{}", E::PATH)))
+    }
+}
+
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl Reply {
+    fn json<T: Serialize>(value: &T) -> Result<Reply, MockError> {
+        Ok(Reply { status: 200, content_type: JSON, headers: Vec::new(), body: serde_json::to_string(value).unwrap() })
+    }
+
+    fn ok() -> Result<Reply, MockError> {
+        Reply::json(&())
+    }
+
+    /// Replies to an endpoint, with the response type it declares.
+    fn to<E: Endpoint>(response: &E::Response) -> Result<Reply, MockError> {
+        Reply::json(response)
+    }
+}
+
+/// Seconds a client is told to wait after being banned by the firewall.
+const BAN_DURATION: u64 = 10;
+
+impl From<MockError> for Reply {
+    fn from(error: MockError) -> Self {
+        let headers = match error.status {
+            429 => vec![("Retry-After", BAN_DURATION.to_string())],
+            _ => Vec::new(),
+        };
+        Reply { status: error.status, content_type: JSON, headers, body: error.to_json() }
+    }
+}
+
+/// How long a session lasts after logging in.
+const SESSION_DURATION: i64 = 7 * 86400;
+
+/// The session of the user, renewed by `/login`.
+pub struct MockSession {
+    pub expires: i64,
+    /// Whether `/login?silent=true` renews the session, as if the auth origin still knew the user.
+    pub silent_login: bool,
+}
+
+pub struct MockServer {
+    pub state: MockState,
+    pub injection: ErrorInjection,
+    /// Last replay-protection counter accepted, see [COUNTER_HEADER].
+    pub last_counter: u64,
+    pub session: MockSession,
+    /// Events to send to the subscribers of [PUSH_PATH], see [MockServer::take_pushed].
+    pub pushed: Vec<PushEvent>,
+}
+
+impl MockServer {
+    pub fn new(now: i64) -> Self {
+        MockServer {
+            state: MockState::seeded(now),
+            injection: ErrorInjection::default(),
+            last_counter: 0,
+            session: MockSession { expires: now + SESSION_DURATION, silent_login: true },
+            pushed: Vec::new(),
+        }
+    }
+
+    /// Takes the events pushed since the last call.
+    pub fn take_pushed(&mut self) -> Vec<PushEvent> {
+        std::mem::take(&mut self.pushed)
+    }
+
+    pub fn handle(&mut self, request: &MockRequest, now: i64) -> Reply {
+        // Requests without a counter are accepted, so that the mock can be used with curl
+        if let Some(counter) = request.header(&COUNTER_HEADER.to_lowercase()).and_then(|c| c.parse::<u64>().ok()) {
+            if counter <= self.last_counter {
+                let mut reply = Reply::from(MockError::known("counter_too_low"));
+                reply.headers.push((COUNTER_HEADER, self.last_counter.to_string()));
+                return reply;
+            }
+            self.last_counter = counter;
+        }
+
+        // The calendar feed is authenticated by its token, not by the session
+        let is_api = request.path.starts_with("/api/") && request.path != "/api/ics";
+        if is_api && now >= self.session.expires {
+            return Reply::from(MockError::known("api_key_expired"));
+        }
+
+        let mut reply = self.route(request, now).unwrap_or_else(Reply::from);
+        if is_api {
+            reply.headers.push((SESSION_EXPIRES_HEADER, self.session.expires.to_string()));
+        }
+
+        // Conditional requests
+        if request.method == "GET" && reply.status == 200 && reply.content_type != EVENT_STREAM {
+            let mut hasher = DefaultHasher::new();
+            reply.body.hash(&mut hasher);
+            let etag = format!("\"{:x}\"", hasher.finish());
+            if request.header("if-none-match") == Some(etag.as_str()) {
+                reply.status = 304;
+                reply.body.clear();
+            }
+            reply.headers.push(("ETag", etag));
+        }
+
+        reply
+    }
+
+    fn route(&mut self, request: &MockRequest, now: i64) -> Result<Reply, MockError> {
+        if let Some(endpoint) = request.path.strip_prefix("/mock/") {
+            return self.control(request, endpoint, now);
+        }
+        if request.path == "/login" {
+            return Ok(self.login(request, now));
+        }
+        if request.path == SILENT_LOGIN_RETURN {
+            let page = include_str!("../../static/silent-login.html");
+            return Ok(Reply { status: 200, content_type: "text/html", headers: Vec::new(), body: page.to_string() });
+        }
+        let Some(endpoint) = request.path.strip_prefix("/api/") else {
+            return Err(MockError::not_found("Page"));
+        };
+
+        // Injected errors
+        if let Some(kind) = request.header("x-mock-error").or(request.query.get("mock_error").map(String::as_str)) {
+            return Err(MockError::known(kind));
+        }
+        if let Some(error) = self.injection.take() {
+            return Err(error);
+        }
+
+        let method: Method = request.method.parse().map_err(|_| MockError::new(405, "method_not_allowed", "Method not allowed", "Méthode non autorisée"))?;
+        let (state, pushed) = (&mut self.state, &mut self.pushed);
+        match (method, endpoint) {
+            (Method::Get, endpoint) if Some(endpoint) == PUSH_PATH.strip_prefix("/api/") => {
+                Ok(Reply { status: 200, content_type: EVENT_STREAM, headers: vec![("Cache-Control", String::from("no-cache"))], body: String::from(": connected\n\n") })
+            }
+            (GetSchedule::METHOD, GetSchedule::PATH) => match request.decode::<GetSchedule>()?.uid {
+                None => Reply::to::<GetSchedule>(&state.events),
+                Some(uid) => {
+                    if !state.friends.friends.iter().any(|(friend, _)| friend.uid == uid) {
+                        return Err(MockError::new(403, "not_friend", "This user is not your friend", "Cet utilisateur n'est pas votre ami"));
+                    }
+                    Reply::to::<GetSchedule>(&state.friend_events.get(&uid).cloned().unwrap_or_default())
+                }
+            },
+            (GetTeacherSchedule::METHOD, GetTeacherSchedule::PATH) => {
+                let GetTeacherSchedule { teacher } = request.decode()?;
+                Reply::to::<GetTeacherSchedule>(&state.events_across_schedules(|e| e.teachers.contains(&teacher)))
+            }
+            (GetRoomSchedule::METHOD, GetRoomSchedule::PATH) => {
+                let GetRoomSchedule { room } = request.decode()?;
+                Reply::to::<GetRoomSchedule>(&state.events_across_schedules(|e| e.location.as_ref().map(Location::room_id) == Some(room.clone())))
+            }
+            (GetScheduleChanges::METHOD, GetScheduleChanges::PATH) => {
+                let GetScheduleChanges { since } = request.decode()?;
+                if since < state.seeded_at {
+                    return Err(MockError::new(410, "changes_unavailable", "Changes are not known that far back", "Les modifications ne sont pas connues aussi loin"));
+                }
+                let mut changes = ScheduleChanges::default();
+                for (ts, eid) in &state.schedule_log {
+                    if *ts < since || changes.removed.contains(eid) || changes.updated.iter().any(|e| e.eid == *eid) {
+                        continue;
+                    }
+                    match state.events.iter().find(|e| e.eid == *eid) {
+                        Some(event) => changes.updated.push(event.clone()),
+                        None => changes.removed.push(eid.clone()),
+                    }
+                }
+                Reply::to::<GetScheduleChanges>(&changes)
+            }
+            (GetUserInfo::METHOD, GetUserInfo::PATH) => Reply::to::<GetUserInfo>(&state.user),
+            (GetFriends::METHOD, GetFriends::PATH) => Reply::to::<GetFriends>(&state.friends),
+            (RequestFriend::METHOD, RequestFriend::PATH) => {
+                let RequestFriend { email } = request.decode()?;
+                let Some(user) = state.users.iter().find(|(user, _)| user.email == email).cloned() else {
+                    return Err(MockError::new(404, "user_not_found", "No user with this email", "Aucun utilisateur avec cet email"));
+                };
+                let friends = &mut state.friends;
+                if friends.friends.iter().any(|(f, _)| f.uid == user.0.uid) || friends.outgoing.iter().any(|r| r.to.0.uid == user.0.uid) {
+                    return Err(MockError::bad_request("Already requested", "Déjà demandé"));
+                }
+                friends.outgoing.push(FriendRequestOutgoing { to: user, at_ts: now });
+                pushed.push(PushEvent::Friends);
+                Reply::ok()
+            }
+            (AcceptFriend::METHOD, AcceptFriend::PATH) => {
+                let AcceptFriend { uid } = request.decode()?;
+                let Some(i) = state.friends.incoming.iter().position(|r| r.from.0.uid == uid) else {
+                    return Err(MockError::not_found("Friend request"));
+                };
+                let (user, groups) = state.friends.incoming.remove(i).from;
+                state.ensure_friend_events(now, uid, &groups);
+                state.friends.friends.push((user, groups));
+                pushed.push(PushEvent::Friends);
+                Reply::ok()
+            }
+            (DeclineFriend::METHOD, DeclineFriend::PATH) => {
+                let DeclineFriend { uid } = request.decode()?;
+                let Some(i) = state.friends.incoming.iter().position(|r| r.from.0.uid == uid) else {
+                    return Err(MockError::not_found("Friend request"));
+                };
+                let declined = state.friends.incoming.remove(i);
+                state.friends.declined.push(declined.from);
+                pushed.push(PushEvent::Friends);
+                Reply::ok()
+            }
+            (RemoveFriend::METHOD, RemoveFriend::PATH) => {
+                let RemoveFriend { uid } = request.decode()?;
+                state.friends.friends.retain(|(friend, _)| friend.uid != uid);
+                state.friends.outgoing.retain(|r| r.to.0.uid != uid);
+                pushed.push(PushEvent::Friends);
+                Reply::ok()
+            }
+            (GetComments::METHOD, GetComments::PATH) => {
+                let GetComments { eid } = request.decode()?;
+                let me = state.me.uid;
+                match state.comments.get(&eid) {
+                    Some(comments) if !comments.is_empty() => Reply::to::<GetComments>(&comments.iter().filter(|c| !c.hidden || c.author.uid == me).cloned().collect()),
+                    _ => Err(MockError::known("textbook_not_found")),
+                }
+            }
+            (CommentRequest::METHOD, CommentRequest::PATH) => {
+                let CommentRequest { eid, cid, parent, content, mentions, idempotency_key } = request.decode()?;
+                // The client didn't get the answer to the first request and sent it again
+                if idempotency_key.as_ref().is_some_and(|key| state.comment_keys.contains_key(key)) {
+                    return Reply::ok();
+                }
+                let mentions = state.all_users().filter(|u| mentions.contains(&u.uid)).cloned().collect::<Vec<_>>();
+                let me = state.me.clone();
+                let comments = state.comments.entry(eid.clone()).or_default();
+                match cid {
+                    Some(cid) => {
+                        let comment = comments.iter_mut().find(|c| c.cid as i64 == cid).ok_or_else(|| MockError::not_found("Comment"))?;
+                        if comment.author.uid != me.uid {
+                            return Err(MockError::new(403, "forbidden", "You can only edit your own comments", "Vous ne pouvez modifier que vos commentaires"));
+                        }
+                        let revisions = state.revisions.entry(comment.cid).or_default();
+                        if revisions.is_empty() {
+                            revisions.push(CommentRevision { content: comment.content.clone(), ts: comment.creation_ts });
+                        }
+                        revisions.push(CommentRevision { content: content.clone(), ts: now });
+                        comment.content = content;
+                        comment.mentions = mentions;
+                        comment.last_edited_ts = now;
+                    }
+                    None => {
+                        if let Some(parent) = parent {
+                            if !comments.iter().any(|c| c.cid as i64 == parent) {
+                                return Err(MockError::not_found("Parent comment"));
+                            }
+                        }
+                        comments.push(Comment {
+                            cid: state.next_cid,
+                            parent: parent.map(|p| p as u64),
+                            author: me,
+                            content,
+                            creation_ts: now,
+                            last_edited_ts: now,
+                            upvotes: 0,
+                            downvotes: 0,
+                            vote: 0,
+                            hidden: false,
+                            mentions,
+                            homework: None,
+                        });
+                        if let Some(key) = idempotency_key {
+                            state.comment_keys.insert(key, state.next_cid);
+                        }
+                        state.next_cid += 1;
+                    }
+                }
+                pushed.push(PushEvent::Comments { eid });
+                Reply::ok()
+            }
+            (DeleteComment::METHOD, DeleteComment::PATH) => {
+                let DeleteComment { eid, cid } = request.decode()?;
+                let comments = state.comments.get_mut(&eid).ok_or_else(|| MockError::not_found("Comment"))?;
+                let i = comments.iter().position(|c| c.cid == cid).ok_or_else(|| MockError::not_found("Comment"))?;
+                if comments[i].author.uid != state.me.uid {
+                    return Err(MockError::new(403, "forbidden", "You can only delete your own comments", "Vous ne pouvez supprimer que vos commentaires"));
+                }
+                comments.remove(i);
+                pushed.push(PushEvent::Comments { eid });
+                Reply::ok()
+            }
+            (VoteRequest::METHOD, VoteRequest::PATH) => {
+                let VoteRequest { eid, vote, cid } = request.decode()?;
+                if !(-1..=1).contains(&vote) {
+                    return Err(MockError::bad_request("A vote must be -1, 0 or 1", "Un vote doit valoir -1, 0 ou 1"));
+                }
+                let comment = state.comments.get_mut(&eid).and_then(|c| c.iter_mut().find(|c| c.cid == cid)).ok_or_else(|| MockError::not_found("Comment"))?;
+                match comment.vote {
+                    1 => comment.upvotes = comment.upvotes.saturating_sub(1),
+                    -1 => comment.downvotes = comment.downvotes.saturating_sub(1),
+                    _ => (),
+                }
+                match vote {
+                    1 => comment.upvotes += 1,
+                    -1 => comment.downvotes += 1,
+                    _ => (),
+                }
+                comment.vote = vote;
+                pushed.push(PushEvent::Comments { eid });
+                Reply::ok()
+            }
+            (GetColors::METHOD, GetColors::PATH) => Reply::to::<GetColors>(&state.colors),
+            (SetColors::METHOD, SetColors::PATH) => {
+                let SetColors(colors) = request.decode()?;
+                state.colors.extend(colors);
+                state.user.last_colors_mod = now;
+                Reply::ok()
+            }
+            (GetCommentCounts::METHOD, GetCommentCounts::PATH) => {
+                let counts = state.comments.iter()
+                    .map(|(eid, comments)| (eid.clone(), comments.iter().filter(|c| !c.hidden).count()))
+                    .filter(|(_, count)| *count > 0)
+                    .collect();
+                Reply::to::<GetCommentCounts>(&counts)
+            }
+            (SetGroups::METHOD, SetGroups::PATH) => {
+                let SetGroups(groups) = request.decode()?;
+                state.user.groups = groups;
+                state.user.onboarded = true;
+                Reply::ok()
+            }
+            (RegenerateToken::METHOD, RegenerateToken::PATH) => {
+                state.user.token = format!("mock-ics-token-{now}");
+                Reply::ok()
+            }
+            // Not called by the app, the url is given to calendar applications
+            (Method::Get, "ics") => {
+                if request.param("token")? != state.user.token {
+                    return Err(MockError::known("invalid_api_key"));
+                }
+                Ok(Reply { status: 200, content_type: "text/calendar", headers: Vec::new(), body: ics(&state.events) })
+            }
+            (ReportRequest::METHOD, ReportRequest::PATH) => {
+                let ReportRequest { eid, cid, reason, note } = request.decode()?;
+                if !state.comments.get(&eid).map(|c| c.iter().any(|c| c.cid == cid)).unwrap_or(false) {
+                    return Err(MockError::not_found("Comment"));
+                }
+                state.reports.push((eid, cid, Report { reporter: state.me.clone(), reason, note, ts: now }));
+                Reply::ok()
+            }
+            (GetReports::METHOD, GetReports::PATH) => Reply::to::<GetReports>(&reported_comments(state)),
+            (GetModerationLog::METHOD, GetModerationLog::PATH) => Reply::to::<GetModerationLog>(&state.moderation_log),
+            (ModerationRequest::METHOD, ModerationRequest::PATH) => {
+                let ModerationRequest { eid, cid, action, note } = request.decode()?;
+                let comments = state.comments.get_mut(&eid).ok_or_else(|| MockError::not_found("Comment"))?;
+                let i = comments.iter().position(|c| c.cid == cid).ok_or_else(|| MockError::not_found("Comment"))?;
+                let content = comments[i].content.clone();
+                match action {
+                    ModerationAction::Dismiss => (),
+                    ModerationAction::Hide => comments[i].hidden = true,
+                    ModerationAction::Delete => {
+                        comments.remove(i);
+                    }
+                }
+                state.reports.retain(|(e, c, _)| *e != eid || *c != cid);
+                pushed.push(PushEvent::Comments { eid: eid.clone() });
+                state.moderation_log.push(ModerationLogEntry { moderator: state.me.clone(), eid, cid, action, note, content, ts: now });
+                Reply::ok()
+            }
+            (GetMentions::METHOD, GetMentions::PATH) => {
+                let me = state.me.uid;
+                let mentions = state.comments.iter()
+                    .flat_map(|(eid, comments)| comments.iter().map(move |c| (eid, c)))
+                    .filter(|(_, c)| c.author.uid != me && c.mentions.iter().any(|m| m.uid == me))
+                    .map(|(eid, c)| Mention { eid: eid.clone(), cid: c.cid, author: c.author.clone(), ts: c.creation_ts })
+                    .collect();
+                Reply::to::<GetMentions>(&mentions)
+            }
+            (GetHomeworks::METHOD, GetHomeworks::PATH) => {
+                let homeworks = state.comments.iter()
+                    .flat_map(|(eid, comments)| comments.iter().map(move |c| (eid, c)))
+                    .filter_map(|(eid, c)| Some(HomeworkItem {
+                        eid: eid.clone(),
+                        cid: c.cid,
+                        summary: state.event(eid).map(|e| e.summary.clone()).unwrap_or_default(),
+                        content: c.content.clone(),
+                        author: c.author.clone(),
+                        homework: c.homework.clone()?,
+                    }))
+                    .collect();
+                Reply::to::<GetHomeworks>(&homeworks)
+            }
+            (HomeworkRequest::METHOD, HomeworkRequest::PATH) => {
+                let HomeworkRequest { eid, cid, homework } = request.decode()?;
+                let comment = state.comments.get_mut(&eid).and_then(|c| c.iter_mut().find(|c| c.cid == cid)).ok_or_else(|| MockError::not_found("Comment"))?;
+                comment.homework = homework;
+                pushed.push(PushEvent::Comments { eid });
+                Reply::ok()
+            }
+            (GetCommentRevisions::METHOD, GetCommentRevisions::PATH) => {
+                let GetCommentRevisions { eid, cid } = request.decode()?;
+                let comment = state.comments.get(&eid).and_then(|c| c.iter().find(|c| c.cid == cid)).ok_or_else(|| MockError::not_found("Comment"))?;
+                match state.revisions.get(&cid) {
+                    Some(revisions) => Reply::to::<GetCommentRevisions>(revisions),
+                    None => Reply::to::<GetCommentRevisions>(&vec![CommentRevision { content: comment.content.clone(), ts: comment.creation_ts }]),
+                }
+            }
+            (method, endpoint) => Err(MockError::not_found(&format!("Endpoint {} /api/{endpoint}", method.as_str()))),
+        }
+    }
+
+    /// Stands for the whole login flow, which goes through the auth origin and comes back to `return`.
+    /// Silent logins fail when [MockSession::silent_login] is unset, as when the user has to type their password.
+    fn login(&mut self, request: &MockRequest, now: i64) -> Reply {
+        let silent = request.query.get("silent").map(|s| s == "true").unwrap_or(false);
+        let mut location = request.query.get("return").cloned().unwrap_or_else(|| String::from("/agenda"));
+        if silent && !self.session.silent_login {
+            location.push_str(if location.contains('?') { "&error=login_required" } else { "?error=login_required" });
+        } else {
+            self.session = MockSession { expires: now + SESSION_DURATION, silent_login: true };
+        }
+        Reply { status: 302, content_type: "text/plain", headers: vec![("Location", location)], body: String::new() }
+    }
+
+    /// Endpoints controlling the mock itself.
+    fn control(&mut self, request: &MockRequest, endpoint: &str, now: i64) -> Result<Reply, MockError> {
+        match (request.method.as_str(), endpoint) {
+            ("POST", "fail") => {
+                let kind = request.param("kind")?.to_string();
+                let count = request.query.get("count").and_then(|c| c.parse().ok()).unwrap_or(1);
+                self.injection.fail_next(kind, count);
+                Reply::ok()
+            }
+            ("POST", "move-event") => {
+                let eid = request.param("eid")?.to_string();
+                let minutes: i64 = request.param("minutes")?.parse().map_err(|_| MockError::bad_request("Invalid minutes", "Minutes invalides"))?;
+                let event = self.state.events.iter_mut().find(|e| e.eid == eid).ok_or_else(|| MockError::not_found("Event"))?;
+                event.start_unixtime = (event.start_unixtime as i64 + minutes * 60) as u64;
+                event.end_unixtime = (event.end_unixtime as i64 + minutes * 60) as u64;
+                self.state.schedule_log.push((now, eid));
+                self.pushed.push(PushEvent::Schedule);
+                Reply::ok()
+            }
+            ("POST", "cancel-event") => {
+                let eid = request.param("eid")?.to_string();
+                self.state.events.retain(|e| e.eid != eid);
+                self.state.schedule_log.push((now, eid));
+                self.pushed.push(PushEvent::Schedule);
+                Reply::ok()
+            }
+            ("POST", "friend-request") => {
+                let uid: i64 = request.param("uid")?.parse().map_err(|_| MockError::bad_request("Invalid uid", "Uid invalide"))?;
+                let user = self.state.users.iter().find(|(user, _)| user.uid == uid).cloned().ok_or_else(|| MockError::not_found("User"))?;
+                let friends = &mut self.state.friends;
+                friends.friends.retain(|(friend, _)| friend.uid != uid);
+                friends.incoming.retain(|r| r.from.0.uid != uid);
+                friends.declined.retain(|(declined, _)| declined.uid != uid);
+                friends.incoming.push(FriendRequestIncoming { from: user, at_ts: now });
+                self.pushed.push(PushEvent::Friends);
+                Reply::ok()
+            }
+            ("POST", "comment") => {
+                let eid = request.param("eid")?.to_string();
+                let content = request.query.get("content").cloned().unwrap_or_else(|| String::from("Quelqu'un a une idée pour l'exercice 2 ?"));
+                let (author, _) = self.state.users.first().cloned().ok_or_else(|| MockError::not_found("User"))?;
+                let state = &mut self.state;
+                let usernames = find_mentions(&content).into_iter().map(|(_, username)| username.to_string()).collect::<Vec<_>>();
+                let mentions = state.all_users().filter(|u| usernames.iter().any(|n| n == u.as_username())).cloned().collect();
+                let mut new_comment = comment(state.next_cid, None, &author, &content, now);
+                new_comment.mentions = mentions;
+                state.comments.entry(eid.clone()).or_default().push(new_comment);
+                state.next_cid += 1;
+                self.pushed.push(PushEvent::Comments { eid });
+                Reply::ok()
+            }
+            ("POST", "expire-session") => {
+                self.session.expires = now + request.query.get("in").and_then(|s| s.parse().ok()).unwrap_or(0);
+                self.session.silent_login = request.query.get("silent").map(|s| s != "false").unwrap_or(true);
+                Reply::ok()
+            }
+            ("POST", "reset") => {
+                *self = MockServer::new(now);
+                Reply::ok()
+            }
+            (method, endpoint) => Err(MockError::not_found(&format!("Endpoint {method} /mock/{endpoint}"))),
+        }
+    }
+}
+
+fn reported_comments(state: &MockState) -> Vec<ReportedComment> {
+    let mut reported: Vec<ReportedComment> = Vec::new();
+    for (eid, cid, report) in &state.reports {
+        if let Some(existing) = reported.iter_mut().find(|r| r.eid == *eid && r.comment.cid == *cid) {
+            existing.reports.push(report.clone());
+            continue;
+        }
+        let Some(comments) = state.comments.get(eid) else { continue };
+        let Some(comment) = comments.iter().find(|c| c.cid == *cid) else { continue };
+        let mut context = Vec::new();
+        let mut parent = comment.parent;
+        while let Some(parent_comment) = parent.and_then(|p| comments.iter().find(|c| c.cid == p)) {
+            context.insert(0, parent_comment.clone());
+            parent = parent_comment.parent;
+        }
+        reported.push(ReportedComment {
+            eid: eid.clone(),
+            event_summary: state.event(eid).map(|e| e.summary.clone()).unwrap_or_default(),
+            comment: comment.clone(),
+            context,
+            reports: vec![report.clone()],
+        });
+    }
+    reported
+}
+
+/// A minimal iCalendar export of the schedule.
+fn ics(events: &[Event]) -> String {
+    let mut ics = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//insagenda//mock-server//FR\r\n");
+    for event in events {
+        ics.push_str("BEGIN:VEVENT\r\n");
+        ics.push_str(&format!("UID:{}\r\n", event.eid));
+        ics.push_str(&format!("DTSTART:{}\r\n", ics_time(event.start_unixtime)));
+        ics.push_str(&format!("DTEND:{}\r\n", ics_time(event.end_unixtime)));
+        ics.push_str(&format!("SUMMARY:{}\r\n", event.summary));
+        if let Some(location) = &event.location {
+            ics.push_str(&format!("LOCATION:{location}\r\n"));
+        }
+        ics.push_str("END:VEVENT\r\n");
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+/// Formats a timestamp as `YYYYMMDDTHHMMSSZ`.
+fn ics_time(ts: u64) -> String {
+    let (days, secs) = ((ts / 86400) as i64, ts % 86400);
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1710331200;
+
+    fn request(method: &str, url: &str, body: &str) -> MockRequest {
+        let (path, query) = match url.split_once('?') {
+            Some((path, query)) => (path.to_string(), decode_params(query)),
+            None => (url.to_string(), HashMap::new()),
+        };
+        MockRequest { method: method.to_string(), path, query, headers: Vec::new(), body: body.to_string() }
+    }
+
+    #[test]
+    fn test_comment_flow() {
+        let mut mock = MockServer::new(NOW);
+        let eid = mock.state.events[0].eid.clone();
+        let body = serde_json::to_string(&CommentRequest { eid: eid.clone(), cid: None, parent: None, content: String::from("Salut @simon.girard"), mentions: vec![2], idempotency_key: Some(String::from("k1")) }).unwrap();
+        assert_eq!(mock.handle(&request("POST", "/api/comment", &body), NOW).status, 200);
+        // Sent again after a timeout
+        assert_eq!(mock.handle(&request("POST", "/api/comment", &body), NOW).status, 200);
+
+        let reply = mock.handle(&request("GET", &format!("/api/comments?eid={eid}"), ""), NOW);
+        let comments: Vec<Comment> = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].mentions[0].uid, 2);
+
+        let vote = format!(r#"{{"eid":"{eid}","vote":1,"cid":{}}}"#, comments[0].cid);
+        mock.handle(&request("POST", "/api/vote", &vote), NOW);
+        mock.handle(&request("POST", "/api/vote", &vote), NOW);
+        let comment = &mock.state.comments[&eid][0];
+        assert_eq!((comment.upvotes, comment.vote), (1, 1));
+
+        let invalid_vote = format!(r#"{{"eid":"{eid}","vote":2,"cid":{}}}"#, comments[0].cid);
+        assert_eq!(mock.handle(&request("POST", "/api/vote", &invalid_vote), NOW).status, 400);
+        let comment = &mock.state.comments[&eid][0];
+        assert_eq!((comment.upvotes, comment.vote), (1, 1));
+    }
+
+    #[test]
+    fn test_error_injection() {
+        let mut mock = MockServer::new(NOW);
+        let mut failing = request("GET", "/api/user-info", "");
+        failing.headers.push((String::from("x-mock-error"), String::from("invalid_api_key")));
+        let reply = mock.handle(&failing, NOW);
+        assert_eq!(reply.status, 401);
+        assert!(reply.body.contains(r#""kind":"invalid_api_key""#));
+
+        mock.handle(&request("POST", "/mock/fail?kind=counter_too_low&count=2", ""), NOW);
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 400);
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 400);
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 200);
+
+        mock.handle(&request("POST", "/mock/fail?kind=firewall_ban&count=1", ""), NOW);
+        let reply = mock.handle(&request("GET", "/api/colors", ""), NOW);
+        assert_eq!(reply.status, 429);
+        assert!(reply.headers.contains(&("Retry-After", BAN_DURATION.to_string())));
+    }
+
+    #[test]
+    fn test_conditional_requests_and_changes() {
+        let mut mock = MockServer::new(NOW);
+        let reply = mock.handle(&request("GET", "/api/schedule", ""), NOW);
+        let etag = reply.headers.iter().find(|(name, _)| *name == "ETag").unwrap().1.clone();
+        let mut conditional = request("GET", "/api/schedule", "");
+        conditional.headers.push((String::from("if-none-match"), etag.clone()));
+        assert_eq!(mock.handle(&conditional, NOW).status, 304);
+
+        let eid = mock.state.events[0].eid.clone();
+        mock.handle(&request("POST", &format!("/mock/move-event?eid={eid}&minutes=30"), ""), NOW + 10);
+        assert_eq!(mock.handle(&conditional, NOW + 10).status, 200);
+
+        let reply = mock.handle(&request("GET", &format!("/api/schedule/changes?since={}", NOW + 5), ""), NOW + 20);
+        let changes: ScheduleChanges = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(changes.updated.iter().map(|e| e.eid.as_str()).collect::<Vec<_>>(), vec![eid.as_str()]);
+        assert_eq!(mock.handle(&request("GET", "/api/schedule/changes?since=0", ""), NOW).status, 410);
+    }
+
+    #[test]
+    fn test_counter() {
+        let mut mock = MockServer::new(NOW);
+        let with_counter = |counter: u64| {
+            let mut request = request("GET", "/api/colors", "");
+            request.headers.push((COUNTER_HEADER.to_lowercase(), counter.to_string()));
+            request
+        };
+        assert_eq!(mock.handle(&with_counter(5), NOW).status, 200);
+        let reply = mock.handle(&with_counter(5), NOW);
+        assert!(reply.body.contains(r#""kind":"counter_too_low""#));
+        assert_eq!(reply.headers, vec![(COUNTER_HEADER, String::from("5"))]);
+        assert_eq!(mock.handle(&with_counter(6), NOW).status, 200);
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 200);
+    }
+
+    #[test]
+    fn test_session() {
+        let mut mock = MockServer::new(NOW);
+        let reply = mock.handle(&request("GET", "/api/colors", ""), NOW);
+        assert!(reply.headers.contains(&(SESSION_EXPIRES_HEADER, (NOW + SESSION_DURATION).to_string())));
+
+        mock.handle(&request("POST", "/mock/expire-session?silent=false", ""), NOW);
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 401);
+        let reply = mock.handle(&request("GET", &format!("/login?silent=true&return={SILENT_LOGIN_RETURN}"), ""), NOW);
+        assert_eq!(reply.headers, vec![("Location", format!("{SILENT_LOGIN_RETURN}?error=login_required"))]);
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 401);
+
+        let reply = mock.handle(&request("GET", "/login?return=/event/abc", ""), NOW);
+        assert_eq!((reply.status, reply.headers), (302, vec![("Location", String::from("/event/abc"))]));
+        assert_eq!(mock.handle(&request("GET", "/api/colors", ""), NOW).status, 200);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut mock = MockServer::new(NOW);
+        let reply = mock.handle(&request("GET", PUSH_PATH, ""), NOW);
+        assert_eq!((reply.status, reply.content_type), (200, EVENT_STREAM));
+        assert!(!reply.headers.iter().any(|(name, _)| *name == "ETag"));
+
+        let eid = mock.state.events[0].eid.clone();
+        mock.handle(&request("POST", &format!("/mock/comment?eid={eid}&content=Salut%20@edouard.foobar"), ""), NOW);
+        mock.handle(&request("POST", "/mock/friend-request?uid=6", ""), NOW);
+        mock.handle(&request("POST", "/api/friends/accept", "uid=6"), NOW);
+        mock.handle(&request("POST", &format!("/mock/cancel-event?eid={eid}"), ""), NOW);
+        assert_eq!(mock.take_pushed(), vec![PushEvent::Comments { eid: eid.clone() }, PushEvent::Friends, PushEvent::Friends, PushEvent::Schedule]);
+        assert!(mock.take_pushed().is_empty());
+
+        let comment = mock.state.comments[&eid].last().unwrap();
+        assert_eq!(comment.mentions, vec![mock.state.me.clone()]);
+        assert_eq!(sse(&PushEvent::Comments { eid: String::from("abc") }), "data: {\"type\":\"comments\",\"eid\":\"abc\"}\n\n");
+
+        // Failed requests push nothing
+        mock.handle(&request("POST", "/api/vote", r#"{"eid":"nope","vote":1,"cid":1}"#), NOW);
+        assert!(mock.take_pushed().is_empty());
+    }
+
+    #[test]
+    fn test_teacher_and_room_schedules() {
+        let mut mock = MockServer::new(NOW);
+        let reply = mock.handle(&request("GET", "/api/schedule/teacher?teacher=Marie%20Curie", ""), NOW);
+        let events: Vec<Event> = serde_json::from_str(&reply.body).unwrap();
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|e| e.teachers == ["Marie Curie"]));
+        // Courses shared with friends are listed once
+        assert_eq!(events.len(), mock.state.events.iter().filter(|e| e.teachers == ["Marie Curie"]).count());
+
+        let reply = mock.handle(&request("GET", "/api/schedule/room?room=Ma-A-RC-1", ""), NOW);
+        let events: Vec<Event> = serde_json::from_str(&reply.body).unwrap();
+        assert!(events.iter().any(|e| e.summary == "Chimie") && events.iter().any(|e| e.summary == "Analyse"));
+        assert!(events.windows(2).all(|w| w[0].start_unixtime <= w[1].start_unixtime && w[0].eid != w[1].eid));
+    }
+
+    #[test]
+    fn test_ics_time() {
+        assert_eq!(ics_time(1710331200), "20240313T120000Z");
+    }
+}